@@ -1,60 +1,130 @@
-use once_cell::sync::Lazy;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_file::{self, ConfigFileError};
+use crate::metrics::Metrics;
 
 // rng
 pub const SEED: u64 = 114211;
 
-// weights
-pub const DISTANCE_EFFORT: u32 = 1; // at 2 distance penalty is squared
-pub const DOUBLE_FINGER_EFFORT: i32 = 1;
-pub const DOUBLE_HAND_EFFORT: i32 = 1;
-
-// typing speed
-const FINGER_CPM: [i32; 8] = [223, 169, 225, 273, 343, 313, 259, 241];
-static MEAN_CPM: Lazy<f64> =
-    Lazy::new(|| FINGER_CPM.iter().sum::<i32>() as f64 / FINGER_CPM.len() as f64);
-static STD_CPM: Lazy<f64> = Lazy::new(|| {
-    (FINGER_CPM
-        .iter()
-        .map(|&x| (x as f64 - *MEAN_CPM).powi(2))
-        .sum::<f64>()
-        / FINGER_CPM.len() as f64)
-        .sqrt()
-});
-static Z_SCORE_CPM: Lazy<Vec<f64>> = Lazy::new(|| {
-    FINGER_CPM
-        .iter()
-        .map(|&x| -(x as f64 - *MEAN_CPM) / *STD_CPM)
-        .collect()
-});
-pub(crate) static FINGER_EFFORT: Lazy<Vec<f64>> = Lazy::new(|| {
-    Z_SCORE_CPM
-        .iter()
-        .map(|&x| x - Z_SCORE_CPM.iter().cloned().fold(f64::NAN, f64::min))
-        .collect()
-});
-
-const ROW_CPM: [i32; 4] = [131, 166, 276, 192];
-static MEAN_CPM2: Lazy<f64> =
-    Lazy::new(|| ROW_CPM.iter().sum::<i32>() as f64 / ROW_CPM.len() as f64);
-static STD_CPM2: Lazy<f64> = Lazy::new(|| {
-    (ROW_CPM
-        .iter()
-        .map(|&x| (x as f64 - *MEAN_CPM2).powi(2))
-        .sum::<f64>()
-        / ROW_CPM.len() as f64)
-        .sqrt()
-});
-static Z_SCORE_CPM2: Lazy<Vec<f64>> = Lazy::new(|| {
-    ROW_CPM
-        .iter()
-        .map(|&x| -(x as f64 - *MEAN_CPM2) / *STD_CPM2)
-        .collect()
-});
-pub(crate) static ROW_EFFORT: Lazy<Vec<f64>> = Lazy::new(|| {
-    Z_SCORE_CPM2
-        .iter()
-        .map(|&x| x - Z_SCORE_CPM2.iter().cloned().fold(f64::NAN, f64::max))
-        .collect()
-});
-
-pub(crate) const EFFORT_WEIGHTING: [f64; 5] = [0.7917, 1.0, 0.0, 0.4773, 0.0]; // dist, finger, row. Also had room for other weightings but removed for simplicity
+/// Weights of the objective function, can be loaded from a TOML or JSON file.
+/// Missing fields use the default values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EffortModel {
+    /// at 2 distance penalty is squared
+    pub distance_effort: u32,
+    pub double_finger_effort: i32,
+    pub double_hand_effort: i32,
+    /// typing speed per finger, left pinky to index then right pinky to index
+    pub finger_cpm: [i32; 8],
+    /// typing speed per row, number row to bottom row
+    pub row_cpm: [i32; 4],
+    /// dist, double finger, double hand, finger, row
+    pub effort_weighting: [f64; 5],
+    /// press shift with the outer finger of the other hand for shifted characters
+    pub shift: bool,
+    /// added to the penalties of every shift press
+    pub shift_effort: f64,
+    /// press space, enter, tab and backspace on their fixed keys
+    pub whitespace: bool,
+    /// typing speed of the left and right thumb, normalized like `finger_cpm`
+    pub thumb_cpm: [i32; 2],
+    /// typing speed of the thumb row, normalized like `row_cpm`
+    pub thumb_row_cpm: i32,
+    /// added to the objective for every occurrence of a metric, negative weights reward it
+    pub metric_weights: Metrics,
+}
+
+impl Default for EffortModel {
+    fn default() -> Self {
+        Self {
+            distance_effort: 1,
+            double_finger_effort: 1,
+            double_hand_effort: 1,
+            finger_cpm: [223, 169, 225, 273, 343, 313, 259, 241],
+            row_cpm: [131, 166, 276, 192],
+            // Also had room for other weightings but removed for simplicity
+            effort_weighting: [0.7917, 1.0, 0.0, 0.4773, 0.0],
+            shift: true,
+            shift_effort: 1.0,
+            whitespace: true,
+            thumb_cpm: [300, 300],
+            thumb_row_cpm: 276,
+            metric_weights: Metrics::default(),
+        }
+    }
+}
+
+impl EffortModel {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigFileError> {
+        config_file::read_config_file(path.as_ref())
+    }
+
+    /// indexed by finger id, the thumbs are 8 and 9
+    pub(crate) fn finger_effort(&self) -> [f64; 10] {
+        let z_score = z_score(&self.finger_cpm);
+        let min = self
+            .finger_cpm
+            .map(&z_score)
+            .into_iter()
+            .fold(f64::NAN, f64::min);
+
+        let mut effort = [0.; 10];
+        for (effort, cpm) in effort
+            .iter_mut()
+            .zip(self.finger_cpm.iter().chain(self.thumb_cpm.iter()))
+        {
+            *effort = z_score(*cpm) - min;
+        }
+        effort
+    }
+
+    /// indexed by row, the thumb row is 4
+    pub(crate) fn row_effort(&self) -> [f64; 5] {
+        let z_score = z_score(&self.row_cpm);
+        let max = self
+            .row_cpm
+            .map(&z_score)
+            .into_iter()
+            .fold(f64::NAN, f64::max);
+
+        let [number, top, middle, bottom] = self.row_cpm;
+        [number, top, middle, bottom, self.thumb_row_cpm].map(|cpm| z_score(cpm) - max)
+    }
+}
+
+/// z-score relative to the typing speeds of `cpm`
+fn z_score(cpm: &[i32]) -> impl Fn(i32) -> f64 {
+    let n = cpm.len() as f64;
+    let mean = cpm.iter().sum::<i32>() as f64 / n;
+    let std = (cpm.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / n).sqrt();
+
+    move |x| -(x as f64 - mean) / std
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_file_matches_default() {
+        let effort_model = EffortModel::from_file("resources/effort.toml").expect("should load");
+        assert_eq!(effort_model, EffortModel::default());
+    }
+
+    #[test]
+    fn test_missing_fields_use_default() {
+        let effort_model: EffortModel =
+            config_file::from_json_str(r#"{ "finger_cpm": [1, 2, 3, 4, 5, 6, 7, 8] }"#)
+                .expect("should parse");
+
+        assert_eq!(effort_model.finger_cpm, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(effort_model.row_cpm, EffortModel::default().row_cpm);
+
+        let finger_effort = effort_model.finger_effort();
+        assert_eq!(finger_effort[7], 0.);
+        assert!(finger_effort[0] > finger_effort[1]);
+    }
+}
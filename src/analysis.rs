@@ -0,0 +1,348 @@
+use std::fmt;
+
+use crate::advanced_setup::EffortModel;
+use crate::metrics::{count_metrics, rate_metrics, Metrics};
+use crate::models::geometry::GeometryError;
+use crate::models::layout::ILayout;
+use crate::models::layout_map::{KeyboardKey, KeyboardRow, KEY_PITCH_MM};
+use crate::models::Hand;
+use crate::ngrams::NgramStats;
+use crate::objective::{
+    do_keypress, get_home_finger_list, get_key_positions, get_key_presses, Efforts, KeyPress,
+    Penalties,
+};
+use crate::{score_genome, Score};
+
+const FINGER_NAMES: [&str; 10] = [
+    "left pinky",
+    "left ring",
+    "left middle",
+    "left index",
+    "right pinky",
+    "right ring",
+    "right middle",
+    "right index",
+    "left thumb",
+    "right thumb",
+];
+
+const ROW_NAMES: [&str; 5] = ["number", "top", "home", "bottom", "thumb"];
+
+/// Key presses, travel distance and objective of one finger
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FingerStats {
+    pub presses: usize,
+    /// % of all key presses
+    pub load: f64,
+    /// millimetres travelled away from and between keys
+    pub distance: f64,
+    /// part of the objective caused by the key presses of this finger
+    pub objective: f64,
+}
+
+/// Part of the objective caused by every penalty term, weights already applied
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PenaltyTerms {
+    pub distance: f64,
+    pub double_finger: f64,
+    pub double_hand: f64,
+    pub finger: f64,
+    pub row: f64,
+    pub shift: f64,
+    /// weighted metrics of the effort model
+    pub metrics: f64,
+}
+
+impl PenaltyTerms {
+    fn add(&mut self, penalties: &Penalties) {
+        let [distance, double_finger, double_hand, finger, row, shift] = penalties;
+        self.distance += distance;
+        self.double_finger += double_finger;
+        self.double_hand += double_hand;
+        self.finger += finger;
+        self.row += row;
+        self.shift += shift;
+    }
+
+    pub fn total(&self) -> f64 {
+        self.distance
+            + self.double_finger
+            + self.double_hand
+            + self.finger
+            + self.row
+            + self.shift
+            + self.metrics
+    }
+
+    fn named(&self) -> [(&'static str, f64); 7] {
+        [
+            ("distance", self.distance),
+            ("double finger", self.double_finger),
+            ("double hand", self.double_hand),
+            ("finger", self.finger),
+            ("row", self.row),
+            ("shift", self.shift),
+            ("metrics", self.metrics),
+        ]
+    }
+}
+
+/// Why a genome scores the way it does.
+///
+/// Replays the text like the objective function and keeps track of every key press,
+/// including shift and the whitespace keys when the effort model presses them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    pub score: Score,
+    pub key_presses: usize,
+    /// indexed by finger id: left pinky to index, right pinky to index, left and right thumb
+    pub fingers: [FingerStats; 10],
+    /// % of key presses of the left and right hand
+    pub hands: [f64; 2],
+    /// % of key presses per row, number row to thumb row
+    pub rows: [f64; 5],
+    /// % of the letter key presses on the home row
+    pub home_row: f64,
+    pub metrics: Metrics,
+    pub penalties: PenaltyTerms,
+}
+
+/// Analyze a genome on a text
+pub fn analyze_genome<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    text: &str,
+    genome: &[char; N],
+    effort_model: &EffortModel,
+) -> Result<Analysis, GeometryError> {
+    let layout_map = layout.get_layout_map();
+    let fixed_keys = layout.get_fixed_keys()?;
+    let key_positions = get_key_positions(genome, layout.get_letter_list());
+    let efforts = Efforts::from(effort_model);
+
+    let mut my_finger_list = get_home_finger_list(&layout_map, &fixed_keys);
+    let mut old_finger: usize = 0;
+    let mut old_hand: Option<Hand> = None;
+
+    let mut finger_presses = [0; 10];
+    let mut hand_presses = [0; 2];
+    let mut row_presses = [0; 5];
+    let mut penalties = PenaltyTerms::default();
+
+    let mut on_press = |key: &KeyboardKey, key_penalties: &Penalties| {
+        finger_presses[key.get_finger_id()] += 1;
+        hand_presses[key.hand as usize] += 1;
+        row_presses[key.row as usize] += 1;
+        penalties.add(key_penalties);
+    };
+
+    let key_presses = get_key_presses(layout, text);
+    for key_press in &key_presses {
+        do_keypress(
+            &mut my_finger_list,
+            &key_positions,
+            *key_press,
+            &mut old_finger,
+            &mut old_hand,
+            &layout_map,
+            &fixed_keys,
+            &efforts,
+            &mut on_press,
+        );
+    }
+
+    let presses: usize = finger_presses.iter().sum();
+    let percent = |count: usize, total: usize| match total {
+        0 => 0.,
+        total => count as f64 / total as f64 * 100.,
+    };
+
+    let mut fingers = [FingerStats::default(); 10];
+    for (finger_id, stats) in fingers.iter_mut().enumerate() {
+        *stats = FingerStats {
+            presses: finger_presses[finger_id],
+            load: percent(finger_presses[finger_id], presses),
+            distance: my_finger_list[finger_id].distance_counter * KEY_PITCH_MM,
+            objective: my_finger_list[finger_id].objective_counter,
+        };
+    }
+
+    // enter and the other fixed keys can be on the home row too, only letters count
+    let mut letter_rows = [0; 5];
+    for key_press in &key_presses {
+        if let KeyPress::Letter(key, _) = key_press {
+            letter_rows[layout_map[key_positions[*key]].row as usize] += 1;
+        }
+    }
+
+    let ngram_stats = NgramStats::new(&key_presses, effort_model.whitespace);
+    penalties.metrics = count_metrics(&ngram_stats, &key_positions, &layout_map)
+        .weighted(&effort_model.metric_weights);
+
+    Ok(Analysis {
+        score: score_genome(layout, text, genome, effort_model)?,
+        key_presses: presses,
+        fingers,
+        hands: hand_presses.map(|count| percent(count, presses)),
+        rows: row_presses.map(|count| percent(count, presses)),
+        home_row: percent(
+            letter_rows[KeyboardRow::MiddleLetter as usize],
+            letter_rows.iter().sum(),
+        ),
+        metrics: rate_metrics(&ngram_stats, &key_positions, &layout_map),
+        penalties,
+    })
+}
+
+/// Analyze every reference genome of a layout, relative to the baseline genome
+pub fn analyze_reference_genomes<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    text: &str,
+    effort_model: &EffortModel,
+) -> Result<Vec<(String, Analysis)>, GeometryError> {
+    layout
+        .get_reference_genomes()
+        .into_iter()
+        .map(|(name, genome)| {
+            let analysis = analyze_genome(layout, text, &genome, effort_model)?;
+            Ok((name.to_string(), analysis))
+        })
+        .collect()
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Score: {:.2}", self.score.absolute)?;
+        writeln!(f, "Relative to baseline: {:+.2} %", self.score.relative)?;
+        writeln!(f, "Key presses: {}", self.key_presses)?;
+        writeln!(f)?;
+
+        writeln!(
+            f,
+            "{:12}\t{:>9}\t{:>7}\t{:>11}\t{:>12}",
+            "Finger", "Presses", "Load", "Distance mm", "Objective"
+        )?;
+        for (name, finger) in FINGER_NAMES.iter().zip(self.fingers.iter()) {
+            writeln!(
+                f,
+                "{name:12}\t{:>9}\t{:>6.2}%\t{:>11.0}\t{:>12.2}",
+                finger.presses, finger.load, finger.distance, finger.objective
+            )?;
+        }
+        writeln!(f)?;
+
+        writeln!(
+            f,
+            "Hands: {:.2}% left, {:.2}% right",
+            self.hands[0], self.hands[1]
+        )?;
+        let rows = ROW_NAMES
+            .iter()
+            .zip(self.rows.iter())
+            .map(|(name, share)| format!("{share:.2}% {name}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(f, "Rows: {rows}")?;
+        writeln!(f, "Home row: {:.2}% of the letters", self.home_row)?;
+        writeln!(f)?;
+
+        for (name, rate) in self.metrics.named() {
+            writeln!(f, "{name:19}\t{rate:>6.2}%")?;
+        }
+        writeln!(f)?;
+
+        let total = self.penalties.total();
+        writeln!(f, "{:13}\t{:>12}\t{:>7}", "Penalty", "Objective", "Share")?;
+        for (name, value) in self.penalties.named() {
+            let share = match total {
+                0. => 0.,
+                total => value / total * 100.,
+            };
+            writeln!(f, "{name:13}\t{value:>12.2}\t{share:>6.2}%")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_analysis_adds_up_to_score() {
+        let text = std::fs::read_to_string("resources/meinBuch.txt").expect("should read file");
+        let layout = QwertzDeDe();
+        let effort_model = EffortModel::default();
+
+        let analysis = analyze_genome(&layout, &text, layout.get_genome(), &effort_model)
+            .expect("should analyze");
+        let absolute = analysis.score.absolute;
+
+        let fingers: f64 = analysis.fingers.iter().map(|finger| finger.objective).sum();
+        assert!((fingers - absolute).abs() < 1e-9 * absolute);
+        assert!((analysis.penalties.total() - absolute).abs() < 1e-9 * absolute);
+
+        let presses: usize = analysis.fingers.iter().map(|finger| finger.presses).sum();
+        assert_eq!(presses, analysis.key_presses);
+        assert!((analysis.hands.iter().sum::<f64>() - 100.).abs() < 1e-9);
+        assert!((analysis.rows.iter().sum::<f64>() - 100.).abs() < 1e-9);
+        assert!(analysis.home_row > 0. && analysis.home_row < 100.);
+        assert!(analysis.fingers.iter().all(|finger| finger.distance >= 0.));
+        assert!(analysis.penalties.shift > 0.);
+        assert_eq!(analysis.penalties.metrics, 0.);
+
+        let effort_model = EffortModel {
+            metric_weights: Metrics {
+                same_finger: 10.,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let analysis = analyze_genome(&layout, &text, layout.get_genome(), &effort_model)
+            .expect("should analyze");
+        let absolute = analysis.score.absolute;
+
+        assert!(analysis.penalties.metrics > 0.);
+        assert!((analysis.penalties.total() - absolute).abs() < 1e-9 * absolute);
+
+        // enter is on the home row but is not a letter
+        let layout = QwertyEnUs();
+        let analysis = analyze_genome(&layout, "q\n\n\n", layout.get_genome(), &effort_model)
+            .expect("should analyze");
+        assert_eq!(analysis.home_row, 0.);
+        assert!(analysis.rows[KeyboardRow::MiddleLetter as usize] > 0.);
+    }
+
+    #[test]
+    fn test_reference_genomes_are_compared_to_baseline() {
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let layout = QwertyEnUs();
+
+        let analyses = analyze_reference_genomes(&layout, &text, &EffortModel::default())
+            .expect("should analyze");
+        let names: Vec<&str> = analyses.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "QWERTY",
+                "Dvorak",
+                "Colemak",
+                "Colemak-DH",
+                "Workman",
+                "ABC"
+            ]
+        );
+
+        let analyses = analyze_reference_genomes(&KoyDeDe(), &text, &EffortModel::default())
+            .expect("should analyze");
+        let names: Vec<&str> = analyses.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["KOY", "Neo2", "Bone"]);
+
+        assert_eq!(analyses[0].1.score.relative, 0.);
+        for (name, analysis) in &analyses[1..] {
+            assert_ne!(analysis.score.relative, 0., "{name}");
+            assert_eq!(analysis.key_presses, analyses[0].1.key_presses);
+        }
+    }
+}
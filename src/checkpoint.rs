@@ -0,0 +1,307 @@
+use std::{fmt, path::Path};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    advanced_setup::EffortModel,
+    config_file::{self, ConfigFileError},
+    models::{
+        constraints::{Constraints, GroupDefinition},
+        genome::parse_genome,
+        geometry::GeometryError,
+        layout_map::GetLayoutMap,
+        letter_list::GetLetterList,
+    },
+    optimizer::{annealing::SaState, Objective},
+    Chain, SaProblem, SaSetup,
+};
+
+/// Where `run_sa` saves its checkpoints
+pub(crate) const CHECKPOINT_PATH: &str = "results/checkpoint.json";
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    File(ConfigFileError),
+    /// the checkpoint was written for another layout, text or effort model
+    Mismatch(&'static str),
+    Geometry(GeometryError),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(err) => write!(f, "{err}"),
+            Self::Mismatch(what) => {
+                write!(f, "checkpoint does not match the {what} of this run")
+            }
+            Self::Geometry(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<ConfigFileError> for CheckpointError {
+    fn from(value: ConfigFileError) -> Self {
+        Self::File(value)
+    }
+}
+
+impl From<GeometryError> for CheckpointError {
+    fn from(value: GeometryError) -> Self {
+        Self::Geometry(value)
+    }
+}
+
+/// Position in the random number stream of a chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: u128,
+}
+
+impl From<&ChaCha12Rng> for RngState {
+    fn from(rng: &ChaCha12Rng) -> Self {
+        Self {
+            seed: rng.get_seed(),
+            stream: rng.get_stream(),
+            word_pos: rng.get_word_pos(),
+        }
+    }
+}
+
+impl From<&RngState> for ChaCha12Rng {
+    fn from(state: &RngState) -> Self {
+        let mut rng = ChaCha12Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(state.word_pos);
+        rng
+    }
+}
+
+/// Everything needed to continue an annealing chain exactly where it stopped.
+///
+/// Floats are written with all digits so the resumed run takes the same decisions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    pub(crate) setup: SaSetup,
+    pub(crate) effort_model: EffortModel,
+    seed: u64,
+    /// characters locked to layout map indices
+    pinned: Vec<(char, usize)>,
+    #[serde(default)]
+    groups: Vec<GroupDefinition>,
+    /// objective of the baseline genome, detects a different layout or text
+    baseline: f64,
+    iteration: usize,
+    temperature: f64,
+    static_count: f64,
+    genome: String,
+    /// absolute objective of `genome` as accumulated by the swaps
+    objective: f64,
+    current_objective: f64,
+    best_genome: String,
+    best_objective: f64,
+    best_absolute: f64,
+    rng: RngState,
+}
+
+impl Checkpoint {
+    pub(crate) fn new<const N: usize>(
+        problem: &SaProblem<N>,
+        seed: u64,
+        state: &SaState<N>,
+        objective: &Objective<N>,
+        rng: &ChaCha12Rng,
+        setup: &SaSetup,
+    ) -> Self {
+        Self {
+            setup: *setup,
+            effort_model: problem.effort_model.clone(),
+            seed,
+            pinned: problem.constraints.pinned().collect(),
+            groups: problem.constraints.group_definitions().cloned().collect(),
+            baseline: problem.layout_score,
+            iteration: state.iteration,
+            temperature: state.temperature,
+            static_count: state.static_count,
+            genome: objective.genome().iter().collect(),
+            objective: objective.absolute(),
+            current_objective: state.current_objective,
+            best_genome: state.best_genome.iter().collect(),
+            best_objective: state.best_objective,
+            best_absolute: state.best_absolute,
+            rng: RngState::from(rng),
+        }
+    }
+
+    pub(crate) fn constraints<L: GetLetterList<N> + GetLayoutMap<N>, const N: usize>(
+        &self,
+        layout: &L,
+    ) -> Result<Constraints<N>, CheckpointError> {
+        let layout_map = layout.get_layout_map();
+
+        let constraints = self
+            .pinned
+            .iter()
+            .try_fold(
+                Constraints::none(layout.get_letter_list()),
+                |constraints, (c, index)| constraints.pin(*c, *index),
+            )
+            .map_err(|_| CheckpointError::Mismatch("layout"))?;
+
+        self.groups
+            .iter()
+            .try_fold(constraints, |constraints, group| {
+                constraints.add_group(&layout_map, group.clone())
+            })
+            .map_err(|_| CheckpointError::Mismatch("layout"))
+    }
+
+    pub(crate) fn load(path: &Path) -> Result<Self, CheckpointError> {
+        Ok(config_file::read_config_file(path)?)
+    }
+
+    /// Write the checkpoint as JSON, replacing the file only once it is complete
+    pub(crate) fn save(&self, path: &str) {
+        let content = serde_json::to_string_pretty(self).expect("Checkpoint should serialize");
+
+        let tmp_path = format!("{path}.tmp");
+        std::fs::write(&tmp_path, content)
+            .unwrap_or_else(|_| panic!("Failed to write file {}", tmp_path));
+        std::fs::rename(&tmp_path, path).unwrap_or_else(|_| panic!("Failed to replace {}", path));
+    }
+
+    pub(crate) fn restore<'p, const N: usize>(
+        &self,
+        problem: &'p SaProblem<N>,
+    ) -> Result<Chain<'p, N>, CheckpointError> {
+        if self.baseline != problem.layout_score {
+            return Err(CheckpointError::Mismatch("layout and text"));
+        }
+
+        let genome = parse_genome(&self.genome, &problem.letter_list)
+            .ok_or(CheckpointError::Mismatch("layout"))?;
+        let best_genome = parse_genome(&self.best_genome, &problem.letter_list)
+            .ok_or(CheckpointError::Mismatch("layout"))?;
+
+        let mut objective = problem.objective(&genome);
+        objective.resume(self.objective);
+        // the n-gram objectives only depend on the genome, the total is the accumulated one
+        let mut best = problem.objective(&best_genome);
+        best.resume(self.best_absolute);
+
+        Ok(Chain {
+            seed: self.seed,
+            rng: ChaCha12Rng::from(&self.rng),
+            objective,
+            state: SaState {
+                iteration: self.iteration,
+                temperature: self.temperature,
+                static_count: self.static_count,
+                current_objective: self.current_objective,
+                best_genome,
+                best_objective: self.best_objective,
+                best_absolute: self.best_absolute,
+                best_state: best.save(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    fn checkpoint_of<const N: usize>(
+        problem: &SaProblem<N>,
+        chain: &Chain<N>,
+        setup: &SaSetup,
+    ) -> Checkpoint {
+        Checkpoint::new(
+            problem,
+            chain.seed,
+            &chain.state,
+            &chain.objective,
+            &chain.rng,
+            setup,
+        )
+    }
+
+    #[test]
+    fn test_resumed_run_matches_uninterrupted_run() {
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let effort_model = EffortModel::default();
+        let constraints = Constraints::none(QwertyEnUs().get_letter_list())
+            .pin('A', 0)
+            .and_then(|constraints| {
+                constraints.group(&QwertyEnUs(), "AEIOU", Rule::SameHand, Some(2.))
+            })
+            .expect("should pin");
+        let problem =
+            SaProblem::new(&QwertyEnUs(), &text, &effort_model, &constraints).expect("fixed keys");
+
+        let setup = SaSetup {
+            temperature: 500.,
+            epoch: 20.,
+            cooling_rate: 0.99,
+            num_iterations: 3000,
+            seed: 7,
+        };
+        let interrupted = SaSetup {
+            num_iterations: 1234,
+            ..setup
+        };
+
+        let mut chain = problem.start(&setup, setup.seed);
+        problem.anneal(&mut chain, &setup, &LoggingOptions::silent());
+
+        let mut first_half = problem.start(&setup, setup.seed);
+        problem.anneal(&mut first_half, &interrupted, &LoggingOptions::silent());
+        assert_eq!(first_half.state.iteration, 1234);
+
+        let json = serde_json::to_string(&checkpoint_of(&problem, &first_half, &setup))
+            .expect("should serialize");
+        let checkpoint: Checkpoint = config_file::from_json_str(&json).expect("should parse");
+
+        let resumed_constraints = checkpoint.constraints(&QwertyEnUs()).expect("should match");
+        assert_eq!(resumed_constraints, constraints);
+
+        let mut resumed = checkpoint.restore(&problem).expect("should match");
+        problem.anneal(&mut resumed, &checkpoint.setup, &LoggingOptions::silent());
+
+        assert_eq!(resumed.state.iteration, chain.state.iteration);
+        assert_eq!(resumed.state.best_genome, chain.state.best_genome);
+        assert_eq!(resumed.objective.genome(), chain.objective.genome());
+        assert_eq!(resumed.state.best_absolute, chain.state.best_absolute);
+    }
+
+    #[test]
+    fn test_checkpoint_of_other_layout_is_rejected() {
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let effort_model = EffortModel::default();
+        let setup = SaSetup {
+            temperature: 100.,
+            epoch: 20.,
+            cooling_rate: 0.9,
+            num_iterations: 10,
+            seed: 1,
+        };
+
+        let constraints = Constraints::none(QwertyEnUs().get_letter_list());
+        let qwerty =
+            SaProblem::new(&QwertyEnUs(), &text, &effort_model, &constraints).expect("fixed keys");
+        let checkpoint = checkpoint_of(&qwerty, &qwerty.start(&setup, setup.seed), &setup);
+
+        let other_text = SaProblem::new(&QwertyEnUs(), "hello world", &effort_model, &constraints)
+            .expect("fixed keys");
+        assert!(matches!(
+            checkpoint.restore(&other_text),
+            Err(CheckpointError::Mismatch(_))
+        ));
+    }
+}
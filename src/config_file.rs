@@ -0,0 +1,66 @@
+use std::{fmt, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+#[derive(Debug)]
+pub enum ConfigFileError {
+    Io(std::io::Error),
+    Parse(String),
+    UnsupportedFormat(String),
+}
+
+impl fmt::Display for ConfigFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to access file: {err}"),
+            Self::Parse(err) => write!(f, "failed to parse file: {err}"),
+            Self::UnsupportedFormat(ext) => {
+                write!(f, "unsupported file format '{ext}', use .toml or .json")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigFileError {}
+
+/// Read a TOML or JSON file, the format is selected by the file extension
+pub(crate) fn read_config_file<T: DeserializeOwned>(path: &Path) -> Result<T, ConfigFileError> {
+    let content = std::fs::read_to_string(path).map_err(ConfigFileError::Io)?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => from_toml_str(&content),
+        Some("json") => from_json_str(&content),
+        ext => Err(ConfigFileError::UnsupportedFormat(
+            ext.unwrap_or_default().to_string(),
+        )),
+    }
+}
+
+/// Write a TOML or JSON file, the format is selected by the file extension
+pub(crate) fn write_config_file<T: Serialize>(
+    path: &Path,
+    value: &T,
+) -> Result<(), ConfigFileError> {
+    let content = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => {
+            toml::to_string_pretty(value).map_err(|err| ConfigFileError::Parse(err.to_string()))?
+        }
+        Some("json") => serde_json::to_string_pretty(value)
+            .map_err(|err| ConfigFileError::Parse(err.to_string()))?,
+        ext => {
+            return Err(ConfigFileError::UnsupportedFormat(
+                ext.unwrap_or_default().to_string(),
+            ))
+        }
+    };
+
+    std::fs::write(path, content).map_err(ConfigFileError::Io)
+}
+
+pub(crate) fn from_toml_str<T: DeserializeOwned>(content: &str) -> Result<T, ConfigFileError> {
+    toml::from_str(content).map_err(|err| ConfigFileError::Parse(err.to_string()))
+}
+
+pub(crate) fn from_json_str<T: DeserializeOwned>(content: &str) -> Result<T, ConfigFileError> {
+    serde_json::from_str(content).map_err(|err| ConfigFileError::Parse(err.to_string()))
+}
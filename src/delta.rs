@@ -0,0 +1,347 @@
+use crate::advanced_setup::EffortModel;
+use crate::metrics::{bigram_metrics, trigram_metrics, Metrics};
+use crate::models::finger_list::FingerList;
+use crate::models::layout_map::{FixedKeys, KeyboardKey};
+use crate::ngrams::{Context, NgramStats};
+use crate::objective::{
+    bigram_objective, get_home_finger_list, get_key_positions, Efforts, KeyPress,
+};
+
+/// Scores swaps of two keys incrementally.
+///
+/// Keeps the objective of every bigram for the current genome,
+/// so a swap only re-evaluates the bigrams that involve one of the swapped letters.
+/// Weighted metrics add the letter bigrams and trigrams after the bigrams.
+pub(crate) struct DeltaEvaluator<'a, const N: usize> {
+    ngram_stats: &'a NgramStats,
+    layout_map: &'a [KeyboardKey; N],
+    fixed_keys: &'a FixedKeys,
+    letter_list: &'a [char; N],
+    efforts: Efforts<'a>,
+    metric_weights: Metrics,
+    home_finger_list: FingerList,
+    /// n-grams that involve a letter, indexed like the letter list
+    letter_ngrams: Vec<Vec<usize>>,
+    genome: [char; N],
+    key_positions: [usize; N],
+    /// objective of every n-gram multiplied by its count
+    ngram_objectives: Vec<f64>,
+    objective: f64,
+    /// n-grams and their objective after the last evaluated swap
+    pending: Vec<(usize, f64)>,
+    pending_swap: Option<(usize, usize)>,
+}
+
+/// Genome of a `DeltaEvaluator` with the objectives of its n-grams,
+/// restoring it is cheaper than evaluating the genome again
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EvaluatorState<const N: usize> {
+    genome: [char; N],
+    key_positions: [usize; N],
+    ngram_objectives: Vec<f64>,
+    objective: f64,
+}
+
+impl<'a, const N: usize> DeltaEvaluator<'a, N> {
+    pub(crate) fn new(
+        ngram_stats: &'a NgramStats,
+        genome: &[char; N],
+        layout_map: &'a [KeyboardKey; N],
+        fixed_keys: &'a FixedKeys,
+        letter_list: &'a [char; N],
+        effort_model: &'a EffortModel,
+    ) -> Self {
+        debug_assert_eq!(ngram_stats.whitespace, effort_model.whitespace);
+
+        let letter = |key_press: &KeyPress| match key_press {
+            KeyPress::Letter(key, _) => Some(*key),
+            _ => None,
+        };
+
+        let mut ngram_letters: Vec<Vec<usize>> = ngram_stats
+            .bigrams
+            .iter()
+            .map(|(context, key_press, _)| {
+                let context_letter = match context {
+                    Context::Start => None,
+                    Context::After(key_press) => letter(key_press),
+                    Context::Space { after, .. } => after.as_ref().and_then(letter),
+                };
+
+                [context_letter, letter(key_press)]
+                    .into_iter()
+                    .flatten()
+                    .collect()
+            })
+            .collect();
+
+        if !effort_model.metric_weights.is_zero() {
+            let bigrams = ngram_stats
+                .letter_bigrams
+                .iter()
+                .map(|(keys, _)| keys.to_vec());
+            let trigrams = ngram_stats
+                .letter_trigrams
+                .iter()
+                .map(|(keys, _)| keys.to_vec());
+            ngram_letters.extend(bigrams.chain(trigrams));
+        }
+
+        let mut letter_ngrams = vec![Vec::new(); N];
+        for (i, keys) in ngram_letters.iter().enumerate() {
+            for key in keys {
+                if letter_ngrams[*key].last() != Some(&i) {
+                    letter_ngrams[*key].push(i);
+                }
+            }
+        }
+
+        let mut evaluator = Self {
+            ngram_stats,
+            layout_map,
+            fixed_keys,
+            letter_list,
+            efforts: Efforts::from(effort_model),
+            metric_weights: effort_model.metric_weights,
+            home_finger_list: get_home_finger_list(layout_map, fixed_keys),
+            letter_ngrams,
+            genome: *genome,
+            key_positions: [0; N],
+            ngram_objectives: vec![0.; ngram_letters.len()],
+            objective: 0.,
+            pending: Vec::new(),
+            pending_swap: None,
+        };
+        evaluator.set_genome(genome);
+        evaluator
+    }
+
+    fn evaluate_ngram(&self, i: usize) -> f64 {
+        let stats = self.ngram_stats;
+        let key = |letter: usize| &self.layout_map[self.key_positions[letter]];
+
+        let (objective, count) = if i < stats.bigrams.len() {
+            let (context, key_press, count) = &stats.bigrams[i];
+            let objective = bigram_objective(
+                context,
+                *key_press,
+                &self.key_positions,
+                &self.home_finger_list,
+                self.layout_map,
+                self.fixed_keys,
+                &self.efforts,
+            );
+            (objective, count)
+        } else if i < stats.bigrams.len() + stats.letter_bigrams.len() {
+            let ([a, b], count) = &stats.letter_bigrams[i - stats.bigrams.len()];
+            let metrics = bigram_metrics(key(*a), key(*b));
+            (metrics.weighted(&self.metric_weights), count)
+        } else {
+            let ([a, b, c], count) =
+                &stats.letter_trigrams[i - stats.bigrams.len() - stats.letter_bigrams.len()];
+            let metrics = trigram_metrics(key(*a), key(*b), key(*c));
+            (metrics.weighted(&self.metric_weights), count)
+        };
+
+        objective * *count as f64
+    }
+
+    /// Evaluate every n-gram for a new genome
+    pub(crate) fn set_genome(&mut self, genome: &[char; N]) {
+        self.genome = *genome;
+        self.key_positions = get_key_positions(genome, self.letter_list);
+
+        for i in 0..self.ngram_objectives.len() {
+            self.ngram_objectives[i] = self.evaluate_ngram(i);
+        }
+        self.objective = self.ngram_objectives.iter().sum();
+
+        self.pending.clear();
+        self.pending_swap = None;
+    }
+
+    pub(crate) fn save(&self) -> EvaluatorState<N> {
+        EvaluatorState {
+            genome: self.genome,
+            key_positions: self.key_positions,
+            ngram_objectives: self.ngram_objectives.clone(),
+            objective: self.objective,
+        }
+    }
+
+    /// Go back to a saved genome without evaluating it
+    pub(crate) fn restore(&mut self, state: &EvaluatorState<N>) {
+        self.genome = state.genome;
+        self.key_positions = state.key_positions;
+        self.ngram_objectives.clone_from(&state.ngram_objectives);
+        self.objective = state.objective;
+
+        self.pending.clear();
+        self.pending_swap = None;
+    }
+
+    /// Continue with the objective that earlier swaps accumulated for the current genome,
+    /// it can differ from a fresh evaluation by rounding
+    pub(crate) fn resume(&mut self, objective: f64) {
+        self.objective = objective;
+    }
+
+    pub(crate) fn layout_map(&self) -> &'a [KeyboardKey; N] {
+        self.layout_map
+    }
+
+    pub(crate) fn genome(&self) -> &[char; N] {
+        &self.genome
+    }
+
+    /// Absolute objective of the current genome
+    pub(crate) fn objective(&self) -> f64 {
+        self.objective
+    }
+
+    fn swap_letters(&mut self, a: usize, b: usize) -> (usize, usize) {
+        let letter_a = self.letter_index(self.genome[a]);
+        let letter_b = self.letter_index(self.genome[b]);
+
+        self.key_positions.swap(letter_a, letter_b);
+
+        (letter_a, letter_b)
+    }
+
+    fn letter_index(&self, c: char) -> usize {
+        self.letter_list
+            .iter()
+            .position(|letter| *letter == c)
+            .expect("Key should be in letter list")
+    }
+
+    /// Change of the absolute objective if the keys at positions `a` and `b` are swapped,
+    /// apply it with `commit_swap`
+    pub(crate) fn swap_delta(&mut self, a: usize, b: usize) -> f64 {
+        let (letter_a, letter_b) = self.swap_letters(a, b);
+
+        let mut ngrams = [
+            self.letter_ngrams[letter_a].as_slice(),
+            self.letter_ngrams[letter_b].as_slice(),
+        ]
+        .concat();
+        ngrams.sort_unstable();
+        ngrams.dedup();
+
+        self.pending.clear();
+        let mut delta = 0.;
+        for i in ngrams {
+            let objective = self.evaluate_ngram(i);
+            delta += objective - self.ngram_objectives[i];
+            self.pending.push((i, objective));
+        }
+
+        // restore the current genome
+        self.key_positions.swap(letter_a, letter_b);
+        self.pending_swap = Some((a, b));
+
+        delta
+    }
+
+    /// Apply the swap of the last `swap_delta`
+    pub(crate) fn commit_swap(&mut self) {
+        let Some((a, b)) = self.pending_swap.take() else {
+            return;
+        };
+
+        self.swap_letters(a, b);
+        self.genome.swap(a, b);
+
+        for (i, objective) in self.pending.drain(..) {
+            self.objective += objective - self.ngram_objectives[i];
+            self.ngram_objectives[i] = objective;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::objective::{baseline_objective_function, get_key_presses};
+    use crate::prelude::*;
+
+    fn assert_swaps_match_full_evaluation(effort_model: &EffortModel) {
+        let layout = QwertzDeDe();
+        let text = std::fs::read_to_string("resources/meinBuch.txt").expect("should read file");
+
+        let ngram_stats = NgramStats::new(&get_key_presses(&layout, &text), true);
+        let layout_map = layout.get_layout_map();
+        let fixed_keys = layout.get_fixed_keys().expect("fixed keys");
+        let letter_list = layout.get_letter_list();
+
+        let mut evaluator = DeltaEvaluator::new(
+            &ngram_stats,
+            layout.get_genome(),
+            &layout_map,
+            &fixed_keys,
+            letter_list,
+            effort_model,
+        );
+
+        let full = |genome: &[char; 48]| {
+            baseline_objective_function(
+                &ngram_stats,
+                genome,
+                &layout_map,
+                &fixed_keys,
+                letter_list,
+                effort_model,
+            )
+        };
+
+        let saved = evaluator.save();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        for i in 0..50 {
+            let (a, b) = (rng.gen_range(0..48), rng.gen_range(0..48));
+
+            let mut swapped = *evaluator.genome();
+            swapped.swap(a, b);
+
+            let expected = full(&swapped) - evaluator.objective();
+            let delta = evaluator.swap_delta(a, b);
+            assert!((delta - expected).abs() < 1e-6 * evaluator.objective());
+
+            if i % 2 == 0 {
+                evaluator.commit_swap();
+                assert_eq!(evaluator.genome(), &swapped);
+            }
+        }
+
+        let objective = evaluator.objective();
+        assert!((objective - full(evaluator.genome())).abs() < 1e-6 * objective);
+
+        // a restored genome scores swaps like a freshly evaluated one
+        evaluator.restore(&saved);
+        assert_eq!(evaluator.save(), saved);
+        let mut swapped = *layout.get_genome();
+        swapped.swap(0, 20);
+        let delta = evaluator.swap_delta(0, 20);
+        assert!((delta - (full(&swapped) - evaluator.objective())).abs() < 1e-6 * objective);
+    }
+
+    #[test]
+    fn test_swaps_match_full_evaluation() {
+        assert_swaps_match_full_evaluation(&EffortModel::default());
+        assert_swaps_match_full_evaluation(&EffortModel {
+            metric_weights: Metrics {
+                same_finger: 20.,
+                skip_bigram: 5.,
+                lateral_stretch: 3.,
+                scissor: 3.,
+                inward_roll: -2.,
+                outward_roll: -1.,
+                redirect: 4.,
+                alternation: -1.,
+            },
+            ..Default::default()
+        });
+    }
+}
@@ -6,6 +6,46 @@ use plotters_backend::{text_anchor, BackendColor, DrawingBackend, FontFamily, Fo
 
 use crate::models::layout_map::KeyboardKey;
 
+/// Pixels per key unit
+const KEY_PIXELS: f64 = 100.;
+/// Border around the keys in pixels
+const MARGIN: f64 = 20.;
+/// Gap between neighbouring keys in pixels
+const KEY_GAP: f64 = 10.;
+
+/// Maps key units to image pixels, the number row is at the top of the image
+struct Transform {
+    left: f64,
+    top: f64,
+}
+
+impl Transform {
+    /// Transform and image size that fit every key of the layout map
+    fn fit(layout_map: &[KeyboardKey]) -> (Self, (u32, u32)) {
+        let edge = |key_edge: fn(&KeyboardKey) -> f64, max: bool| {
+            let edges = layout_map.iter().map(key_edge);
+            match max {
+                true => edges.fold(f64::NEG_INFINITY, f64::max),
+                false => edges.fold(f64::INFINITY, f64::min),
+            }
+        };
+        let left = edge(|key| key.x - key.width / 2., false);
+        let right = edge(|key| key.x + key.width / 2., true);
+        let bottom = edge(|key| key.y - key.height / 2., false);
+        let top = edge(|key| key.y + key.height / 2., true);
+
+        let size = |length: f64| (length * KEY_PIXELS + 2. * MARGIN).ceil() as u32;
+        (Self { left, top }, (size(right - left), size(top - bottom)))
+    }
+
+    fn point(&self, x: f64, y: f64) -> (i32, i32) {
+        (
+            ((x - self.left) * KEY_PIXELS + MARGIN).round() as i32,
+            ((self.top - y) * KEY_PIXELS + MARGIN).round() as i32,
+        )
+    }
+}
+
 // KEYBOARD FUNCTIONS
 pub(crate) fn draw_keyboard<const N: usize>(
     my_genome: &[char; N],
@@ -14,15 +54,16 @@ pub(crate) fn draw_keyboard<const N: usize>(
 ) {
     let file_name = format!("results/{}.png", id);
 
-    const IMG_HEIGHT: i32 = 600;
-    let mut plt = BitMapBackend::new(&file_name, (1400, IMG_HEIGHT as u32));
+    let (transform, size) = Transform::fit(layout_map);
+    let mut plt = BitMapBackend::new(&file_name, size);
     // plt.into_drawing_area().fill(&plotters::style::RGBColor(255, 255, 255)).unwrap();
 
     for i in 0..N {
         let layout = &layout_map[i];
 
-        let x = layout.x;
-        let y = IMG_HEIGHT - layout.y;
+        let (x, y) = transform.point(layout.x, layout.y);
+        let half_width = ((layout.width * KEY_PIXELS - KEY_GAP) / 2.).round() as i32;
+        let half_height = ((layout.height * KEY_PIXELS - KEY_GAP) / 2.).round() as i32;
 
         let letter = my_genome[i];
         let mut my_color = RGBColor(176, 176, 176);
@@ -56,8 +97,8 @@ pub(crate) fn draw_keyboard<const N: usize>(
         }
 
         plt.draw_rect(
-            (x - 45, y - 45),
-            (x + 45, y + 45),
+            (x - half_width, y - half_height),
+            (x + half_width, y + half_height),
             &plotters::style::ShapeStyle {
                 color: my_color,
                 filled: true,
@@ -0,0 +1,121 @@
+pub(crate) mod firmware;
+pub(crate) mod keylayout;
+pub(crate) mod klc;
+pub(crate) mod xkb;
+
+use std::fmt;
+
+use crate::models::geometry::{get_key_columns, GeometryError, ROW_NAMES};
+use crate::models::key_maps::get_key_characters;
+use crate::models::layout::ILayout;
+
+/// Letters changed by a dead key and what they become
+const COMPOSITIONS: [(char, &str, &str); 6] = [
+    ('^', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+    ('´', "aeiouyAEIOUY", "áéíóúýÁÉÍÓÚÝ"),
+    ('`', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ('ˇ', "cnrszCNRSZ", "čňřšžČŇŘŠŽ"),
+    ('˜', "anoANO", "ãñõÃÑÕ"),
+    ('¸', "cC", "çÇ"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportError {
+    Geometry(GeometryError),
+    /// the format has no key at this position of a standard keyboard
+    MissingKey {
+        format: &'static str,
+        row: usize,
+        column: i32,
+    },
+    /// the firmware has no keycode for the character
+    UnsupportedCharacter {
+        format: &'static str,
+        character: char,
+    },
+    /// a key of a board file is neither a key name, a modifier nor a single character
+    UnknownBoardKey(String),
+    /// a key of the layout is missing in the board file
+    NotOnBoard(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Geometry(err) => write!(f, "{err}"),
+            Self::MissingKey {
+                format,
+                row,
+                column,
+            } => write!(
+                f,
+                "{format} has no key in column {column} of the {} row",
+                ROW_NAMES[*row]
+            ),
+            Self::UnsupportedCharacter { format, character } => {
+                write!(f, "{format} has no keycode for '{character}'")
+            }
+            Self::UnknownBoardKey(key) => write!(f, "unknown key '{key}' in the board file"),
+            Self::NotOnBoard(key) => write!(f, "key {key} of the layout is not on the board"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// XKB name of the key in a row and column of a standard keyboard,
+/// board files of the firmware exporters use these names too
+pub(crate) fn key_name(row: usize, column: i32) -> Option<String> {
+    match (row, column) {
+        (0, 0) => Some("TLDE".to_string()),
+        (0, 1..=12) => Some(format!("AE{column:02}")),
+        (1, 1..=12) => Some(format!("AD{column:02}")),
+        (2, 1..=11) => Some(format!("AC{column:02}")),
+        (2, 12) => Some("BKSL".to_string()),
+        (3, 0) => Some("LSGT".to_string()),
+        (3, 1..=10) => Some(format!("AB{column:02}")),
+        _ => None,
+    }
+}
+
+/// Characters of a key and its position on a standard keyboard
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ExportKey {
+    /// letter row, 0 is the number row
+    pub(crate) row: usize,
+    /// column like in [`Geometry`](crate::models::geometry::Geometry)
+    pub(crate) column: i32,
+    /// unshifted and shifted character
+    pub(crate) characters: [Option<char>; 2],
+}
+
+/// Keys of a genome from the number row to the bottom row and from left to right
+pub(crate) fn get_export_keys<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+) -> Result<Vec<ExportKey>, ExportError> {
+    let columns = get_key_columns(&layout.get_layout_map()).map_err(ExportError::Geometry)?;
+
+    let mut keys: Vec<ExportKey> = columns
+        .into_iter()
+        .zip(get_key_characters(layout, genome))
+        .map(|((row, column), characters)| ExportKey {
+            row,
+            column,
+            characters,
+        })
+        .collect();
+    keys.sort_by_key(|key| (key.row, key.column));
+
+    Ok(keys)
+}
+
+/// Letters that a dead key changes and the characters they become,
+/// a dead key followed by space types the dead key itself
+pub(crate) fn get_compositions(dead_key: char) -> Vec<(char, char)> {
+    COMPOSITIONS
+        .iter()
+        .filter(|(key, _, _)| *key == dead_key)
+        .flat_map(|(_, letters, composed)| letters.chars().zip(composed.chars()))
+        .collect()
+}
@@ -0,0 +1,609 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{get_export_keys, key_name, ExportError};
+use crate::config_file::{self, ConfigFileError};
+use crate::models::layout::ILayout;
+use crate::models::layout_map::FixedKey;
+
+/// Digits and symbols of US English as
+/// (unshifted, shifted, QMK keycode, ZMK keycode, shifted QMK keycode, shifted ZMK keycode)
+const US_KEYS: [(char, char, &str, &str, &str, &str); 21] = [
+    ('1', '!', "KC_1", "N1", "KC_EXLM", "EXCL"),
+    ('2', '@', "KC_2", "N2", "KC_AT", "AT"),
+    ('3', '#', "KC_3", "N3", "KC_HASH", "HASH"),
+    ('4', '$', "KC_4", "N4", "KC_DLR", "DOLLAR"),
+    ('5', '%', "KC_5", "N5", "KC_PERC", "PERCENT"),
+    ('6', '^', "KC_6", "N6", "KC_CIRC", "CARET"),
+    ('7', '&', "KC_7", "N7", "KC_AMPR", "AMPS"),
+    ('8', '*', "KC_8", "N8", "KC_ASTR", "STAR"),
+    ('9', '(', "KC_9", "N9", "KC_LPRN", "LPAR"),
+    ('0', ')', "KC_0", "N0", "KC_RPRN", "RPAR"),
+    ('`', '~', "KC_GRV", "GRAVE", "KC_TILD", "TILDE"),
+    ('-', '_', "KC_MINS", "MINUS", "KC_UNDS", "UNDER"),
+    ('=', '+', "KC_EQL", "EQUAL", "KC_PLUS", "PLUS"),
+    ('[', '{', "KC_LBRC", "LBKT", "KC_LCBR", "LBRC"),
+    (']', '}', "KC_RBRC", "RBKT", "KC_RCBR", "RBRC"),
+    ('\\', '|', "KC_BSLS", "BSLH", "KC_PIPE", "PIPE"),
+    (';', ':', "KC_SCLN", "SEMI", "KC_COLN", "COLON"),
+    ('\'', '"', "KC_QUOT", "SQT", "KC_DQUO", "DQT"),
+    (',', '<', "KC_COMM", "COMMA", "KC_LABK", "LT"),
+    ('.', '>', "KC_DOT", "DOT", "KC_RABK", "GT"),
+    ('/', '?', "KC_SLSH", "FSLH", "KC_QUES", "QMARK"),
+];
+
+/// QMK keycode and ZMK binding of the fixed keys, in the order of [`FixedKey::ALL`]
+const FIXED_KEYS: [(&str, &str); 7] = [
+    ("KC_LSFT", "&kp LSHFT"),
+    ("KC_RSFT", "&kp RSHFT"),
+    ("KC_SPC", "&kp SPACE"),
+    ("KC_SPC", "&kp SPACE"),
+    ("KC_TAB", "&kp TAB"),
+    ("KC_ENT", "&kp RET"),
+    ("KC_BSPC", "&kp BSPC"),
+];
+
+/// Keys that layouts do not place as (name in board files, QMK keycode, ZMK binding)
+const OTHER_KEYS: [(&str, &str, &str); 15] = [
+    ("esc", "KC_ESC", "&kp ESC"),
+    ("caps", "KC_CAPS", "&kp CAPS"),
+    ("del", "KC_DEL", "&kp DEL"),
+    ("lctl", "KC_LCTL", "&kp LCTRL"),
+    ("lgui", "KC_LGUI", "&kp LGUI"),
+    ("lalt", "KC_LALT", "&kp LALT"),
+    ("ralt", "KC_RALT", "&kp RALT"),
+    ("rgui", "KC_RGUI", "&kp RGUI"),
+    ("app", "KC_APP", "&kp K_APP"),
+    ("rctl", "KC_RCTL", "&kp RCTRL"),
+    ("left", "KC_LEFT", "&kp LEFT"),
+    ("down", "KC_DOWN", "&kp DOWN"),
+    ("up", "KC_UP", "&kp UP"),
+    ("right", "KC_RGHT", "&kp RIGHT"),
+    ("none", "KC_NO", "&none"),
+];
+
+/// Keys of a keyboard in the order of its firmware, loaded from a TOML or JSON board file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Board {
+    pub name: String,
+    /// layout macro of the board in QMK
+    pub layout: String,
+    /// rows of the layout macro: letter keys by their XKB name (`AE01`, `AD01`, …),
+    /// fixed keys by name (`left_shift`, `tab`, …), other keys like `lctl` or single characters
+    pub keys: Vec<Vec<String>>,
+}
+
+impl Board {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigFileError> {
+        config_file::read_config_file(path.as_ref())
+    }
+}
+
+/// Keycodes of a character for a host that is set to US English
+struct Keycode {
+    qmk: String,
+    zmk: String,
+    /// QMK keycode of the key that types the character
+    key: String,
+    shifted: bool,
+}
+
+fn keycode(c: char) -> Option<Keycode> {
+    if c.is_ascii_alphabetic() {
+        let letter = c.to_ascii_uppercase();
+        let key = format!("KC_{letter}");
+        return Some(match c.is_ascii_lowercase() {
+            true => Keycode {
+                qmk: key.clone(),
+                zmk: letter.to_string(),
+                key,
+                shifted: false,
+            },
+            false => Keycode {
+                qmk: format!("S({key})"),
+                zmk: format!("LS({letter})"),
+                key,
+                shifted: true,
+            },
+        });
+    }
+    if c == ' ' {
+        return Some(Keycode {
+            qmk: "KC_SPC".to_string(),
+            zmk: "SPACE".to_string(),
+            key: "KC_SPC".to_string(),
+            shifted: false,
+        });
+    }
+
+    US_KEYS
+        .iter()
+        .find(|(unshifted, shifted, ..)| c == *unshifted || c == *shifted)
+        .map(|&(unshifted, _, qmk, zmk, shifted_qmk, shifted_zmk)| {
+            let shifted = c != unshifted;
+            Keycode {
+                qmk: if shifted { shifted_qmk } else { qmk }.to_string(),
+                zmk: if shifted { shifted_zmk } else { zmk }.to_string(),
+                key: qmk.to_string(),
+                shifted,
+            }
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Firmware {
+    Qmk,
+    Zmk,
+}
+
+impl Firmware {
+    fn name(self) -> &'static str {
+        match self {
+            Firmware::Qmk => "QMK",
+            Firmware::Zmk => "ZMK",
+        }
+    }
+
+    /// QMK keycode or ZMK binding that types a character,
+    /// QMK types characters outside of US English with its Unicode feature
+    fn binding(self, c: char) -> Result<String, ExportError> {
+        match (self, keycode(c)) {
+            (Firmware::Qmk, Some(keycode)) => Ok(keycode.qmk),
+            (Firmware::Zmk, Some(keycode)) => Ok(format!("&kp {}", keycode.zmk)),
+            (Firmware::Qmk, None) => Ok(format!("UC(0x{:04X})", c as u32)),
+            (Firmware::Zmk, None) => Err(ExportError::UnsupportedCharacter {
+                format: self.name(),
+                character: c,
+            }),
+        }
+    }
+
+    fn pick(self, (qmk, zmk): (&str, &str)) -> String {
+        match self {
+            Firmware::Qmk => qmk.to_string(),
+            Firmware::Zmk => zmk.to_string(),
+        }
+    }
+}
+
+/// Binding of a key of the board
+struct FirmwareKey {
+    /// name of the key in the board file
+    name: String,
+    binding: String,
+    /// binding with shift if it types another character than on a US keyboard
+    shifted: Option<String>,
+}
+
+/// Bindings of a letter key, shift only gets its own binding
+/// if the characters are not the two levels of one US key
+fn letter_bindings(
+    firmware: Firmware,
+    characters: [Option<char>; 2],
+) -> Result<(String, Option<String>), ExportError> {
+    let (unshifted, shifted) = match characters {
+        [Some(unshifted), Some(shifted)] => {
+            let same_key = matches!(
+                (keycode(unshifted), keycode(shifted)),
+                (Some(a), Some(b)) if a.key == b.key && !a.shifted && b.shifted
+            );
+            (unshifted, (!same_key).then_some(shifted))
+        }
+        [Some(c), None] | [None, Some(c)] => (c, None),
+        [None, None] => return Ok((firmware.pick(("KC_NO", "&none")), None)),
+    };
+
+    Ok((
+        firmware.binding(unshifted)?,
+        shifted.map(|c| firmware.binding(c)).transpose()?,
+    ))
+}
+
+/// Bindings of the rows of a board with the keys of `genome` on their XKB names
+fn firmware_keys<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+    board: &Board,
+    firmware: Firmware,
+) -> Result<Vec<Vec<FirmwareKey>>, ExportError> {
+    let mut letters = BTreeMap::new();
+    for key in get_export_keys(layout, genome)? {
+        let name = key_name(key.row, key.column).ok_or(ExportError::MissingKey {
+            format: firmware.name(),
+            row: key.row,
+            column: key.column,
+        })?;
+        letters.insert(name, key.characters);
+    }
+
+    if let Some(name) = letters
+        .keys()
+        .find(|name| !board.keys.iter().flatten().any(|key| key == *name))
+    {
+        return Err(ExportError::NotOnBoard(name.clone()));
+    }
+
+    let key_names: Vec<String> = (0..4)
+        .flat_map(|row| (0..=12).filter_map(move |column| key_name(row, column)))
+        .collect();
+
+    let firmware_key = |name: &String| {
+        let (binding, shifted) = if let Some(characters) = letters.get(name) {
+            letter_bindings(firmware, *characters)?
+        } else if key_names.contains(name) {
+            letter_bindings(firmware, [None, None])?
+        } else if let Some(key) = FixedKey::ALL.iter().find(|key| key.name() == name) {
+            (firmware.pick(FIXED_KEYS[*key as usize]), None)
+        } else if let Some(&(_, qmk, zmk)) = OTHER_KEYS.iter().find(|(other, ..)| other == name) {
+            (firmware.pick((qmk, zmk)), None)
+        } else {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => (firmware.binding(c)?, None),
+                _ => return Err(ExportError::UnknownBoardKey(name.clone())),
+            }
+        };
+
+        Ok(FirmwareKey {
+            name: name.clone(),
+            binding,
+            shifted,
+        })
+    };
+
+    board
+        .keys
+        .iter()
+        .map(|row| row.iter().map(firmware_key).collect())
+        .collect()
+}
+
+/// QMK `keymap.c` that types `genome` on the keys of `board`.
+///
+/// The keycodes expect the host to be set to US English, characters that shift types
+/// on another key than in US English become key overrides and characters outside of
+/// US English are typed with `UC()`.
+pub fn export_qmk<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+    name: &str,
+    board: &Board,
+) -> Result<String, ExportError> {
+    let rows = firmware_keys(layout, genome, board, Firmware::Qmk)?;
+
+    let keymap = rows
+        .iter()
+        .map(|row| {
+            let bindings: Vec<&str> = row.iter().map(|key| key.binding.as_str()).collect();
+            format!("        {}", bindings.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    let overrides: Vec<(String, String)> = rows
+        .iter()
+        .flatten()
+        .filter_map(|key| {
+            let shifted = key.shifted.as_ref()?;
+            Some((
+                format!("{}_override", key.name.to_lowercase()),
+                format!("ko_make_basic(MOD_MASK_SHIFT, {}, {shifted})", key.binding),
+            ))
+        })
+        .collect();
+
+    let mut content = format!("// {name} on {}, generated by keyboards\n", board.name);
+    content.push_str("#include QMK_KEYBOARD_H\n\n");
+    if rows.iter().flatten().any(|key| {
+        key.binding.starts_with("UC(") || key.shifted.as_ref().is_some_and(|s| s.starts_with("UC("))
+    }) {
+        content.push_str(
+            "// characters outside of US English need UNICODE_ENABLE = yes in rules.mk\n",
+        );
+    }
+    content.push_str(&format!(
+        "const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {{\n    \
+             [0] = {}(\n\
+         {keymap}\n    \
+             )\n\
+         }};\n",
+        board.layout
+    ));
+
+    if !overrides.is_empty() {
+        content.push_str(
+            "\n// shift types other characters than in US English, \
+             needs KEY_OVERRIDE_ENABLE = yes in rules.mk\n",
+        );
+        for (id, key_override) in &overrides {
+            content.push_str(&format!("const key_override_t {id} = {key_override};\n"));
+        }
+        content.push_str("\nconst key_override_t *key_overrides[] = {\n");
+        for (id, _) in &overrides {
+            content.push_str(&format!("    &{id},\n"));
+        }
+        content.push_str("};\n");
+    }
+
+    Ok(content)
+}
+
+/// ZMK `.keymap` devicetree file that types `genome` on the keys of `board`.
+///
+/// The bindings expect the host to be set to US English, characters that shift types
+/// on another key than in US English become mod-morph behaviors.
+/// ZMK can not type characters outside of US English.
+pub fn export_zmk<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+    name: &str,
+    board: &Board,
+) -> Result<String, ExportError> {
+    let rows = firmware_keys(layout, genome, board, Firmware::Zmk)?;
+
+    let mut behaviors = Vec::new();
+    let keymap = rows
+        .iter()
+        .map(|row| {
+            let bindings: Vec<String> = row
+                .iter()
+                .map(|key| match &key.shifted {
+                    Some(shifted) => {
+                        let label = format!("{}_morph", key.name.to_lowercase());
+                        behaviors.push(format!(
+                            "        {label}: {label} {{\n            \
+                                 compatible = \"zmk,behavior-mod-morph\";\n            \
+                                 #binding-cells = <0>;\n            \
+                                 bindings = <{}>, <{shifted}>;\n            \
+                                 mods = <(MOD_LSFT|MOD_RSFT)>;\n        \
+                             }};\n",
+                            key.binding
+                        ));
+                        format!("&{label}")
+                    }
+                    None => key.binding.clone(),
+                })
+                .collect();
+            format!("                {}", bindings.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut content = format!("// {name} on {}, generated by keyboards\n", board.name);
+    content.push_str("#include <behaviors.dtsi>\n#include <dt-bindings/zmk/keys.h>\n\n/ {\n");
+    if !behaviors.is_empty() {
+        content.push_str(&format!(
+            "    behaviors {{\n{}    }};\n\n",
+            behaviors.join("\n")
+        ));
+    }
+    content.push_str(&format!(
+        "    keymap {{\n        \
+             compatible = \"zmk,keymap\";\n\n        \
+             default_layer {{\n            \
+                 bindings = <\n\
+         {keymap}\n            \
+                 >;\n        \
+             }};\n    \
+         }};\n\
+         }};\n"
+    ));
+
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::custom_layout::CustomLayout;
+    use crate::models::genome::GetGenome;
+    use crate::models::layout::{QwertyEnUs, QwertzDeDe};
+
+    fn load_board(file: &str) -> Board {
+        Board::from_file(format!("resources/boards/{file}")).expect("should load board")
+    }
+
+    /// Character typed by a keycode or binding and the character it types with shift
+    fn parse_binding(firmware: Firmware, binding: &str) -> (char, Option<char>) {
+        if let Some(hex) = binding
+            .strip_prefix("UC(0x")
+            .and_then(|hex| hex.strip_suffix(')'))
+        {
+            let c = u32::from_str_radix(hex, 16).expect("hex code point");
+            return (char::from_u32(c).expect("character"), None);
+        }
+
+        let c = (0..0x80u32)
+            .filter_map(char::from_u32)
+            .find(|c| firmware.binding(*c).as_deref() == Ok(binding))
+            .unwrap_or_else(|| panic!("unknown binding {binding}"));
+        let key = keycode(c).expect("US character");
+        let shifted = (0..0x80u32).filter_map(char::from_u32).find(|other| {
+            keycode(*other).is_some_and(|other| other.key == key.key && other.shifted)
+        });
+
+        (c, shifted.filter(|_| !key.shifted))
+    }
+
+    /// Keycodes of the layout macro and the key overrides of a `keymap.c`
+    fn parse_qmk(keymap: &str) -> (Vec<Vec<String>>, BTreeMap<String, String>) {
+        let (_, macro_body) = keymap.split_once("[0] = ").expect("keymap");
+        let (_, macro_body) = macro_body.split_once("(\n").expect("layout macro");
+        let (macro_body, _) = macro_body.split_once("\n    )").expect("end of macro");
+        let rows = macro_body
+            .lines()
+            .map(|line| {
+                line.trim()
+                    .trim_end_matches(',')
+                    .split(", ")
+                    .map(str::to_string)
+                    .collect()
+            })
+            .collect();
+
+        let overrides = keymap
+            .lines()
+            .filter_map(|line| line.split_once("ko_make_basic(MOD_MASK_SHIFT, "))
+            .map(|(_, codes)| {
+                let (trigger, replacement) = codes.split_once(", ").expect("two keycodes");
+                let replacement = replacement.strip_suffix(");").expect("end of override");
+                (trigger.to_string(), replacement.to_string())
+            })
+            .collect();
+
+        (rows, overrides)
+    }
+
+    /// Bindings of the default layer and the two bindings of every mod-morph of a `.keymap`
+    fn parse_zmk(keymap: &str) -> (Vec<String>, BTreeMap<String, (String, String)>) {
+        let bindings = |line: &str| -> Vec<String> {
+            line.split('&')
+                .map(str::trim)
+                .filter(|binding| !binding.is_empty())
+                .map(|binding| format!("&{binding}"))
+                .collect()
+        };
+
+        let mut morphs = BTreeMap::new();
+        let mut label = None;
+        for line in keymap.lines().map(str::trim) {
+            if let Some((name, _)) = line.split_once("_morph: ") {
+                label = Some(format!("&{name}_morph"));
+            } else if let (Some(list), Some(name)) = (line.strip_prefix("bindings = <"), &label) {
+                let list = list.strip_suffix(">;").expect("end of bindings");
+                let [unshifted, shifted] =
+                    <[String; 2]>::try_from(bindings(&list.replace(['<', '>', ','], " ")))
+                        .expect("two bindings");
+                morphs.insert(name.clone(), (unshifted, shifted));
+                label = None;
+            }
+        }
+
+        let (_, layer) = keymap.split_once("default_layer").expect("layer");
+        let (_, layer) = layer.split_once("bindings = <\n").expect("layer bindings");
+        let (layer, _) = layer.split_once(">;").expect("end of layer");
+
+        (layer.lines().flat_map(bindings).collect(), morphs)
+    }
+
+    #[test]
+    fn test_qmk_keymap_follows_the_board() {
+        let layout = QwertyEnUs();
+        let board = load_board("ansi_60.toml");
+        let keymap =
+            export_qmk(&layout, layout.get_genome(), "qwerty", &board).expect("should export");
+        assert!(keymap.contains("[0] = LAYOUT_60_ansi(\n"));
+
+        let (rows, overrides) = parse_qmk(&keymap);
+        assert_eq!(rows.iter().map(Vec::len).sum::<usize>(), 61);
+        assert_eq!(rows[0][..3], ["KC_GRV", "KC_1", "KC_2"]);
+        assert_eq!(rows[0][13], "KC_BSPC");
+        assert_eq!(rows[2][..3], ["KC_CAPS", "KC_A", "KC_S"]);
+        assert_eq!(rows[3][0], "KC_LSFT");
+        assert_eq!(rows[3][10], "KC_SLSH");
+        assert_eq!(rows[4][3], "KC_SPC");
+        // qwerty has no key in the backslash position
+        assert_eq!(rows[1][13], "KC_NO");
+        assert!(overrides.is_empty());
+        assert!(!keymap.contains("UNICODE_ENABLE"));
+
+        let iso = load_board("iso_60.toml");
+        assert_eq!(
+            export_qmk(&QwertzDeDe(), QwertzDeDe().get_genome(), "qwertz", &board),
+            Err(ExportError::NotOnBoard("LSGT".to_string()))
+        );
+        let keymap = export_qmk(&QwertzDeDe(), QwertzDeDe().get_genome(), "qwertz", &iso)
+            .expect("should export");
+        assert!(keymap.contains("UNICODE_ENABLE = yes"));
+        let (rows, overrides) = parse_qmk(&keymap);
+        assert_eq!(rows[0][2], "KC_2");
+        assert_eq!(rows[0][11], "UC(0x00DF)");
+        assert_eq!(rows[1][6], "KC_Z");
+        assert_eq!(rows[3][1], "KC_LABK");
+        assert_eq!(overrides["KC_2"], "KC_DQUO");
+        assert_eq!(overrides["UC(0x00DF)"], "KC_QUES");
+        assert_eq!(overrides["KC_LABK"], "KC_RABK");
+        assert!(!overrides.contains_key("KC_Z") && !overrides.contains_key("KC_1"));
+    }
+
+    #[test]
+    fn test_zmk_keymap_is_parsed_back() {
+        // a layout that only uses US characters but pairs some of them differently
+        let definition = std::fs::read_to_string("resources/layouts/qwerty_en_us.toml")
+            .expect("should read layout")
+            .replace(r#""2" = ["2", "@"]"#, r#""2" = ["2", "\""]"#)
+            .replace(r#""'" = ["'", "\""]"#, r#""'" = ["'", "@"]"#);
+        let layout = CustomLayout::<46>::from_toml_str(&definition).expect("should load layout");
+        let board = load_board("ansi_60.toml");
+        let keymap =
+            export_zmk(&layout, layout.get_genome(), "swapped", &board).expect("should export");
+
+        let (bindings, morphs) = parse_zmk(&keymap);
+        assert_eq!(bindings.len(), 61);
+        assert_eq!(bindings[..3], ["&kp GRAVE", "&kp N1", "&ae02_morph"]);
+        assert_eq!(bindings[13], "&kp BSPC");
+        assert_eq!(bindings[27], "&none");
+        assert_eq!(bindings[57], "&kp RALT");
+        assert_eq!(morphs.len(), 2);
+        assert_eq!(
+            morphs["&ae02_morph"],
+            ("&kp N2".to_string(), "&kp DQT".to_string())
+        );
+        assert_eq!(
+            morphs["&ac11_morph"],
+            ("&kp SQT".to_string(), "&kp AT".to_string())
+        );
+
+        let letters: BTreeMap<String, [Option<char>; 2]> =
+            get_export_keys(&layout, layout.get_genome())
+                .expect("keys")
+                .into_iter()
+                .map(|key| {
+                    let name = key_name(key.row, key.column).expect("key name");
+                    (name, key.characters)
+                })
+                .collect();
+        for (name, binding) in board.keys.iter().flatten().zip(&bindings) {
+            let Some(characters) = letters.get(name) else {
+                continue;
+            };
+            let parsed = match morphs.get(binding) {
+                Some((unshifted, shifted)) => [
+                    Some(parse_binding(Firmware::Zmk, unshifted).0),
+                    Some(parse_binding(Firmware::Zmk, shifted).0),
+                ],
+                None => {
+                    let (c, shifted) = parse_binding(Firmware::Zmk, binding);
+                    [Some(c), shifted]
+                }
+            };
+            assert_eq!(parsed, *characters, "{name}");
+        }
+
+        assert_eq!(
+            export_zmk(
+                &QwertzDeDe(),
+                QwertzDeDe().get_genome(),
+                "qwertz",
+                &load_board("iso_60.toml")
+            ),
+            Err(ExportError::UnsupportedCharacter {
+                format: "ZMK",
+                character: '°'
+            })
+        );
+        let mut unknown = board.clone();
+        unknown.keys[4][6] = "hyper".to_string();
+        assert_eq!(
+            export_zmk(&QwertyEnUs(), QwertyEnUs().get_genome(), "qwerty", &unknown),
+            Err(ExportError::UnknownBoardKey("hyper".to_string()))
+        );
+        unknown.keys[3][1] = "|".to_string();
+        assert_eq!(
+            export_zmk(&QwertyEnUs(), QwertyEnUs().get_genome(), "qwerty", &unknown),
+            Err(ExportError::NotOnBoard("AB01".to_string()))
+        );
+    }
+}
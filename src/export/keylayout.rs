@@ -0,0 +1,353 @@
+use super::{get_compositions, get_export_keys, ExportError};
+use crate::models::layout::ILayout;
+
+/// Virtual key codes of macOS on ANSI keyboards, number row to bottom row.
+/// The number and bottom row start in column 0, the others in column 1.
+const KEY_CODES: [&[u8]; 4] = [
+    &[50, 18, 19, 20, 21, 23, 22, 26, 28, 25, 29, 27, 24],
+    &[12, 13, 14, 15, 17, 16, 32, 34, 31, 35, 33, 30],
+    &[0, 1, 2, 3, 5, 4, 38, 40, 37, 41, 39, 42],
+    &[10, 6, 7, 8, 9, 11, 45, 46, 43, 47, 44],
+];
+
+/// Key code of the space bar
+const SPACE: u8 = 49;
+
+/// Key code of the key in a row and column of a standard keyboard,
+/// on ISO keyboards the key left of `1` and the key next to left shift swap their codes
+fn key_code(row: usize, column: i32, iso: bool) -> Option<u8> {
+    match (row, column, iso) {
+        (0, 0, true) => return Some(10),
+        (3, 0, true) => return Some(50),
+        _ => {}
+    }
+    let first = match row {
+        1 | 2 => 1,
+        _ => 0,
+    };
+    let index = usize::try_from(column - first).ok()?;
+
+    KEY_CODES.get(row)?.get(index).copied()
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '"' => "&quot;".to_string(),
+        '\'' => "&apos;".to_string(),
+        c => c.to_string(),
+    }
+}
+
+/// State after a dead key
+fn state(dead_key: char) -> String {
+    format!("dead {:04X}", dead_key as u32)
+}
+
+/// Action of a character that a dead key can change
+fn action(c: char) -> String {
+    format!("key {:04X}", c as u32)
+}
+
+/// Apple keyboard layout (`.keylayout`) that types `genome` on the keys of `layout`.
+///
+/// Keys are mapped by their row and column to the key codes of a standard keyboard,
+/// key map 0 is unshifted and key map 1 is shifted. Dead keys switch to a state
+/// in which the letters of the dead key are composed, any other key ends it with the dead key.
+pub fn export_keylayout<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+    name: &str,
+) -> Result<String, ExportError> {
+    let export_keys = get_export_keys(layout, genome)?;
+    let iso = export_keys
+        .iter()
+        .any(|key| (key.row, key.column) == (3, 0));
+
+    let mut keys = Vec::new();
+    for key in export_keys {
+        let code = key_code(key.row, key.column, iso).ok_or(ExportError::MissingKey {
+            format: "keylayout",
+            row: key.row,
+            column: key.column,
+        })?;
+        keys.push((code, key.characters));
+    }
+    let dead_keys: Vec<char> = layout
+        .get_dead_keys()
+        .iter()
+        .copied()
+        .filter(|c| {
+            keys.iter()
+                .any(|(_, characters)| characters.contains(&Some(*c)))
+        })
+        .collect();
+
+    // every character that a dead key of the layout changes, space types the dead key
+    let mut composed: Vec<(char, Vec<(char, char)>)> = Vec::new();
+    if !dead_keys.is_empty() {
+        composed.push((' ', dead_keys.iter().map(|c| (*c, *c)).collect()));
+    }
+    for dead_key in &dead_keys {
+        for (letter, result) in get_compositions(*dead_key) {
+            match composed.iter_mut().find(|(c, _)| *c == letter) {
+                Some((_, results)) => results.push((*dead_key, result)),
+                None => composed.push((letter, vec![(*dead_key, result)])),
+            }
+        }
+    }
+    let is_composed = |c: char| composed.iter().any(|(letter, _)| *letter == c);
+
+    let id = -1 - name.chars().map(|c| c as i64).sum::<i64>() % 30_000;
+    let name: String = name.chars().map(escape).collect();
+
+    let mut lines = vec![
+        r#"<?xml version="1.1" encoding="UTF-8"?>"#.to_string(),
+        r#"<!DOCTYPE keyboard SYSTEM "file://localhost/System/Library/DTDs/KeyboardLayout.dtd">"#
+            .to_string(),
+        format!(r#"<keyboard group="126" id="{id}" name="{name}" maxout="1">"#),
+        "    <layouts>".to_string(),
+        r#"        <layout first="0" last="255" mapSet="keys" modifiers="modifiers"/>"#.to_string(),
+        "    </layouts>".to_string(),
+        r#"    <modifierMap id="modifiers" defaultIndex="0">"#.to_string(),
+        r#"        <keyMapSelect mapIndex="0">"#.to_string(),
+        r#"            <modifier keys="caps? command?"/>"#.to_string(),
+        "        </keyMapSelect>".to_string(),
+        r#"        <keyMapSelect mapIndex="1">"#.to_string(),
+        r#"            <modifier keys="anyShift caps? command?"/>"#.to_string(),
+        "        </keyMapSelect>".to_string(),
+        "    </modifierMap>".to_string(),
+        r#"    <keyMapSet id="keys">"#.to_string(),
+    ];
+    for shift in [0, 1] {
+        lines.push(format!(r#"        <keyMap index="{shift}">"#));
+        let key_codes = keys
+            .iter()
+            .filter_map(|(code, characters)| Some((*code, characters[shift]?)))
+            .chain([(SPACE, ' ')]);
+        for (code, c) in key_codes {
+            let target = match c {
+                c if dead_keys.contains(&c) => format!(r#"action="{}""#, action(c)),
+                c if is_composed(c) => format!(r#"action="{}""#, action(c)),
+                c => format!(r#"output="{}""#, escape(c)),
+            };
+            lines.push(format!(r#"            <key code="{code}" {target}/>"#));
+        }
+        lines.push("        </keyMap>".to_string());
+    }
+    lines.push("    </keyMapSet>".to_string());
+
+    lines.push("    <actions>".to_string());
+    for dead_key in &dead_keys {
+        lines.push(format!(r#"        <action id="{}">"#, action(*dead_key)));
+        lines.push(format!(
+            r#"            <when state="none" next="{}"/>"#,
+            state(*dead_key)
+        ));
+        lines.push("        </action>".to_string());
+    }
+    for (letter, results) in &composed {
+        if dead_keys.contains(letter) {
+            continue;
+        }
+        lines.push(format!(r#"        <action id="{}">"#, action(*letter)));
+        lines.push(format!(
+            r#"            <when state="none" output="{}"/>"#,
+            escape(*letter)
+        ));
+        for (dead_key, result) in results {
+            lines.push(format!(
+                r#"            <when state="{}" output="{}"/>"#,
+                state(*dead_key),
+                escape(*result)
+            ));
+        }
+        lines.push("        </action>".to_string());
+    }
+    lines.push("    </actions>".to_string());
+
+    lines.push("    <terminators>".to_string());
+    for dead_key in &dead_keys {
+        lines.push(format!(
+            r#"        <when state="{}" output="{}"/>"#,
+            state(*dead_key),
+            escape(*dead_key)
+        ));
+    }
+    lines.push("    </terminators>".to_string());
+    lines.push("</keyboard>".to_string());
+    lines.push(String::new());
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::models::genome::GetGenome;
+    use crate::models::key_maps::sorted_key_map_entries;
+    use crate::models::layout::{QwertyEnUs, QwertzDeDe};
+
+    /// What a key does: type a character or start a dead key
+    #[derive(Debug, Clone, PartialEq)]
+    enum Output {
+        Character(char),
+        DeadKey(char),
+    }
+
+    /// Keys of both key maps and the characters composed by every dead key
+    struct ParsedLayout {
+        keys: BTreeMap<(usize, u8), Output>,
+        compositions: BTreeMap<char, Vec<(char, char)>>,
+    }
+
+    /// Attributes of an XML tag with the entities replaced
+    fn attributes(tag: &str) -> BTreeMap<&str, String> {
+        let mut attributes = BTreeMap::new();
+        let mut rest = tag;
+        while let Some((name, value)) = rest.split_once("=\"") {
+            let name = name.split_whitespace().last().expect("attribute name");
+            let (value, next) = value.split_once('"').expect("closed attribute");
+            let value = value
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&");
+            attributes.insert(name, value);
+            rest = next;
+        }
+        attributes
+    }
+
+    fn single(value: &str) -> char {
+        let mut chars = value.chars();
+        let c = chars.next().expect("one character");
+        assert!(chars.next().is_none(), "{value} is more than one character");
+        c
+    }
+
+    fn parse_keylayout(xml: &str) -> ParsedLayout {
+        let mut key_actions = Vec::new();
+        // action id -> (state, output or next state)
+        let mut actions: BTreeMap<String, Vec<(String, String, bool)>> = BTreeMap::new();
+        let mut terminators = BTreeMap::new();
+        let mut key_map = 0;
+        let mut current_action = String::new();
+        let mut in_terminators = false;
+
+        for tag in xml.split('<').skip(1) {
+            let tag = tag.split('>').next().expect("closed tag");
+            let name = tag.split_whitespace().next().unwrap_or_default();
+            let values = attributes(tag);
+            match name {
+                "keyMap" => key_map = values["index"].parse().expect("index"),
+                "key" => key_actions.push((key_map, values["code"].parse().expect("code"), values)),
+                "action" => current_action = values["id"].clone(),
+                "terminators" => in_terminators = true,
+                "when" if in_terminators => {
+                    terminators.insert(values["state"].clone(), single(&values["output"]));
+                }
+                "when" => {
+                    let (result, next) = match values.get("output") {
+                        Some(output) => (output.clone(), false),
+                        None => (values["next"].clone(), true),
+                    };
+                    actions.entry(current_action.clone()).or_default().push((
+                        values["state"].clone(),
+                        result,
+                        next,
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        let mut compositions: BTreeMap<char, Vec<(char, char)>> = BTreeMap::new();
+        let mut keys = BTreeMap::new();
+        for (key_map, code, values) in key_actions {
+            let output = match (values.get("output"), values.get("action")) {
+                (Some(output), _) => Output::Character(single(output)),
+                (None, Some(action)) => {
+                    let mut output = None;
+                    for (state, result, next) in &actions[action] {
+                        match (state.as_str(), next) {
+                            ("none", true) => output = Some(Output::DeadKey(terminators[result])),
+                            ("none", false) => output = Some(Output::Character(single(result))),
+                            (state, _) => {
+                                let dead_key = terminators[state];
+                                let letter = actions[action]
+                                    .iter()
+                                    .find(|(state, _, _)| state == "none")
+                                    .map(|(_, letter, _)| single(letter))
+                                    .expect("output without a dead key");
+                                let composed = compositions.entry(dead_key).or_default();
+                                if !composed.contains(&(letter, single(result))) {
+                                    composed.push((letter, single(result)));
+                                }
+                            }
+                        }
+                    }
+                    output.expect("action has a start")
+                }
+                _ => panic!("key without output"),
+            };
+            keys.insert((key_map, code), output);
+        }
+
+        ParsedLayout { keys, compositions }
+    }
+
+    #[test]
+    fn test_dead_keys_are_parsed_back() {
+        let layout = QwertzDeDe();
+        let xml = export_keylayout(&layout, layout.get_genome(), "QWERTZ <de>").expect("export");
+        assert!(xml.contains(r#"name="QWERTZ &lt;de&gt;""#));
+        let parsed = parse_keylayout(&xml);
+
+        assert_eq!(parsed.keys[&(0, 24)], Output::DeadKey('´'));
+        assert_eq!(parsed.keys[&(1, 24)], Output::DeadKey('`'));
+        // kVK_ISO_Section left of 1 and code 50 next to left shift
+        assert_eq!(parsed.keys[&(0, 10)], Output::DeadKey('^'));
+        assert_eq!(parsed.keys[&(1, 10)], Output::Character('°'));
+        assert_eq!(parsed.keys[&(0, 50)], Output::Character('<'));
+        assert_eq!(parsed.keys[&(1, 50)], Output::Character('>'));
+        assert_eq!(parsed.keys[&(1, 42)], Output::Character('\''));
+        assert_eq!(parsed.keys[&(0, SPACE)], Output::Character(' '));
+        assert!(parsed.compositions[&'´'].contains(&('e', 'é')));
+        assert!(parsed.compositions[&'`'].contains(&(' ', '`')));
+        assert!(parsed.compositions[&'^'].contains(&('O', 'Ô')));
+
+        let mut parsed_characters: Vec<(char, bool)> = parsed
+            .keys
+            .iter()
+            .filter(|((_, code), _)| *code != SPACE)
+            .map(|((key_map, _), output)| match output {
+                Output::Character(c) | Output::DeadKey(c) => (*c, *key_map == 1),
+            })
+            .collect();
+        parsed_characters.sort();
+        let typed: Vec<(char, bool)> = sorted_key_map_entries(&layout)
+            .into_iter()
+            .map(|(c, (_, shift))| (c, shift))
+            .collect();
+        assert_eq!(parsed_characters, typed);
+    }
+
+    #[test]
+    fn test_layouts_without_dead_keys_only_have_outputs() {
+        let layout = QwertyEnUs();
+        let xml = export_keylayout(&layout, layout.get_genome(), "QWERTY").expect("export");
+        let parsed = parse_keylayout(&xml);
+
+        assert!(!xml.contains("action="));
+        assert!(parsed.compositions.is_empty());
+        assert_eq!(parsed.keys[&(0, 0)], Output::Character('a'));
+        assert_eq!(parsed.keys[&(1, 50)], Output::Character('~'));
+        assert_eq!(parsed.keys.len(), 2 * (46 + 1));
+    }
+}
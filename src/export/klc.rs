@@ -0,0 +1,397 @@
+use super::{get_compositions, get_export_keys, ExportError, ExportKey};
+use crate::models::layout::ILayout;
+
+/// Scan codes and virtual keys of US English, number row to bottom row.
+/// The number and bottom row start in column 0, the others in column 1.
+const KEYS: [&[(u8, &str)]; 4] = [
+    &[
+        (0x29, "OEM_3"),
+        (0x02, "1"),
+        (0x03, "2"),
+        (0x04, "3"),
+        (0x05, "4"),
+        (0x06, "5"),
+        (0x07, "6"),
+        (0x08, "7"),
+        (0x09, "8"),
+        (0x0a, "9"),
+        (0x0b, "0"),
+        (0x0c, "OEM_MINUS"),
+        (0x0d, "OEM_PLUS"),
+    ],
+    &[
+        (0x10, "Q"),
+        (0x11, "W"),
+        (0x12, "E"),
+        (0x13, "R"),
+        (0x14, "T"),
+        (0x15, "Y"),
+        (0x16, "U"),
+        (0x17, "I"),
+        (0x18, "O"),
+        (0x19, "P"),
+        (0x1a, "OEM_4"),
+        (0x1b, "OEM_6"),
+    ],
+    &[
+        (0x1e, "A"),
+        (0x1f, "S"),
+        (0x20, "D"),
+        (0x21, "F"),
+        (0x22, "G"),
+        (0x23, "H"),
+        (0x24, "J"),
+        (0x25, "K"),
+        (0x26, "L"),
+        (0x27, "OEM_1"),
+        (0x28, "OEM_7"),
+        (0x2b, "OEM_5"),
+    ],
+    &[
+        (0x56, "OEM_102"),
+        (0x2c, "Z"),
+        (0x2d, "X"),
+        (0x2e, "C"),
+        (0x2f, "V"),
+        (0x30, "B"),
+        (0x31, "N"),
+        (0x32, "M"),
+        (0x33, "OEM_COMMA"),
+        (0x34, "OEM_PERIOD"),
+        (0x35, "OEM_2"),
+    ],
+];
+
+/// Scan code and US virtual key of the key in a row and column of a standard keyboard
+fn scan_code(row: usize, column: i32) -> Option<(u8, &'static str)> {
+    let first = match row {
+        1 | 2 => 1,
+        _ => 0,
+    };
+    let index = usize::try_from(column - first).ok()?;
+
+    KEYS.get(row)?.get(index).copied()
+}
+
+/// Letters and digits are written as they are, other characters as UTF-16 hex,
+/// dead keys end with `@`
+fn klc_character(c: Option<char>, dead_keys: &[char]) -> String {
+    match c {
+        None => "-1".to_string(),
+        Some(c) if c.is_ascii_alphanumeric() => c.to_string(),
+        Some(c) if dead_keys.contains(&c) => format!("{:04x}@", c as u32),
+        Some(c) => format!("{:04x}", c as u32),
+    }
+}
+
+/// Virtual keys of letters and digits are named after their character, the other keys keep
+/// the virtual key of US English at their position if it is still free
+fn virtual_keys(keys: &[(ExportKey, u8, &'static str)]) -> Vec<String> {
+    let named: Vec<Option<String>> = keys
+        .iter()
+        .map(|(key, _, _)| {
+            key.characters[0]
+                .filter(char::is_ascii_alphanumeric)
+                .map(|c| c.to_ascii_uppercase().to_string())
+        })
+        .collect();
+    let is_named = |vk: &str| named.iter().flatten().any(|other| other == vk);
+
+    let mut virtual_keys: Vec<Option<String>> = keys
+        .iter()
+        .zip(&named)
+        .map(|((_, _, us), named)| match named {
+            Some(vk) => Some(vk.clone()),
+            None => (!is_named(us)).then(|| us.to_string()),
+        })
+        .collect();
+    // virtual keys of the positions on the layout, then OEM keys before letters and digits
+    let mut others: Vec<&str> = KEYS
+        .iter()
+        .flat_map(|row| row.iter())
+        .map(|(_, vk)| *vk)
+        .filter(|vk| !keys.iter().any(|(_, _, us)| us == vk))
+        .collect();
+    others.sort_by_key(|vk| !vk.starts_with("OEM"));
+    let free: Vec<&str> = keys
+        .iter()
+        .map(|(_, _, us)| *us)
+        .chain(others)
+        .filter(|vk| !virtual_keys.iter().flatten().any(|other| other == vk))
+        .collect();
+
+    let mut free = free.into_iter();
+    for vk in virtual_keys.iter_mut().filter(|vk| vk.is_none()) {
+        *vk = free.next().map(String::from);
+    }
+
+    virtual_keys
+        .into_iter()
+        .map(|vk| vk.expect("there are as many virtual keys as keys"))
+        .collect()
+}
+
+/// Microsoft Keyboard Layout Creator source that types `genome` on the keys of `layout`.
+///
+/// Keys are mapped by their row and column to the scan codes of a standard keyboard,
+/// both shift states are written and dead keys get a `DEADKEY` table.
+/// Lines end with CRLF, MKLC expects the file in UTF-16.
+pub fn export_klc<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+    name: &str,
+) -> Result<String, ExportError> {
+    let keys = get_export_keys(layout, genome)?
+        .into_iter()
+        .map(|key| {
+            let (code, vk) = scan_code(key.row, key.column).ok_or(ExportError::MissingKey {
+                format: "KLC",
+                row: key.row,
+                column: key.column,
+            })?;
+            Ok((key, code, vk))
+        })
+        .collect::<Result<Vec<_>, ExportError>>()?;
+    let dead_keys: Vec<char> = layout
+        .get_dead_keys()
+        .iter()
+        .copied()
+        .filter(|c| {
+            keys.iter()
+                .any(|(key, _, _)| key.characters.contains(&Some(*c)))
+        })
+        .collect();
+
+    let quoted = name.replace('"', "'");
+    let id: String = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(8)
+        .collect();
+    let id = if id.is_empty() { "keyboard" } else { &id };
+
+    let mut lines = vec![
+        format!("KBD\t{id}\t\"{quoted}\""),
+        String::new(),
+        "COPYRIGHT\t\"(c)\"".to_string(),
+        String::new(),
+        "COMPANY\t\"keyboards\"".to_string(),
+        String::new(),
+        "LOCALENAME\t\"en-US\"".to_string(),
+        String::new(),
+        "LOCALEID\t\"00000409\"".to_string(),
+        String::new(),
+        "VERSION\t1.0".to_string(),
+        String::new(),
+        "SHIFTSTATE".to_string(),
+        String::new(),
+        "0\t//Column 4".to_string(),
+        "1\t//Column 5 : Shft".to_string(),
+        String::new(),
+        "LAYOUT\t\t;an extra '@' at the end is a dead key".to_string(),
+        String::new(),
+        "//SC\tVK_\t\tCap\t0\t1".to_string(),
+        "//--\t----\t\t----\t----\t----".to_string(),
+        String::new(),
+    ];
+    for ((key, code, _), vk) in keys.iter().zip(virtual_keys(&keys)) {
+        let [unshifted, shifted] = key.characters;
+        let caps = match (unshifted, shifted) {
+            (Some(unshifted), Some(shifted)) => unshifted.to_uppercase().eq([shifted]),
+            _ => false,
+        };
+        let comment: String = key.characters.iter().flatten().collect();
+
+        lines.push(format!(
+            "{code:02x}\t{vk}\t\t{}\t{}\t{}\t// {comment}",
+            caps as u8,
+            klc_character(unshifted, &dead_keys),
+            klc_character(shifted, &dead_keys),
+        ));
+    }
+    lines.push("39\tSPACE\t\t0\t0020\t0020\t// space".to_string());
+    lines.push(String::new());
+
+    for dead_key in &dead_keys {
+        lines.push(format!("DEADKEY\t{:04x}", *dead_key as u32));
+        lines.push(String::new());
+        for (letter, composed) in get_compositions(*dead_key) {
+            lines.push(format!(
+                "{:04x}\t{:04x}\t// {letter} -> {composed}",
+                letter as u32, composed as u32
+            ));
+        }
+        lines.push(format!(
+            "0020\t{:04x}\t//   -> {dead_key}",
+            *dead_key as u32
+        ));
+        lines.push(String::new());
+    }
+
+    lines.extend([
+        "KEYNAME".to_string(),
+        String::new(),
+        "01\tEsc".to_string(),
+        "0e\tBackspace".to_string(),
+        "0f\tTab".to_string(),
+        "1c\tEnter".to_string(),
+        "1d\tCtrl".to_string(),
+        "2a\tShift".to_string(),
+        "36\t\"Right Shift\"".to_string(),
+        "38\tAlt".to_string(),
+        "39\tSpace".to_string(),
+        "3a\t\"Caps Lock\"".to_string(),
+        String::new(),
+    ]);
+    if !dead_keys.is_empty() {
+        lines.push("KEYNAME_DEAD".to_string());
+        lines.push(String::new());
+        for dead_key in &dead_keys {
+            lines.push(format!("{:04x}\t\"{dead_key}\"", *dead_key as u32));
+        }
+        lines.push(String::new());
+    }
+    lines.extend([
+        "DESCRIPTIONS".to_string(),
+        String::new(),
+        format!("0409\t{quoted}"),
+        String::new(),
+        "LANGUAGENAMES".to_string(),
+        String::new(),
+        "0409\tEnglish (United States)".to_string(),
+        String::new(),
+        "ENDKBD".to_string(),
+        String::new(),
+    ]);
+
+    Ok(lines.join("\r\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::models::genome::GetGenome;
+    use crate::models::key_maps::sorted_key_map_entries;
+    use crate::models::layout::{QwertyEnUs, QwertzDeDe};
+
+    /// Virtual key and characters with their dead key flag of every scan code
+    type ParsedLayout = BTreeMap<u8, (String, Vec<Option<(char, bool)>>)>;
+
+    fn parse_character(value: &str) -> Option<(char, bool)> {
+        if value == "-1" {
+            return None;
+        }
+        let (value, dead) = match value.strip_suffix('@') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+        let c = match value.chars().count() {
+            1 => value.chars().next(),
+            _ => char::from_u32(u32::from_str_radix(value, 16).expect("hex character")),
+        };
+
+        Some((c.expect("valid character"), dead))
+    }
+
+    /// Keys of the `LAYOUT` section and the `DEADKEY` tables
+    fn parse_klc(klc: &str) -> (ParsedLayout, BTreeMap<char, Vec<(char, char)>>) {
+        let mut layout = BTreeMap::new();
+        let mut dead_keys: BTreeMap<char, Vec<(char, char)>> = BTreeMap::new();
+        let mut section = "";
+        let mut dead_key = None;
+
+        for line in klc.lines() {
+            let line = line.split("//").next().unwrap_or_default().trim();
+            let fields: Vec<&str> = line.split('\t').filter(|f| !f.is_empty()).collect();
+            match fields.as_slice() {
+                [] => {}
+                ["LAYOUT", ..] | ["KEYNAME", ..] | ["KEYNAME_DEAD", ..] | ["DESCRIPTIONS", ..] => {
+                    section = fields[0];
+                }
+                ["DEADKEY", key] => {
+                    section = "DEADKEY";
+                    dead_key = parse_character(key).map(|(c, _)| c);
+                }
+                [code, vk, _, characters @ ..] if section == "LAYOUT" => {
+                    let code = u8::from_str_radix(code, 16).expect("scan code");
+                    let characters = characters.iter().map(|c| parse_character(c)).collect();
+                    layout.insert(code, (vk.to_string(), characters));
+                }
+                [letter, composed] if section == "DEADKEY" => {
+                    let [letter, composed] = [letter, composed]
+                        .map(|c| parse_character(c).expect("composed character").0);
+                    let dead_key = dead_key.expect("dead key of the table");
+                    dead_keys
+                        .entry(dead_key)
+                        .or_default()
+                        .push((letter, composed));
+                }
+                _ => {}
+            }
+        }
+
+        (layout, dead_keys)
+    }
+
+    #[test]
+    fn test_dead_keys_are_parsed_back() {
+        let layout = QwertzDeDe();
+        let klc = export_klc(&layout, layout.get_genome(), "QWERTZ (de-DE)").expect("export");
+        assert!(klc.starts_with("KBD\tQWERTZde\t\"QWERTZ (de-DE)\"\r\n"));
+        let (keys, dead_keys) = parse_klc(&klc);
+
+        assert_eq!(keys[&0x0d].1, [Some(('´', true)), Some(('`', true))]);
+        assert_eq!(keys[&0x29].1, [Some(('^', true)), Some(('°', false))]);
+        assert_eq!(
+            keys[&0x1e],
+            (
+                "A".to_string(),
+                vec![Some(('a', false)), Some(('A', false))]
+            )
+        );
+        assert_eq!(keys[&0x56].1, [Some(('<', false)), Some(('>', false))]);
+        assert!(dead_keys[&'´'].contains(&('e', 'é')) && dead_keys[&'´'].contains(&(' ', '´')));
+        assert!(dead_keys[&'^'].contains(&('O', 'Ô')));
+        assert_eq!(dead_keys.len(), 3);
+
+        let mut parsed: Vec<(char, bool)> = keys
+            .iter()
+            .filter(|(code, _)| **code != 0x39)
+            .flat_map(|(_, (_, levels))| levels.iter().zip([false, true]))
+            .filter_map(|(c, shift)| Some(((*c)?.0, shift)))
+            .collect();
+        parsed.sort();
+        let typed: Vec<(char, bool)> = sorted_key_map_entries(&layout)
+            .into_iter()
+            .map(|(c, (_, shift))| (c, shift))
+            .collect();
+        assert_eq!(parsed, typed);
+    }
+
+    #[test]
+    fn test_virtual_keys_follow_the_letters() {
+        let layout = QwertyEnUs();
+        let mut genome = *layout.get_genome();
+        let [a, question] = ['A', '?'].map(|c| {
+            genome
+                .iter()
+                .position(|letter| *letter == c)
+                .expect("letter of the genome")
+        });
+        genome.swap(a, question);
+
+        let (keys, dead_keys) =
+            parse_klc(&export_klc(&layout, &genome, "swapped").expect("export"));
+        assert!(dead_keys.is_empty());
+        assert_eq!(keys[&0x35].0, "A");
+        assert_eq!(keys[&0x1e].0, "OEM_2");
+        assert_eq!(keys[&0x1e].1, [Some(('/', false)), Some(('?', false))]);
+
+        let mut virtual_keys: Vec<&String> = keys.values().map(|(vk, _)| vk).collect();
+        virtual_keys.sort();
+        virtual_keys.dedup();
+        assert_eq!(virtual_keys.len(), keys.len());
+    }
+}
@@ -0,0 +1,247 @@
+use std::path::Path;
+
+use super::{get_export_keys, key_name, ExportError};
+use crate::models::layout::ILayout;
+
+/// Keysym names of the ASCII characters that are not letters or digits
+const KEYSYMS: [(char, &str); 33] = [
+    (' ', "space"),
+    ('!', "exclam"),
+    ('"', "quotedbl"),
+    ('#', "numbersign"),
+    ('$', "dollar"),
+    ('%', "percent"),
+    ('&', "ampersand"),
+    ('\'', "apostrophe"),
+    ('(', "parenleft"),
+    (')', "parenright"),
+    ('*', "asterisk"),
+    ('+', "plus"),
+    (',', "comma"),
+    ('-', "minus"),
+    ('.', "period"),
+    ('/', "slash"),
+    (':', "colon"),
+    (';', "semicolon"),
+    ('<', "less"),
+    ('=', "equal"),
+    ('>', "greater"),
+    ('?', "question"),
+    ('@', "at"),
+    ('[', "bracketleft"),
+    ('\\', "backslash"),
+    (']', "bracketright"),
+    ('^', "asciicircum"),
+    ('_', "underscore"),
+    ('`', "grave"),
+    ('{', "braceleft"),
+    ('|', "bar"),
+    ('}', "braceright"),
+    ('~', "asciitilde"),
+];
+
+/// Keysyms of the dead keys that `get_compositions` knows
+const DEAD_KEYSYMS: [(char, &str); 6] = [
+    ('^', "dead_circumflex"),
+    ('´', "dead_acute"),
+    ('`', "dead_grave"),
+    ('ˇ', "dead_caron"),
+    ('˜', "dead_tilde"),
+    ('¸', "dead_cedilla"),
+];
+
+/// Named keysyms for ASCII and the dead keys of the layout, Unicode keysyms for everything else
+fn keysym(c: Option<char>, dead_keys: &[char]) -> String {
+    let Some(c) = c else {
+        return "NoSymbol".to_string();
+    };
+
+    let dead_keysym = DEAD_KEYSYMS
+        .iter()
+        .find(|(dead_key, _)| *dead_key == c && dead_keys.contains(&c));
+    if let Some((_, name)) = dead_keysym {
+        return name.to_string();
+    }
+
+    match KEYSYMS.iter().find(|(other, _)| *other == c) {
+        Some((_, name)) => name.to_string(),
+        None if c.is_ascii_alphanumeric() => c.to_string(),
+        None => format!("U{:04X}", c as u32),
+    }
+}
+
+/// XKB symbols file that types `genome` on the keys of `layout`.
+///
+/// Keys are mapped by their row and column to the key codes of a standard keyboard
+/// (`TLDE`, `AE01`…`AE12`, `AD01`…`AD12`, `AC01`…`AC11`, `BKSL`, `LSGT`, `AB01`…`AB10`),
+/// keys that the layout does not place keep the characters of `us(basic)`.
+/// The dead keys of the layout get `dead_*` keysyms.
+pub fn export_xkb<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+    name: &str,
+) -> Result<String, ExportError> {
+    let dead_keys = layout.get_dead_keys();
+    let mut lines = Vec::new();
+    for key in get_export_keys(layout, genome)? {
+        let code = key_name(key.row, key.column).ok_or(ExportError::MissingKey {
+            format: "XKB",
+            row: key.row,
+            column: key.column,
+        })?;
+        let [unshifted, shifted] = key.characters;
+
+        lines.push(match shifted {
+            Some(_) => format!(
+                "    key <{code}> {{ [ {}, {} ] }};",
+                keysym(unshifted, dead_keys),
+                keysym(shifted, dead_keys)
+            ),
+            None => format!(
+                "    key <{code}> {{ [ {} ] }};",
+                keysym(unshifted, dead_keys)
+            ),
+        });
+    }
+
+    Ok(format!(
+        "// {name}, generated by keyboards\n\
+         default partial alphanumeric_keys\n\
+         xkb_symbols \"basic\" {{\n    \
+             include \"us(basic)\"\n    \
+             name[Group1] = \"{}\";\n\n\
+         {}\n\
+         }};\n",
+        name.replace('"', "'"),
+        lines.join("\n")
+    ))
+}
+
+/// Shell commands that install a symbols file for the current user and activate it,
+/// the layout is named like the file
+pub fn setxkbmap_snippet(path: &Path) -> String {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+
+    format!(
+        "mkdir -p ~/.xkb/symbols\n\
+         cp {} ~/.xkb/symbols/{name}\n\
+         setxkbmap -I ~/.xkb -layout {name} -print | xkbcomp -I$HOME/.xkb - $DISPLAY\n",
+        path.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::models::genome::GetGenome;
+    use crate::models::geometry::{Geometry, GeometryLayout};
+    use crate::models::key_maps::sorted_key_map_entries;
+    use crate::models::layout::{Neo2DeDe, QwertyEnUs, QwertzDeDe};
+
+    /// Characters of every key code in a symbols file
+    fn parse_symbols(symbols: &str) -> BTreeMap<String, Vec<Option<char>>> {
+        let character = |name: &str| match name {
+            "NoSymbol" => None,
+            _ => Some(
+                KEYSYMS
+                    .iter()
+                    .chain(&DEAD_KEYSYMS)
+                    .find(|(_, keysym)| *keysym == name)
+                    .map(|(c, _)| *c)
+                    .or_else(|| {
+                        name.strip_prefix('U')
+                            .and_then(|hex| char::from_u32(u32::from_str_radix(hex, 16).ok()?))
+                    })
+                    .unwrap_or_else(|| {
+                        let mut chars = name.chars();
+                        let c = chars.next().expect("keysym should not be empty");
+                        assert!(chars.next().is_none(), "unknown keysym {name}");
+                        c
+                    }),
+            ),
+        };
+
+        symbols
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("key <"))
+            .map(|line| {
+                let (code, rest) = line.split_once('>').expect("key code");
+                let (_, levels) = rest.split_once('[').expect("levels");
+                let (levels, _) = levels.split_once(']').expect("levels");
+                let levels = levels.split(',').map(|name| character(name.trim()));
+
+                (code.to_string(), levels.collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_symbols_have_both_shift_levels() {
+        let layout = QwertyEnUs();
+        let mut genome = *layout.get_genome();
+        let [a, question] = ['A', '?'].map(|c| {
+            genome
+                .iter()
+                .position(|letter| *letter == c)
+                .expect("letter of the genome")
+        });
+        genome.swap(a, question);
+
+        let symbols = export_xkb(&layout, &genome, "swapped").expect("should export");
+        assert!(symbols.contains("key <TLDE> { [ grave, asciitilde ] };"));
+        let keys = parse_symbols(&symbols);
+        assert_eq!(keys.len(), 46);
+        assert_eq!(keys["AB10"], [Some('a'), Some('A')]);
+        assert_eq!(keys["AC01"], [Some('/'), Some('?')]);
+        assert_eq!(keys["AE01"], [Some('1'), Some('!')]);
+        assert!(!keys.contains_key("LSGT") && !keys.contains_key("BKSL"));
+
+        // the key codes follow the columns, not the positions of the keys
+        let split = GeometryLayout::new(QwertyEnUs(), Geometry::Split).expect("split layout");
+        assert_eq!(export_xkb(&split, &genome, "swapped"), Ok(symbols));
+    }
+
+    #[test]
+    fn test_every_character_is_parsed_back() {
+        let layout = QwertzDeDe();
+        let symbols = export_xkb(&layout, layout.get_genome(), "qwertz").expect("should export");
+        assert!(symbols.contains("key <TLDE> { [ dead_circumflex, U00B0 ] };"));
+        assert!(symbols.contains("key <AE12> { [ dead_acute, dead_grave ] };"));
+        let keys = parse_symbols(&symbols);
+        assert_eq!(keys["LSGT"], [Some('<'), Some('>')]);
+        assert_eq!(keys["BKSL"], [Some('#'), Some('\'')]);
+        assert_eq!(keys["AE11"], [Some('ß'), Some('?')]);
+        assert_eq!(keys["AD06"], [Some('z'), Some('Z')]);
+        assert_eq!(keys["TLDE"], [Some('^'), Some('°')]);
+        assert_eq!(keys["AE12"], [Some('´'), Some('`')]);
+
+        let mut parsed: Vec<(char, bool)> = keys
+            .values()
+            .flat_map(|levels| levels.iter().zip([false, true]))
+            .filter_map(|(c, shift)| Some(((*c)?, shift)))
+            .collect();
+        parsed.sort();
+        let typed: Vec<(char, bool)> = sorted_key_map_entries(&layout)
+            .into_iter()
+            .map(|(c, (_, shift))| (c, shift))
+            .collect();
+        assert_eq!(parsed, typed);
+
+        let neo = export_xkb(&Neo2DeDe(), Neo2DeDe().get_genome(), "neo").expect("should export");
+        assert!(neo.contains("dead_caron") && !neo.contains("asciicircum"));
+        // without dead keys the accents stay ordinary characters
+        assert!(
+            export_xkb(&QwertyEnUs(), QwertyEnUs().get_genome(), "qwerty")
+                .expect("should export")
+                .contains("asciicircum")
+        );
+
+        assert!(setxkbmap_snippet(Path::new("results/qwertz.xkb"))
+            .contains("cp results/qwertz.xkb ~/.xkb/symbols/qwertz"));
+    }
+}
@@ -0,0 +1,870 @@
+use std::{fmt, path::Path};
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::config_file::{self, ConfigFileError};
+use crate::models::custom_layout::CustomLayout;
+use crate::models::geometry::GeometryError;
+use crate::models::key_maps::get_key_characters;
+use crate::models::layout::ILayout;
+use crate::models::layout_map::{
+    get_default_fixed_key, FixedKey, FixedKeys, KeyboardKey, KeyboardRow, RawLayout,
+};
+
+/// Key of a keyboard-layout-editor layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct KleKey {
+    /// center of the key in key units, y grows downwards like in the editor
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// degrees clockwise around the center
+    pub rotation: f64,
+    /// legends from the top left to the bottom right, empty positions are empty
+    pub labels: Vec<String>,
+    /// index of the row in the file
+    pub row: usize,
+}
+
+/// Keys of a keyboard-layout-editor raw JSON file
+#[derive(Debug, Clone, PartialEq)]
+pub struct KleKeyboard {
+    pub name: Option<String>,
+    pub keys: Vec<KleKey>,
+}
+
+#[derive(Debug)]
+pub enum KleError {
+    Io(std::io::Error),
+    Parse(String),
+    /// a row that is neither a list of keys nor the metadata at the start
+    InvalidRow(usize),
+    /// rotation properties are only allowed on the first key of a row
+    MisplacedRotation(usize),
+    WrongKeyCount {
+        expected: usize,
+        found: usize,
+    },
+    Assignment(ConfigFileError),
+    InvalidAssignment(usize),
+    /// the heuristic needs a home row with at least 10 letter keys
+    NoHomeRow,
+    MissingFixedKey(&'static str),
+}
+
+impl fmt::Display for KleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read file: {err}"),
+            Self::Parse(err) => write!(f, "failed to parse KLE JSON: {err}"),
+            Self::InvalidRow(row) => write!(f, "row {row} is not a list of keys"),
+            Self::MisplacedRotation(row) => {
+                write!(
+                    f,
+                    "row {row} sets a rotation after the first key of the row"
+                )
+            }
+            Self::WrongKeyCount { expected, found } => write!(
+                f,
+                "the layout has {expected} keys, the keyboard has {found} keys with characters"
+            ),
+            Self::Assignment(err) => write!(f, "assignment file: {err}"),
+            Self::InvalidAssignment(key) => {
+                write!(
+                    f,
+                    "key {key} of the assignment has an invalid row or finger"
+                )
+            }
+            Self::NoHomeRow => write!(
+                f,
+                "no home row with at least 10 keys, assign the fingers with an assignment file"
+            ),
+            Self::MissingFixedKey(name) => {
+                write!(f, "fixed key '{name}' is missing and its row as well")
+            }
+        }
+    }
+}
+
+impl std::error::Error for KleError {}
+
+/// Position of the next key while the rows of a file are read
+#[derive(Debug, Default)]
+struct Cursor {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    rotation: f64,
+    rotation_x: f64,
+    rotation_y: f64,
+}
+
+impl Cursor {
+    fn apply(
+        &mut self,
+        properties: &Map<String, Value>,
+        first: bool,
+        row: usize,
+    ) -> Result<(), KleError> {
+        let number = |name: &str| properties.get(name).and_then(Value::as_f64);
+        let rotation = ["r", "rx", "ry"].iter().any(|name| number(name).is_some());
+        if rotation && !first {
+            return Err(KleError::MisplacedRotation(row));
+        }
+
+        if let Some(angle) = number("r") {
+            self.rotation = angle;
+        }
+        // a new rotation origin starts a new cluster of keys at the origin
+        if let Some(x) = number("rx") {
+            self.rotation_x = x;
+            self.x = self.rotation_x;
+            self.y = self.rotation_y;
+        }
+        if let Some(y) = number("ry") {
+            self.rotation_y = y;
+            self.x = self.rotation_x;
+            self.y = self.rotation_y;
+        }
+        if let Some(x) = number("x") {
+            self.x += x;
+        }
+        if let Some(y) = number("y") {
+            self.y += y;
+        }
+        if let Some(width) = number("w") {
+            self.width = width;
+        }
+        if let Some(height) = number("h") {
+            self.height = height;
+        }
+
+        Ok(())
+    }
+
+    /// Key at the cursor, the cursor moves to the right of it
+    fn key(&mut self, labels: &str, row: usize) -> KleKey {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let dx = self.x + self.width / 2. - self.rotation_x;
+        let dy = self.y + self.height / 2. - self.rotation_y;
+
+        let key = KleKey {
+            x: self.rotation_x + dx * cos - dy * sin,
+            y: self.rotation_y + dx * sin + dy * cos,
+            width: self.width,
+            height: self.height,
+            rotation: self.rotation,
+            labels: labels.split('\n').map(String::from).collect(),
+            row,
+        };
+
+        self.x += self.width;
+        self.width = 1.;
+        self.height = 1.;
+        key
+    }
+}
+
+impl KleKeyboard {
+    /// Read the raw data of keyboard-layout-editor.com as downloaded JSON
+    pub fn from_json_str(json: &str) -> Result<Self, KleError> {
+        let rows: Vec<Value> =
+            serde_json::from_str(json).map_err(|err| KleError::Parse(err.to_string()))?;
+
+        let mut name = None;
+        let mut keys = Vec::new();
+        let mut cursor = Cursor {
+            width: 1.,
+            height: 1.,
+            ..Default::default()
+        };
+
+        for (row, items) in rows.iter().enumerate() {
+            match items {
+                Value::Object(metadata) if row == 0 => {
+                    name = metadata
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .map(String::from);
+                }
+                Value::Array(items) => {
+                    for (i, item) in items.iter().enumerate() {
+                        match item {
+                            Value::String(labels) => keys.push(cursor.key(labels, row)),
+                            Value::Object(properties) => cursor.apply(properties, i == 0, row)?,
+                            _ => return Err(KleError::InvalidRow(row)),
+                        }
+                    }
+
+                    cursor.y += 1.;
+                    cursor.x = cursor.rotation_x;
+                }
+                _ => return Err(KleError::InvalidRow(row)),
+            }
+        }
+
+        Ok(Self { name, keys })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, KleError> {
+        let content = std::fs::read_to_string(path).map_err(KleError::Io)?;
+        Self::from_json_str(&content)
+    }
+}
+
+/// What a key of a KLE file is used for, found by its legends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Letter,
+    Shift,
+    Space,
+    Tab,
+    Enter,
+    Backspace,
+    /// modifiers, function keys and everything else that is not modeled
+    Other,
+}
+
+impl KleKey {
+    fn legends(&self) -> impl Iterator<Item = &str> {
+        self.labels
+            .iter()
+            .map(|label| label.trim())
+            .filter(|label| !label.is_empty())
+    }
+
+    fn role(&self) -> Role {
+        let legends: Vec<String> = self.legends().map(str::to_lowercase).collect();
+        if legends.is_empty() {
+            return match (self.width, self.height) {
+                (width, _) if width >= 2. => Role::Space,
+                (width, height) if width <= 1. && height <= 1. => Role::Letter,
+                _ => Role::Other,
+            };
+        }
+
+        for legend in &legends {
+            match legend.as_str() {
+                "shift" | "⇧" => return Role::Shift,
+                "space" | "spc" | "␣" => return Role::Space,
+                "tab" | "⇥" | "↹" => return Role::Tab,
+                "enter" | "return" | "ent" | "↵" | "⏎" | "⌤" => return Role::Enter,
+                "backspace" | "bksp" | "bspc" | "⌫" => return Role::Backspace,
+                _ => {}
+            }
+        }
+
+        match legends.iter().all(|legend| legend.chars().count() == 1) {
+            true => Role::Letter,
+            false => Role::Other,
+        }
+    }
+}
+
+/// Row, finger and home key of the keys with characters, in file order
+#[derive(Debug, Deserialize)]
+struct Assignment {
+    keys: Vec<KeyAssignment>,
+}
+
+/// Numbered like in layout definition files
+#[derive(Debug, Deserialize)]
+struct KeyAssignment {
+    row: i32,
+    finger: i32,
+    home: bool,
+}
+
+/// Rows from the number row down, further rows with characters are thumb rows
+fn letter_rows(keys: &[&KleKey]) -> Vec<i32> {
+    let mut file_rows: Vec<usize> = keys.iter().map(|key| key.row).collect();
+    file_rows.dedup();
+    let first = 5 - file_rows.len().min(4) as i32;
+
+    keys.iter()
+        .map(|key| {
+            let index = file_rows
+                .iter()
+                .position(|row| *row == key.row)
+                .unwrap_or(0) as i32;
+            (first + index).min(5)
+        })
+        .collect()
+}
+
+/// Every key gets the finger of the nearest home key, ties go to the left one.
+/// The first four keys of the home row are the left home keys, the right ones follow
+/// after the two keys in the middle.
+fn assign_fingers(keys: &[&KleKey]) -> Result<Vec<KeyAssignment>, KleError> {
+    let rows = letter_rows(keys);
+
+    let mut home_row: Vec<usize> = (0..keys.len()).filter(|i| rows[*i] == 3).collect();
+    if home_row.len() < 10 {
+        return Err(KleError::NoHomeRow);
+    }
+    home_row.sort_by(|a, b| keys[*a].x.total_cmp(&keys[*b].x));
+    let home_keys: Vec<(usize, i32)> = home_row[..4]
+        .iter()
+        .chain(home_row[6..10].iter())
+        .copied()
+        .zip(1..)
+        .collect();
+    let split = (keys[home_keys[3].0].x + keys[home_keys[4].0].x) / 2.;
+
+    let assignments = (0..keys.len())
+        .map(|i| {
+            let key = keys[i];
+            let finger = match rows[i] {
+                5 if key.x < split => 9,
+                5 => 10,
+                _ => {
+                    let mut nearest = home_keys[0];
+                    for home_key in &home_keys[1..] {
+                        let distance = |(index, _): (usize, i32)| (keys[index].x - key.x).abs();
+                        if distance(*home_key) < distance(nearest) {
+                            nearest = *home_key;
+                        }
+                    }
+                    nearest.1
+                }
+            };
+
+            KeyAssignment {
+                row: rows[i],
+                finger,
+                home: home_keys.iter().any(|(index, _)| *index == i),
+            }
+        })
+        .collect();
+
+    Ok(assignments)
+}
+
+fn read_assignment(path: &Path, count: usize) -> Result<Vec<KeyAssignment>, KleError> {
+    let assignment: Assignment =
+        config_file::read_config_file(path).map_err(KleError::Assignment)?;
+
+    if assignment.keys.len() != count {
+        return Err(KleError::WrongKeyCount {
+            expected: count,
+            found: assignment.keys.len(),
+        });
+    }
+    if let Some(index) = assignment
+        .keys
+        .iter()
+        .position(|key| !(1..=5).contains(&key.row) || !(1..=10).contains(&key.finger))
+    {
+        return Err(KleError::InvalidAssignment(index));
+    }
+
+    Ok(assignment.keys)
+}
+
+/// Genome printed on the keys, if their legends are the letters of the layout
+fn legend_genome<L: ILayout<N>, const N: usize>(layout: &L, keys: &[&KleKey]) -> Option<[char; N]> {
+    let letter_list = layout.get_letter_list();
+    let genome: Vec<char> = keys
+        .iter()
+        .map(|key| {
+            key.legends()
+                .filter_map(|legend| legend.chars().next())
+                .find_map(|c| layout.get_key_map(c))
+                .map(|(index, _)| letter_list[index - 1])
+        })
+        .collect::<Option<_>>()?;
+
+    let unique = letter_list.iter().all(|letter| genome.contains(letter));
+    unique.then(|| genome.try_into().ok()).flatten()
+}
+
+/// Shift, space, tab, enter and backspace from the keys with their legends.
+/// Keys below the bottom row are pressed with the thumbs, the others with the pinkies.
+fn fixed_keys<const N: usize>(
+    keyboard: &KleKeyboard,
+    layout_map: &[KeyboardKey; N],
+    to_key: impl Fn(&KleKey) -> KeyboardKey,
+) -> Result<FixedKeys, KleError> {
+    let home_x = |finger: i32| {
+        layout_map
+            .iter()
+            .find(|key| key.home && key.get_finger_number() == finger)
+            .map(|key| key.x)
+    };
+    let split = match (home_x(4), home_x(5)) {
+        (Some(left), Some(right)) => (left + right) / 2.,
+        _ => layout_map.iter().map(|key| key.x).sum::<f64>() / N as f64,
+    };
+    let bottom = layout_map
+        .iter()
+        .filter(|key| key.row == KeyboardRow::BottomLetter)
+        .map(|key| key.y)
+        .fold(f64::INFINITY, f64::min);
+    let nearest_row = |y: f64| {
+        layout_map
+            .iter()
+            .filter(|key| key.row != KeyboardRow::Thumb)
+            .min_by(|a, b| (a.y - y).abs().total_cmp(&(b.y - y).abs()))
+            .map_or(3, |key| key.row as i32 + 1)
+    };
+
+    let place = |key: &KleKey, thumb: bool| -> KeyboardKey {
+        let shape = to_key(key);
+        let left = shape.x < split;
+        let raw: RawLayout = match (thumb || shape.y < bottom - 0.5, left) {
+            (true, true) => (shape.x, shape.y, 5, 9, thumb),
+            (true, false) => (shape.x, shape.y, 5, 10, thumb),
+            (false, true) => (shape.x, shape.y, nearest_row(shape.y), 1, false),
+            (false, false) => (shape.x, shape.y, nearest_row(shape.y), 8, false),
+        };
+
+        KeyboardKey::from(raw)
+            .with_size(shape.width, shape.height)
+            .with_rotation(shape.rotation)
+    };
+
+    let by_role = |role: Role| {
+        let mut keys: Vec<&KleKey> = keyboard
+            .keys
+            .iter()
+            .filter(|key| key.role() == role)
+            .collect();
+        keys.sort_by(|a, b| a.x.total_cmp(&b.x));
+        keys
+    };
+    let shifts = by_role(Role::Shift);
+    let spaces = by_role(Role::Space);
+    let first = |role| by_role(role).first().map(|key| place(key, false));
+
+    let mut placed: [Option<KeyboardKey>; 7] = [None; 7];
+    placed[FixedKey::LeftShift as usize] = shifts
+        .first()
+        .map(|key| place(key, false))
+        .filter(|key| key.x < split);
+    placed[FixedKey::RightShift as usize] = shifts
+        .last()
+        .map(|key| place(key, false))
+        .filter(|key| key.x >= split);
+    match spaces.as_slice() {
+        [] => {}
+        // a space bar is pressed left or right of its center
+        [space] => {
+            let key = place(space, true);
+            let half = key.width / 2.;
+            placed[FixedKey::LeftSpace as usize] = Some(KeyboardKey {
+                x: key.x - half / 2.,
+                width: half,
+                ..KeyboardKey::from((key.x, key.y, 5, 9, true))
+            });
+            placed[FixedKey::RightSpace as usize] = Some(KeyboardKey {
+                x: key.x + half / 2.,
+                width: half,
+                ..KeyboardKey::from((key.x, key.y, 5, 10, true))
+            });
+        }
+        [left, .., right] => {
+            placed[FixedKey::LeftSpace as usize] = Some(place(left, true));
+            placed[FixedKey::RightSpace as usize] = Some(place(right, true));
+        }
+    }
+    placed[FixedKey::Tab as usize] = first(Role::Tab);
+    placed[FixedKey::Enter as usize] = first(Role::Enter);
+    placed[FixedKey::Backspace as usize] = first(Role::Backspace);
+
+    let mut keys = Vec::new();
+    for fixed_key in FixedKey::ALL {
+        let key = match placed[fixed_key as usize] {
+            Some(key) => key,
+            None => get_default_fixed_key(layout_map, fixed_key)
+                .ok_or(KleError::MissingFixedKey(fixed_key.name()))?,
+        };
+        keys.push(key);
+    }
+
+    Ok(FixedKeys(
+        keys.try_into().expect("all fixed keys were placed"),
+    ))
+}
+
+/// Place the characters of `layout` on a keyboard from keyboard-layout-editor.
+///
+/// Keys with single character legends (or without legends) are the keys of the layout map,
+/// in file order; if there are too many, keys with characters the layout does not type are
+/// left out. Their row, finger and home keys are guessed from the home row unless an
+/// assignment file lists them. The genome is read from the legends if they are the letters
+/// of the layout, otherwise the baseline genome of the layout is used.
+pub fn import_kle<L: ILayout<N>, const N: usize>(
+    keyboard: &KleKeyboard,
+    layout: &L,
+    assignment: Option<&Path>,
+) -> Result<CustomLayout<N>, KleError> {
+    let mut letter_keys: Vec<&KleKey> = keyboard
+        .keys
+        .iter()
+        .filter(|key| key.role() == Role::Letter)
+        .collect();
+    if letter_keys.len() > N {
+        // keys like `\` on a board for a layout without them
+        letter_keys.retain(|key| {
+            let mut legends = key.legends().peekable();
+            legends.peek().is_none()
+                || legends.any(|legend| legend.chars().any(|c| layout.get_key_map(c).is_some()))
+        });
+    }
+    if letter_keys.len() != N {
+        return Err(KleError::WrongKeyCount {
+            expected: N,
+            found: letter_keys.len(),
+        });
+    }
+
+    let assignments = match assignment {
+        Some(path) => read_assignment(path, N)?,
+        None => assign_fingers(&letter_keys)?,
+    };
+
+    // the bottom edge of the lowest key is at y = 0
+    let bottom = keyboard
+        .keys
+        .iter()
+        .map(|key| key.y + key.height / 2.)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let to_key = |key: &KleKey| {
+        KeyboardKey::from((key.x, bottom - key.y, 3, 1, false))
+            .with_size(key.width, key.height)
+            .with_rotation(key.rotation)
+    };
+
+    let layout_map: [KeyboardKey; N] = letter_keys
+        .iter()
+        .zip(assignments.iter())
+        .map(|(key, assignment)| {
+            let raw = (
+                key.x,
+                bottom - key.y,
+                assignment.row,
+                assignment.finger,
+                assignment.home,
+            );
+            KeyboardKey::from(raw)
+                .with_size(key.width, key.height)
+                .with_rotation(key.rotation)
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .expect("key count was checked");
+
+    let fixed_keys = fixed_keys(keyboard, &layout_map, to_key)?;
+    let genome = legend_genome(layout, &letter_keys).unwrap_or(*layout.get_genome());
+    let name = keyboard.name.as_deref().unwrap_or("KLE import");
+
+    Ok(CustomLayout::from_layout(
+        name, layout, genome, layout_map, fixed_keys,
+    ))
+}
+
+/// Legends of a letter key: the capital of a letter like on keycaps,
+/// otherwise the shifted character above the unshifted one
+fn legend(letter: char, [unshifted, shifted]: [Option<char>; 2]) -> String {
+    match (unshifted, shifted) {
+        (Some(unshifted), Some(shifted)) if unshifted.to_uppercase().eq([shifted]) => {
+            shifted.to_string()
+        }
+        (Some(unshifted), Some(shifted)) => format!("{shifted}\n{unshifted}"),
+        (Some(c), None) | (None, Some(c)) => c.to_string(),
+        (None, None) => letter.to_string(),
+    }
+}
+
+/// Positions in the editor are rounded, so that sums of key sizes stay readable
+fn round(value: f64) -> f64 {
+    (value * 10_000.).round() / 10_000.
+}
+
+/// Write `genome` on the keys of `layout` as keyboard-layout-editor raw data, with the fixed keys.
+///
+/// Keys are written row by row from the number row down. Rotated keys follow at the end,
+/// each in its own row around its center, because a rotation applies to all following rows.
+pub fn export_kle<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+    name: &str,
+) -> Result<String, GeometryError> {
+    let characters = get_key_characters(layout, genome);
+    let fixed_keys = layout.get_fixed_keys()?;
+
+    let mut keys: Vec<(KeyboardKey, String)> = layout
+        .get_layout_map()
+        .into_iter()
+        .zip(genome.iter().zip(characters))
+        .map(|(key, (letter, characters))| (key, legend(*letter, characters)))
+        .collect();
+    for fixed_key in FixedKey::ALL {
+        let legend = match fixed_key {
+            FixedKey::LeftShift | FixedKey::RightShift => "Shift",
+            FixedKey::LeftSpace | FixedKey::RightSpace => "Space",
+            FixedKey::Tab => "Tab",
+            FixedKey::Enter => "Enter",
+            FixedKey::Backspace => "Backspace",
+        };
+        keys.push((fixed_keys[fixed_key], legend.to_string()));
+    }
+
+    // the editor starts at the top left corner, its y grows downwards
+    let left = keys
+        .iter()
+        .map(|(key, _)| key.x - key.width / 2.)
+        .fold(f64::INFINITY, f64::min);
+    let top = keys
+        .iter()
+        .map(|(key, _)| key.y + key.height / 2.)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let key_top = |key: &KeyboardKey| round(top - key.y - key.height / 2.);
+    let key_left = |key: &KeyboardKey| round(key.x - key.width / 2. - left);
+    let size = |properties: &mut Map<String, Value>, key: &KeyboardKey| {
+        if key.width != 1. {
+            properties.insert("w".into(), json!(round(key.width)));
+        }
+        if key.height != 1. {
+            properties.insert("h".into(), json!(round(key.height)));
+        }
+        if key.home {
+            properties.insert("n".into(), json!(true));
+        }
+    };
+
+    let (mut rotated, mut straight): (Vec<_>, Vec<_>) =
+        keys.into_iter().partition(|(key, _)| key.rotation != 0.);
+    straight.sort_by(|(a, _), (b, _)| {
+        (key_top(a), key_left(a))
+            .partial_cmp(&(key_top(b), key_left(b)))
+            .expect("positions are finite")
+    });
+    rotated.sort_by(|(a, _), (b, _)| key_top(a).total_cmp(&key_top(b)));
+
+    let mut rows = vec![json!({ "name": name })];
+    let mut cursor_y = 0.;
+    for row in straight.chunk_by(|(a, _), (b, _)| key_top(a) == key_top(b)) {
+        let row_top = key_top(&row[0].0);
+        let mut items = Vec::new();
+        let mut cursor_x = 0.;
+        for (i, (key, legend)) in row.iter().enumerate() {
+            let mut properties = Map::new();
+            if i == 0 && row_top != cursor_y {
+                properties.insert("y".into(), json!(round(row_top - cursor_y)));
+            }
+            if key_left(key) != cursor_x {
+                properties.insert("x".into(), json!(round(key_left(key) - cursor_x)));
+            }
+            size(&mut properties, key);
+
+            if !properties.is_empty() {
+                items.push(Value::Object(properties));
+            }
+            items.push(json!(legend));
+            cursor_x = round(key_left(key) + key.width);
+        }
+        rows.push(Value::Array(items));
+        cursor_y = row_top + 1.;
+    }
+    for (key, legend) in rotated {
+        let mut properties = Map::new();
+        properties.insert("r".into(), json!(round(key.rotation)));
+        properties.insert("rx".into(), json!(round(key.x - left)));
+        properties.insert("ry".into(), json!(round(top - key.y)));
+        properties.insert("x".into(), json!(round(-key.width / 2.)));
+        properties.insert("y".into(), json!(round(-key.height / 2.)));
+        size(&mut properties, &key);
+        rows.push(json!([properties, legend]));
+    }
+
+    let rows: Vec<String> = rows.iter().map(Value::to_string).collect();
+    Ok(format!("[\n{}\n]\n", rows.join(",\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::genome::GetGenome;
+    use crate::models::key_maps::sorted_key_map_entries;
+    use crate::models::layout::{QwertyEnUs, QwertzDeDe};
+    use crate::models::layout_map::GetLayoutMap;
+    use crate::models::letter_list::GetLetterList;
+
+    fn load_ansi() -> KleKeyboard {
+        KleKeyboard::from_file("resources/kle/ansi_60.json").expect("should parse keyboard")
+    }
+
+    #[test]
+    fn test_keys_are_placed_like_in_the_editor() {
+        let keyboard = load_ansi();
+        assert_eq!(keyboard.name.as_deref(), Some("ANSI 60%"));
+        assert_eq!(keyboard.keys.len(), 61);
+
+        let backspace = &keyboard.keys[13];
+        assert_eq!((backspace.x, backspace.y, backspace.width), (14., 0.5, 2.));
+        let space = keyboard
+            .keys
+            .iter()
+            .find(|key| key.width == 6.25)
+            .expect("space bar");
+        assert_eq!((space.x, space.y, space.row), (6.875, 4.5, 5));
+
+        let rotated = KleKeyboard::from_json_str(r#"[[{"r": 90, "rx": 1, "ry": 2}, "A", "B"]]"#)
+            .expect("should parse keyboard");
+        let [a, b] = [&rotated.keys[0], &rotated.keys[1]];
+        assert!((a.x - 0.5).abs() < 1e-9 && (a.y - 2.5).abs() < 1e-9);
+        assert!((b.x - 0.5).abs() < 1e-9 && (b.y - 3.5).abs() < 1e-9);
+        assert_eq!(a.rotation, 90.);
+
+        assert!(matches!(
+            KleKeyboard::from_json_str(r#"[["A", {"r": 10}, "B"]]"#),
+            Err(KleError::MisplacedRotation(0))
+        ));
+    }
+
+    #[test]
+    fn test_ansi_board_is_the_traditional_keyboard() {
+        let layout = QwertyEnUs();
+        let imported = import_kle(&load_ansi(), &layout, None).expect("should import");
+        let traditional = layout.get_layout_map();
+
+        assert_eq!(imported.get_genome(), layout.get_genome());
+        for (key, expected) in imported.get_layout_map().iter().zip(traditional.iter()) {
+            assert_eq!(
+                (key.x, key.y, key.row),
+                (expected.x, expected.y, expected.row)
+            );
+            assert_eq!(key.home, expected.home);
+            // the number row is assigned diagonally on the traditional keyboard
+            if key.row != KeyboardRow::Number {
+                assert_eq!(key.get_finger_number(), expected.get_finger_number());
+            }
+        }
+
+        let fixed_keys = imported.get_fixed_keys().expect("fixed keys");
+        assert_eq!(fixed_keys[FixedKey::LeftShift].get_finger_number(), 1);
+        assert_eq!(fixed_keys[FixedKey::Enter].get_finger_number(), 8);
+        assert_eq!(fixed_keys[FixedKey::Backspace].width, 2.);
+        assert!(fixed_keys[FixedKey::LeftSpace].home && fixed_keys[FixedKey::RightSpace].home);
+        assert!(fixed_keys[FixedKey::LeftSpace].x < fixed_keys[FixedKey::RightSpace].x);
+
+        // an assignment file reproduces the traditional keyboard and saved layouts load again
+        let directory = std::env::temp_dir().join(format!("kle-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("should create directory");
+        let keys = traditional
+            .iter()
+            .map(|key| {
+                format!(
+                    "{{ row = {}, finger = {}, home = {} }}",
+                    key.row as i32 + 1,
+                    key.get_finger_number(),
+                    key.home
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let assignment = directory.join("assignment.toml");
+        std::fs::write(&assignment, format!("keys = [\n{keys}\n]\n")).expect("should write");
+
+        let imported = import_kle(&load_ansi(), &layout, Some(&assignment)).expect("should import");
+        assert_eq!(imported.get_layout_map(), traditional);
+
+        for file in ["layout.toml", "layout.json"] {
+            let path = directory.join(file);
+            imported.save(&path).expect("should save layout");
+            let loaded = CustomLayout::<46>::from_file(&path).expect("should load layout");
+
+            assert_eq!(loaded.get_layout_map(), imported.get_layout_map());
+            assert_eq!(
+                loaded.get_fixed_keys().expect("fixed keys"),
+                imported.get_fixed_keys().expect("fixed keys")
+            );
+            assert_eq!(loaded.get_genome(), imported.get_genome());
+            assert_eq!(
+                sorted_key_map_entries(&loaded),
+                sorted_key_map_entries(&layout)
+            );
+        }
+        std::fs::remove_dir_all(&directory).expect("should remove directory");
+    }
+
+    #[test]
+    fn test_exported_keyboard_imports_as_the_same_layout() {
+        let layout = QwertyEnUs();
+        let mut genome = *layout.get_letter_list();
+        genome.reverse();
+
+        let json = export_kle(&layout, &genome, "reversed").expect("should export");
+        let keyboard = KleKeyboard::from_json_str(&json).expect("should parse keyboard");
+        assert_eq!(keyboard.name.as_deref(), Some("reversed"));
+        assert_eq!(keyboard.keys.len(), 46 + 7);
+
+        let imported = import_kle(&keyboard, &layout, None).expect("should import");
+        assert_eq!(imported.get_genome(), &genome);
+        let layout_map = layout.get_layout_map();
+        let offset = |key: &KeyboardKey| (key.x - layout_map[0].x, key.y - layout_map[0].y);
+        let imported_map = imported.get_layout_map();
+        for (key, expected) in imported_map.iter().zip(layout_map.iter()) {
+            let (x, y) = (key.x - imported_map[0].x, key.y - imported_map[0].y);
+            assert!((x - offset(expected).0).abs() < 1e-9 && (y - offset(expected).1).abs() < 1e-9);
+            assert_eq!((key.width, key.home), (expected.width, expected.home));
+        }
+
+        // rotated keys keep their center
+        let [a, b] = ['A', 'B'].map(|c| {
+            genome
+                .iter()
+                .position(|letter| *letter == c)
+                .expect("letter of the genome")
+        });
+        let mut rotated_map = layout_map;
+        rotated_map[a] = rotated_map[a].with_rotation(30.);
+        let rotated = CustomLayout::from_layout(
+            "rotated",
+            &layout,
+            genome,
+            rotated_map,
+            layout.get_fixed_keys().expect("fixed keys"),
+        );
+        let keyboard = KleKeyboard::from_json_str(
+            &export_kle(&rotated, &genome, "rotated").expect("should export"),
+        )
+        .expect("should parse keyboard");
+        let center = |legend: &str| {
+            let key = keyboard
+                .keys
+                .iter()
+                .find(|key| key.labels == [legend])
+                .expect("key with legend");
+            (key.x, key.y, key.rotation)
+        };
+        let (x, y, rotation) = center("A");
+        let (other_x, other_y, _) = center("B");
+        assert_eq!(rotation, 30.);
+        assert!((x - other_x - (layout_map[a].x - layout_map[b].x)).abs() < 1e-9);
+        assert!((y - other_y + (layout_map[a].y - layout_map[b].y)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_legends_show_both_shift_levels() {
+        let layout = QwertzDeDe();
+        let json = export_kle(&layout, layout.get_genome(), "QWERTZ").expect("should export");
+        let keyboard = KleKeyboard::from_json_str(&json).expect("should parse keyboard");
+        let labels = |legend: &str| {
+            keyboard
+                .keys
+                .iter()
+                .find(|key| key.labels.iter().any(|label| label == legend))
+                .map(|key| key.labels.clone())
+        };
+
+        assert_eq!(labels("A"), Some(vec!["A".to_string()]));
+        assert_eq!(labels("ß"), Some(vec!["?".to_string(), "ß".to_string()]));
+        assert_eq!(labels("Ü"), Some(vec!["Ü".to_string()]));
+        assert!(labels("Shift").is_some() && labels("Space").is_some());
+    }
+}
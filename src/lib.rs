@@ -1,63 +1,256 @@
-pub(crate) mod advanced_setup;
+pub mod advanced_setup;
+pub(crate) mod analysis;
+pub(crate) mod checkpoint;
+pub(crate) mod config_file;
+mod delta;
 mod draw;
+pub(crate) mod export;
+pub(crate) mod kle;
 pub(crate) mod logging;
+pub(crate) mod metrics;
 pub(crate) mod models;
+mod ngrams;
 mod objective;
+pub(crate) mod optimizer;
 pub mod prelude;
 
-use advanced_setup::SEED;
+use std::{collections::BTreeSet, path::Path};
+
+use advanced_setup::EffortModel;
+use checkpoint::{Checkpoint, CheckpointError, CHECKPOINT_PATH};
+use delta::DeltaEvaluator;
 use draw::draw_keyboard;
 use logging::{append_to_file, append_updates};
-use models::genome::{create_genome, shuffle_genome};
+use logging::{LoggingOptions, SaveImageOption};
+use models::constraints::Constraints;
+use models::genome::create_genome;
+use models::geometry::GeometryError;
 use models::layout::ILayout;
-use objective::determine_keypress;
+use models::layout_map::{FixedKeys, KeyboardKey};
+use ngrams::NgramStats;
+use objective::{determine_keypress, get_key_presses};
+use optimizer::annealing::{anneal, SaState};
+use optimizer::{Objective, Optimizer};
 use rand::prelude::*;
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct SaSetup {
     pub temperature: f64,
     pub epoch: f64,
     pub cooling_rate: f64,
     pub num_iterations: usize,
+    /// every random decision of a run is derived from this seed
+    pub seed: u64,
 }
 
-// simulated annealing
-pub fn run_sa<L: ILayout<N>, const N: usize>(
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    /// raw objective, lower is better
+    pub absolute: f64,
+    /// + % worse and - % better than the baseline genome of the layout
+    pub relative: f64,
+}
+
+/// Evaluate a genome on a text
+pub fn score_genome<L: ILayout<N>, const N: usize>(
+    layout: &L,
     text: &str,
-    setup: SaSetup,
-    logging: logging::LoggingOptions,
-) -> [char; N] {
-    let SaSetup {
-        mut temperature,
-        epoch,
-        cooling_rate,
-        num_iterations,
-    } = setup;
+    genome: &[char; N],
+    effort_model: &EffortModel,
+) -> Result<Score, GeometryError> {
+    let layout_map = layout.get_layout_map();
+    let fixed_keys = layout.get_fixed_keys()?;
+    let letter_list = layout.get_letter_list();
+    let ngram_stats = NgramStats::new(&get_key_presses(layout, text), effort_model.whitespace);
+
+    let layout_score = objective::baseline_objective_function(
+        &ngram_stats,
+        layout.get_genome(),
+        &layout_map,
+        &fixed_keys,
+        letter_list,
+        effort_model,
+    );
+    let absolute = objective::baseline_objective_function(
+        &ngram_stats,
+        genome,
+        &layout_map,
+        &fixed_keys,
+        letter_list,
+        effort_model,
+    );
 
-    let mut rng = rand::rngs::StdRng::seed_from_u64(SEED);
+    Ok(Score {
+        absolute,
+        relative: (absolute / layout_score - 1.0) * 100.0,
+    })
+}
 
-    let genome = L::get_genome();
-    let layout_map = L::get_layout_map();
+/// Characters of a text that the layout can not type and that are left out of its score,
+/// like the layer 3 punctuation of Neo2, Bone and KOY
+pub fn untyped_characters<L: ILayout<N>, const N: usize>(layout: &L, text: &str) -> BTreeSet<char> {
+    text.chars()
+        .filter(|c| !c.is_control() && determine_keypress(layout, *c).is_none())
+        .collect()
+}
 
-    let mut letter_list = *L::get_letter_list();
+/// Save a genome as `results/{id}.png`
+pub fn draw_genome<L: ILayout<N>, const N: usize>(layout: &L, genome: &[char; N], id: &str) {
+    draw_keyboard(genome, id, &layout.get_layout_map());
+}
 
-    if logging.verbosity.is_normal() {
-        println!("Calculating raw baseline: ");
+/// Result of one annealing chain
+#[derive(Debug, Clone)]
+pub struct ChainResult<const N: usize> {
+    pub seed: u64,
+    pub genome: [char; N],
+    pub score: Score,
+}
+
+/// Result of `run_sa_parallel`, `chains` is ordered like the derived seeds
+#[derive(Debug, Clone)]
+pub struct ParallelSaResult<const N: usize> {
+    pub best: ChainResult<N>,
+    pub chains: Vec<ChainResult<N>>,
+}
+
+/// Everything the annealing chains of a layout and text share
+struct SaProblem<'a, const N: usize> {
+    ngram_stats: NgramStats,
+    layout_map: [KeyboardKey; N],
+    fixed_keys: FixedKeys,
+    letter_list: [char; N],
+    effort_model: &'a EffortModel,
+    constraints: &'a Constraints<N>,
+    layout_score: f64,
+}
+
+/// Annealing run of one seed between two iterations
+struct Chain<'p, const N: usize> {
+    seed: u64,
+    rng: ChaCha12Rng,
+    objective: Objective<'p, N>,
+    state: SaState<N>,
+}
+
+impl<'a, const N: usize> SaProblem<'a, N> {
+    fn new<L: ILayout<N>>(
+        layout: &L,
+        text: &str,
+        effort_model: &'a EffortModel,
+        constraints: &'a Constraints<N>,
+    ) -> Result<Self, GeometryError> {
+        let layout_map = layout.get_layout_map();
+        let fixed_keys = layout.get_fixed_keys()?;
+        let letter_list = *layout.get_letter_list();
+
+        let ngram_stats = NgramStats::new(&get_key_presses(layout, text), effort_model.whitespace);
+
+        let layout_score = objective::baseline_objective_function(
+            &ngram_stats,
+            layout.get_genome(),
+            &layout_map,
+            &fixed_keys,
+            &letter_list,
+            effort_model,
+        );
+
+        Ok(Self {
+            ngram_stats,
+            layout_map,
+            fixed_keys,
+            letter_list,
+            effort_model,
+            constraints,
+            layout_score,
+        })
     }
 
-    let key_presses: Vec<_> = text
-        .chars()
-        .filter_map(determine_keypress::<L, N>)
-        .collect();
+    fn score(&self, absolute: f64) -> Score {
+        Score {
+            absolute,
+            relative: (absolute / self.layout_score - 1.0) * 100.0,
+        }
+    }
 
-    let layout_score = objective::baseline_objective_function::<L, N>(
-        &key_presses,
-        genome,
-        &layout_map,
-        &letter_list,
-    );
+    fn objective(&self, genome: &[char; N]) -> Objective<'_, N> {
+        let evaluator = DeltaEvaluator::new(
+            &self.ngram_stats,
+            genome,
+            &self.layout_map,
+            &self.fixed_keys,
+            &self.letter_list,
+            self.effort_model,
+        );
+        Objective::new(evaluator, self.layout_score, self.constraints)
+    }
+
+    /// Objective of a random genome and the generator it was drawn from
+    fn random_start(&self, seed: u64) -> (Objective<'_, N>, ChaCha12Rng) {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let genome = create_genome(self.constraints, &mut rng);
+
+        (self.objective(&genome), rng)
+    }
+
+    /// New chain from a random genome
+    fn start(&self, setup: &SaSetup, seed: u64) -> Chain<'_, N> {
+        let (objective, rng) = self.random_start(seed);
+
+        Chain {
+            seed,
+            rng,
+            state: SaState::new(&objective, setup),
+            objective,
+        }
+    }
 
+    /// Continue an annealing chain until the temperature or the iterations of `setup` run out
+    fn anneal(&self, chain: &mut Chain<'_, N>, setup: &SaSetup, logging: &LoggingOptions) {
+        let Chain {
+            seed,
+            rng,
+            objective,
+            state,
+        } = chain;
+
+        anneal(
+            state,
+            objective,
+            rng,
+            setup,
+            logging,
+            |state, objective, rng| {
+                if let Some(every) = logging.checkpoint {
+                    if state.iteration % every.get() == 0 {
+                        Checkpoint::new(self, *seed, state, objective, rng, setup)
+                            .save(CHECKPOINT_PATH);
+                    }
+                }
+            },
+        );
+    }
+}
+
+fn draw_final<const N: usize>(
+    genome: &[char; N],
+    layout_map: &[KeyboardKey; N],
+    logging: &LoggingOptions,
+) {
+    match logging.image {
+        SaveImageOption::AllBest | SaveImageOption::Last | SaveImageOption::FirstAndLast => {
+            draw_keyboard(genome, "final", layout_map);
+        }
+        SaveImageOption::None => {}
+    }
+}
+
+fn print_baseline<const N: usize>(problem: &SaProblem<N>, logging: &LoggingOptions) {
     if logging.verbosity.is_normal() {
-        println!("{}", layout_score);
+        println!("{}", problem.layout_score);
 
         println!(
             "From here everything is relative with + % worse and - % better than this baseline \n
@@ -66,22 +259,29 @@ pub fn run_sa<L: ILayout<N>, const N: usize>(
 
         println!("Temperature \t Iteration \t Best Score \t New Score");
     }
+}
 
-    let mut current_genome = create_genome(&mut letter_list, &mut rng);
-    let mut current_objective = objective::objective_function::<L, N>(
-        &key_presses,
-        &current_genome,
-        &layout_map,
-        Some(layout_score),
-        &letter_list,
-    );
+// simulated annealing
+pub fn run_sa<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    text: &str,
+    effort_model: &EffortModel,
+    constraints: &Constraints<N>,
+    setup: SaSetup,
+    logging: LoggingOptions,
+) -> Result<[char; N], GeometryError> {
+    if logging.verbosity.is_normal() {
+        println!("Calculating raw baseline: ");
+    }
 
-    let mut best_genome = current_genome;
-    let mut best_objective = current_objective;
+    let problem = SaProblem::new(layout, text, effort_model, constraints)?;
+    print_baseline(&problem, &logging);
+
+    let mut chain = problem.start(&setup, setup.seed);
 
     match logging.image {
-        logging::SaveImageOption::FirstAndLast | logging::SaveImageOption::AllBest => {
-            draw_keyboard(&best_genome, "0", &layout_map)
+        SaveImageOption::FirstAndLast | SaveImageOption::AllBest => {
+            draw_keyboard(&chain.state.best_genome, "0", &problem.layout_map)
         }
         _ => {}
     };
@@ -91,97 +291,134 @@ pub fn run_sa<L: ILayout<N>, const N: usize>(
         append_updates("temperature | iteration | bestObjective | newObjective");
     }
 
-    // run SA
-    let mut static_count = 0.0;
+    problem.anneal(&mut chain, &setup, &logging);
 
-    for iteration in 1..=num_iterations {
-        if temperature <= 1.0 {
-            break;
-        }
+    draw_final(&chain.state.best_genome, &problem.layout_map, &logging);
 
-        // ~ create new genome ~
-        let new_genome = shuffle_genome(&current_genome, 2., &mut rng);
+    Ok(chain.state.best_genome)
+}
 
-        // ~ asses ~
-        let new_objective = objective::objective_function::<L, N>(
-            &key_presses,
-            &new_genome,
-            &layout_map,
-            Some(layout_score),
-            &letter_list,
-        );
-        let delta = new_objective - current_objective;
+/// Continue a run of `run_sa` from a checkpoint file.
+///
+/// The setup and effort model are taken from the checkpoint,
+/// the layout and text have to be the same as in the interrupted run.
+pub fn resume_sa<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    text: &str,
+    checkpoint: impl AsRef<Path>,
+    logging: LoggingOptions,
+) -> Result<[char; N], CheckpointError> {
+    let checkpoint = Checkpoint::load(checkpoint.as_ref())?;
+    let constraints = checkpoint.constraints(layout)?;
 
-        if logging.verbosity.is_normal() {
-            println!("{temperature:.2}\t{iteration}\t{best_objective:.2}\t{new_objective:.2}");
-        }
+    let problem = SaProblem::new(layout, text, &checkpoint.effort_model, &constraints)?;
+    print_baseline(&problem, &logging);
 
-        if delta < 0.0 {
-            current_genome = new_genome;
-            current_objective = new_objective;
+    let mut chain = checkpoint.restore(&problem)?;
 
-            if logging.text {
-                let update_line = format!(
-                    "{temperature:.2}, {iteration}, {best_objective:.5}, {new_objective:.5}"
-                );
-                append_updates(&update_line);
-            }
+    if logging.text {
+        append_updates(&format!(
+            "\nResuming Run at iteration {}",
+            chain.state.iteration
+        ));
+        append_updates("temperature | iteration | bestObjective | newObjective");
+    }
 
-            if new_objective < best_objective {
-                best_genome = new_genome;
-                best_objective = new_objective;
+    problem.anneal(&mut chain, &checkpoint.setup, &logging);
 
-                if logging::SaveImageOption::AllBest == logging.image {
-                    println!("(new best, png being saved)");
-                    draw_keyboard(&best_genome, iteration.to_string().as_str(), &layout_map);
-                }
+    draw_final(&chain.state.best_genome, &problem.layout_map, &logging);
 
-                if logging.text {
-                    append_to_file(
-                        "results/bestGenomes.txt",
-                        &format!("{iteration}: {best_genome:#?}\n"),
-                    );
-                }
+    Ok(chain.state.best_genome)
+}
 
-                if logging.image == logging::SaveImageOption::None && logging.verbosity.is_normal()
-                {
-                    println!("(new best)");
-                }
+/// Run `chains` independent annealing chains on all cores.
+///
+/// The seed of every chain is derived from the seed of the setup,
+/// so the result only depends on the inputs and not on the number of threads.
+/// Progress of the single chains is not printed, only the summary.
+pub fn run_sa_parallel<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    text: &str,
+    effort_model: &EffortModel,
+    constraints: &Constraints<N>,
+    setup: SaSetup,
+    chains: usize,
+    logging: LoggingOptions,
+) -> Result<ParallelSaResult<N>, GeometryError> {
+    assert!(chains > 0, "at least one chain is needed");
+
+    let problem = SaProblem::new(layout, text, effort_model, constraints)?;
+
+    let mut seeder = ChaCha12Rng::seed_from_u64(setup.seed);
+    let seeds: Vec<u64> = (0..chains).map(|_| seeder.gen()).collect();
+
+    let chains: Vec<ChainResult<N>> = seeds
+        .into_par_iter()
+        .map(|seed| {
+            let mut chain = problem.start(&setup, seed);
+            problem.anneal(&mut chain, &setup, &LoggingOptions::silent());
+            ChainResult {
+                seed,
+                genome: chain.state.best_genome,
+                score: problem.score(chain.state.best_absolute),
             }
-        } else if (-delta / temperature).exp() > rand::random() {
-            current_genome = new_genome;
-            current_objective = new_objective;
-        }
-
-        static_count += 1.0;
+        })
+        .collect();
 
-        if static_count > epoch {
-            static_count = 0.0;
-            temperature *= cooling_rate;
+    let best = chains
+        .iter()
+        .min_by(|a, b| a.score.absolute.total_cmp(&b.score.absolute))
+        .expect("there is at least one chain")
+        .clone();
 
-            if rand::random::<f64>() < 0.5 {
-                current_genome = best_genome;
-                current_objective = best_objective;
-            }
+    if logging.verbosity.is_normal() {
+        println!("Chain\tSeed\t\t\tRelative Score");
+        for (i, chain) in chains.iter().enumerate() {
+            println!("{i}\t{:<20}\t{:+.2} %", chain.seed, chain.score.relative);
         }
     }
 
-    match logging.image {
-        logging::SaveImageOption::AllBest
-        | logging::SaveImageOption::Last
-        | logging::SaveImageOption::FirstAndLast => {
-            draw_keyboard(&best_genome, "final", &layout_map);
+    if logging.text {
+        for chain in &chains {
+            append_to_file(
+                "results/bestGenomes.txt",
+                &format!("chain {}: {:#?}\n", chain.seed, chain.genome),
+            );
         }
-        _ => {}
     }
 
-    best_genome
+    draw_final(&best.genome, &problem.layout_map, &logging);
+
+    Ok(ParallelSaResult { best, chains })
+}
+
+/// Optimize a random genome with any search strategy
+pub fn run_optimizer<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    text: &str,
+    effort_model: &EffortModel,
+    constraints: &Constraints<N>,
+    optimizer: &dyn Optimizer<N>,
+    seed: u64,
+) -> Result<ChainResult<N>, GeometryError> {
+    let problem = SaProblem::new(layout, text, effort_model, constraints)?;
+    let (mut objective, mut rng) = problem.random_start(seed);
+
+    let genome = optimizer.optimize(&mut objective, &mut rng);
+    objective.set_genome(&genome);
+
+    Ok(ChainResult {
+        seed,
+        genome,
+        score: problem.score(objective.absolute()),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use advanced_setup::SEED;
     use prelude::*;
 
     fn get_setup() -> SaSetup {
@@ -190,33 +427,176 @@ mod tests {
             epoch: 20.,
             cooling_rate: 0.9,
             num_iterations: 10,
+            seed: SEED,
         }
     }
 
     #[test]
     fn test_run_sa_qwerty_does_not_panic() {
-        run_sa::<QwertyEnUs, 46>(
+        run_sa(
+            &QwertyEnUs(),
             &std::fs::read_to_string("resources/myBook.txt").expect("should read file"),
+            &EffortModel::default(),
+            &Constraints::none(QwertyEnUs().get_letter_list()),
             get_setup(),
             LoggingOptions::silent(),
-        );
+        )
+        .expect("fixed keys");
     }
 
     #[test]
     fn test_run_sa_qwerty_with_german_umlaute_ignored() {
-        run_sa::<QwertyEnUs, 46>(
+        run_sa(
+            &QwertyEnUs(),
             &std::fs::read_to_string("resources/meinBuch.txt").expect("should read file"),
+            &EffortModel::default(),
+            &Constraints::none(QwertyEnUs().get_letter_list()),
             get_setup(),
             LoggingOptions::silent(),
+        )
+        .expect("fixed keys");
+    }
+
+    #[test]
+    fn test_score_genome_baseline_is_zero() {
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let layout = QwertyEnUs();
+
+        let effort_model = EffortModel::default();
+        let score =
+            score_genome(&layout, &text, layout.get_genome(), &effort_model).expect("fixed keys");
+        assert!(score.absolute > 0.);
+        assert_eq!(score.relative, 0.);
+
+        let genome = parse_genome(
+            "QWERTYUIOPASDFGHJKLZXCVBNM0123456789~-+[];'<>?",
+            layout.get_letter_list(),
+        )
+        .expect("should be a permutation");
+        assert!(
+            score_genome(&layout, &text, &genome, &effort_model)
+                .expect("fixed keys")
+                .relative
+                != 0.
+        );
+        assert!(parse_genome("QWERTY", layout.get_letter_list()).is_none());
+    }
+
+    #[test]
+    fn test_untyped_characters() {
+        let text = "(x!) é\r\n";
+
+        assert_eq!(
+            untyped_characters(&Neo2DeDe(), text),
+            BTreeSet::from(['(', ')', '!', 'é'])
+        );
+        assert_eq!(
+            untyped_characters(&QwertzDeDe(), text),
+            BTreeSet::from(['é'])
+        );
+    }
+
+    #[test]
+    fn test_run_sa_parallel_is_deterministic() {
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let constraints = Constraints::none(QwertyEnUs().get_letter_list());
+        let run = || {
+            run_sa_parallel(
+                &QwertyEnUs(),
+                &text,
+                &EffortModel::default(),
+                &constraints,
+                get_setup(),
+                4,
+                LoggingOptions::silent(),
+            )
+            .expect("fixed keys")
+        };
+
+        let result = run();
+        assert_eq!(result.chains.len(), 4);
+        assert!(result
+            .chains
+            .iter()
+            .all(|chain| chain.score.absolute >= result.best.score.absolute));
+
+        let seeds: BTreeSet<_> = result.chains.iter().map(|chain| chain.seed).collect();
+        assert_eq!(seeds.len(), 4);
+
+        let again = run();
+        for (chain, other) in result.chains.iter().zip(&again.chains) {
+            assert_eq!(chain.genome, other.genome);
+        }
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_run() {
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let effort_model = EffortModel::default();
+        let setup = SaSetup {
+            temperature: 500.,
+            epoch: 20.,
+            cooling_rate: 0.99,
+            num_iterations: 2000,
+            seed: 42,
+        };
+        let constraints = Constraints::none(QwertyEnUs().get_letter_list());
+
+        let run = || {
+            run_sa(
+                &QwertyEnUs(),
+                &text,
+                &effort_model,
+                &constraints,
+                setup,
+                LoggingOptions::silent(),
+            )
+            .expect("fixed keys")
+        };
+        assert_eq!(run(), run());
+
+        let problem =
+            SaProblem::new(&QwertyEnUs(), &text, &effort_model, &constraints).expect("fixed keys");
+        let history = || {
+            let mut chain = problem.start(&setup, setup.seed);
+            (1..=setup.num_iterations / 100)
+                .map(|step| {
+                    let steps = SaSetup {
+                        num_iterations: step * 100,
+                        ..setup
+                    };
+                    problem.anneal(&mut chain, &steps, &LoggingOptions::silent());
+                    (chain.state.current_objective, chain.state.best_objective)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(history(), history());
+
+        let other_seed = SaSetup { seed: 43, ..setup };
+        assert_ne!(
+            run(),
+            run_sa(
+                &QwertyEnUs(),
+                &text,
+                &effort_model,
+                &constraints,
+                other_seed,
+                LoggingOptions::silent()
+            )
+            .expect("fixed keys")
         );
     }
 
     #[test]
     fn test_run_sa_qwertz_does_not_panic() {
-        run_sa::<QwertzDeDe, 48>(
+        run_sa(
+            &QwertzDeDe(),
             &std::fs::read_to_string("resources/meinBuch.txt").expect("should read file"),
+            &EffortModel::default(),
+            &Constraints::none(QwertzDeDe().get_letter_list()),
             get_setup(),
             LoggingOptions::silent(),
-        );
+        )
+        .expect("fixed keys");
     }
 }
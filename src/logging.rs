@@ -1,6 +1,7 @@
 use std::{
     fs::{File, OpenOptions},
     io::Write,
+    num::NonZeroUsize,
     path::Path,
 };
 
@@ -8,6 +9,8 @@ pub struct LoggingOptions {
     pub text: bool,
     pub image: SaveImageOption,
     pub verbosity: Verbosity,
+    /// save `results/checkpoint.json` every n iterations
+    pub checkpoint: Option<NonZeroUsize>,
 }
 
 impl LoggingOptions {
@@ -16,12 +19,15 @@ impl LoggingOptions {
             text: false,
             image: SaveImageOption::None,
             verbosity: Verbosity::Silent,
+            checkpoint: None,
         }
     }
 }
 
+#[derive(Default)]
 pub enum Verbosity {
     Silent,
+    #[default]
     Normal,
 }
 
@@ -34,12 +40,6 @@ impl Verbosity {
     }
 }
 
-impl Default for Verbosity {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
 #[derive(PartialEq)]
 pub enum SaveImageOption {
     None,
@@ -1,37 +1,828 @@
 // #![feature(test)]
 
+use std::{collections::BTreeSet, error::Error, num::NonZeroUsize};
+
 use chrono::Utc;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use keyboards::prelude::*;
 
-fn main() {
-    let start_time = Utc::now().time();
+#[derive(Parser)]
+#[command(about = "Optimize and evaluate keyboard layouts with simulated annealing")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-    let training_set_path = "resources/meinBuch.txt";
+#[derive(Subcommand)]
+enum Command {
+    /// Search for a better layout with simulated annealing
+    Optimize(OptimizeArgs),
+    /// Continue an optimization from a checkpoint
+    Resume {
+        #[command(flatten)]
+        layout: LayoutArgs,
+        #[arg(long, default_value = "results/checkpoint.json")]
+        checkpoint: String,
+        #[command(flatten)]
+        logging: LoggingArgs,
+    },
+    /// Evaluate a genome on a corpus
+    Score {
+        #[command(flatten)]
+        genome: GenomeArgs,
+        /// Effort model file (.toml/.json), defaults to the built-in weights
+        #[arg(long)]
+        effort: Option<String>,
+    },
+    /// Report finger load, hand balance, row usage and the penalty terms of a genome
+    Analyze {
+        #[command(flatten)]
+        genome: GenomeArgs,
+        /// Effort model file (.toml/.json), defaults to the built-in weights
+        #[arg(long)]
+        effort: Option<String>,
+    },
+    /// Render a genome as `results/<id>.png`
+    Draw {
+        #[command(flatten)]
+        genome: GenomeArgs,
+        /// Name of the image in the results directory
+        #[arg(long, default_value = "layout")]
+        id: String,
+    },
+    /// Score several layouts side by side
+    Compare {
+        /// Built-in layout names or layout definition files
+        #[arg(long = "layout", required = true)]
+        layouts: Vec<String>,
+        #[arg(long, default_value = DEFAULT_CORPUS)]
+        corpus: String,
+        /// Effort model files (.toml/.json), every layout is scored with each of them
+        #[arg(long = "effort")]
+        efforts: Vec<String>,
+    },
+    /// Score every reference genome of a layout, with the bigram and trigram metrics
+    References {
+        /// Built-in layout (qwerty-en-us, qwertz-de-de, dvorak, colemak, colemak-dh, workman,
+        /// neo2, bone, koy) or a layout definition file (.toml/.json)
+        #[arg(long, default_value = "qwerty-en-us")]
+        layout: String,
+        /// Physical keyboard for the characters of the layout, defaults to the keyboard of the layout
+        #[arg(long, value_enum)]
+        geometry: Option<GeometryArg>,
+        /// Training texts, every genome is scored on each of them
+        #[arg(long = "corpus", default_value = DEFAULT_CORPUS)]
+        corpora: Vec<String>,
+        /// Effort model file (.toml/.json), defaults to the built-in weights
+        #[arg(long)]
+        effort: Option<String>,
+    },
+    /// Write a genome on its keyboard in the file format of another tool
+    Export {
+        #[command(flatten)]
+        genome: GenomeArgs,
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// File to write
+        #[arg(long)]
+        output: String,
+        /// Board file (.toml/.json) with the firmware key order, needed for qmk and zmk
+        #[arg(long, required_if_eq_any([("format", "qmk"), ("format", "zmk")]))]
+        board: Option<String>,
+    },
+    /// Convert a keyboard-layout-editor JSON file into a layout definition file
+    ImportKle {
+        /// Raw data of a keyboard-layout-editor.com layout
+        #[arg(long)]
+        kle: String,
+        /// Built-in layout or layout definition file with the characters to place
+        #[arg(long, default_value = "qwerty-en-us")]
+        layout: String,
+        /// Row, finger and home key of every letter key (.toml/.json), guessed if missing
+        #[arg(long)]
+        assignment: Option<String>,
+        /// Layout definition file to write (.toml/.json)
+        #[arg(long)]
+        output: String,
+    },
+}
 
-    let file_content = std::fs::read_to_string(training_set_path).expect("Unable to open file");
+const DEFAULT_CORPUS: &str = "resources/meinBuch.txt";
 
-    // Select your layout with the first generic parameter.
-    // The second ist the number of keys and must match the Layout, otherwise you will get a compiler error.
-    let result = run_sa::<QwertyEnUs, 46>(
-        &file_content,
-        SaSetup {
-            temperature: 500.,
-            epoch: 20.,
-            cooling_rate: 0.99,
-            num_iterations: 25000,
-        },
+#[derive(Args)]
+struct LayoutArgs {
+    /// Built-in layout (qwerty-en-us, qwertz-de-de, dvorak, colemak, colemak-dh, workman,
+    /// neo2, bone, koy) or a layout definition file (.toml/.json)
+    #[arg(long, default_value = "qwerty-en-us")]
+    layout: String,
+    /// Physical keyboard for the characters of the layout, defaults to the keyboard of the layout
+    #[arg(long, value_enum)]
+    geometry: Option<GeometryArg>,
+    /// Training text
+    #[arg(long, default_value = DEFAULT_CORPUS)]
+    corpus: String,
+}
+
+#[derive(Args)]
+struct GenomeArgs {
+    #[command(flatten)]
+    layout: LayoutArgs,
+    /// One character per key in layout map order, defaults to the baseline genome of the layout
+    #[arg(long)]
+    genome: Option<String>,
+}
+
+#[derive(Args)]
+struct OptimizeArgs {
+    #[command(flatten)]
+    layout: LayoutArgs,
+    /// Effort model file (.toml/.json), defaults to the built-in weights
+    #[arg(long)]
+    effort: Option<String>,
+    /// Constraints file (.toml/.json) with characters that must not move
+    #[arg(long)]
+    constraints: Option<String>,
+    #[arg(long, value_enum, default_value_t = Strategy::Annealing)]
+    strategy: Strategy,
+    #[arg(long, default_value_t = 500.)]
+    temperature: f64,
+    #[arg(long, default_value_t = 20.)]
+    epoch: f64,
+    #[arg(long, default_value_t = 0.99)]
+    cooling_rate: f64,
+    /// Random swaps tried by annealing and late acceptance (default 25000),
+    /// or scans of all swaps by hill climbing (default 1000) and tabu search (default 500),
+    /// the genetic algorithm runs `--generations` instead
+    #[arg(long)]
+    iterations: Option<usize>,
+    /// Iterations a swap stays forbidden after it was made in tabu search
+    #[arg(long, default_value_t = 20)]
+    tabu_tenure: usize,
+    /// Number of past scores a swap is compared with in late acceptance
+    #[arg(long, default_value_t = 100)]
+    history_length: usize,
+    /// Number of genomes in the genetic algorithm
+    #[arg(long, default_value_t = 100)]
+    population: usize,
+    #[arg(long, default_value_t = 200)]
+    generations: usize,
+    #[arg(long, value_enum, default_value_t = CrossoverArg::Pmx)]
+    crossover: CrossoverArg,
+    /// Runs with the same seed and options give the same result
+    #[arg(long, default_value_t = keyboards::advanced_setup::SEED)]
+    seed: u64,
+    /// Number of independent annealing chains, run in parallel (annealing only)
+    #[arg(long, default_value_t = 1)]
+    chains: usize,
+    #[command(flatten)]
+    logging: LoggingArgs,
+}
+
+#[derive(Args)]
+struct LoggingArgs {
+    /// Append progress to the text files in the results directory
+    #[arg(long)]
+    log_text: bool,
+    #[arg(long, value_enum, default_value_t = ImageArg::FirstAndLast)]
+    image: ImageArg,
+    /// Only print the result
+    #[arg(long)]
+    quiet: bool,
+    /// Save results/checkpoint.json every n iterations, continue with `resume`
+    /// (a single annealing chain only)
+    #[arg(long, value_name = "N")]
+    checkpoint_every: Option<NonZeroUsize>,
+}
+
+impl From<&LoggingArgs> for LoggingOptions {
+    fn from(value: &LoggingArgs) -> Self {
         LoggingOptions {
-            text: true,
-            image: SaveImageOption::FirstAndLast,
-            verbosity: Verbosity::Normal,
-        },
+            text: value.log_text,
+            image: value.image.into(),
+            verbosity: match value.quiet {
+                true => Verbosity::Silent,
+                false => Verbosity::Normal,
+            },
+            checkpoint: value.checkpoint_every,
+        }
+    }
+}
+
+/// Search strategy, each iteration of tabu search tries every swap
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Strategy {
+    Annealing,
+    HillClimbing,
+    Tabu,
+    LateAcceptance,
+    Genetic,
+}
+
+impl Strategy {
+    /// `--iterations` if it is not set, an iteration of hill climbing and tabu search
+    /// scores every swap instead of one
+    fn default_iterations(self) -> usize {
+        match self {
+            Strategy::HillClimbing => 1000,
+            Strategy::Tabu => 500,
+            Strategy::Annealing | Strategy::LateAcceptance => 25000,
+            // the genetic algorithm counts `--generations` instead
+            Strategy::Genetic => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CrossoverArg {
+    Pmx,
+    Order,
+    Cycle,
+}
+
+impl From<CrossoverArg> for Crossover {
+    fn from(value: CrossoverArg) -> Self {
+        match value {
+            CrossoverArg::Pmx => Crossover::Pmx,
+            CrossoverArg::Order => Crossover::Order,
+            CrossoverArg::Cycle => Crossover::Cycle,
+        }
+    }
+}
+
+/// Row-staggered ANSI or ISO, ortholinear like a Planck or split with column stagger
+/// and thumb clusters like an Ergodox or Corne
+#[derive(Clone, Copy, ValueEnum)]
+enum GeometryArg {
+    Ansi,
+    Iso,
+    Ortholinear,
+    Split,
+}
+
+impl From<GeometryArg> for Geometry {
+    fn from(value: GeometryArg) -> Self {
+        match value {
+            GeometryArg::Ansi => Geometry::Ansi,
+            GeometryArg::Iso => Geometry::Iso,
+            GeometryArg::Ortholinear => Geometry::Ortholinear,
+            GeometryArg::Split => Geometry::Split,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// Raw data for keyboard-layout-editor.com
+    Kle,
+    /// XKB symbols file for Linux
+    Xkb,
+    /// Microsoft Keyboard Layout Creator source for Windows
+    Klc,
+    /// Keyboard layout for macOS
+    Keylayout,
+    /// `keymap.c` for QMK firmware
+    Qmk,
+    /// `.keymap` for ZMK firmware
+    Zmk,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImageArg {
+    None,
+    Last,
+    FirstAndLast,
+    AllBest,
+}
+
+impl From<ImageArg> for SaveImageOption {
+    fn from(value: ImageArg) -> Self {
+        match value {
+            ImageArg::None => SaveImageOption::None,
+            ImageArg::Last => SaveImageOption::Last,
+            ImageArg::FirstAndLast => SaveImageOption::FirstAndLast,
+            ImageArg::AllBest => SaveImageOption::AllBest,
+        }
+    }
+}
+
+/// Run `$body` with `$layout` bound to the layout selected by `$name`.
+/// Layout definition files are tried with every supported number of keys,
+/// a `$geometry` moves the keys of the layout to another physical keyboard.
+macro_rules! with_layout {
+    ($name:expr, $geometry:expr, |$layout:ident| $body:expr) => {
+        match $name {
+            "qwerty" | "qwerty-en-us" => {
+                with_geometry!($geometry, QwertyEnUs(), |$layout| $body)
+            }
+            "qwertz" | "qwertz-de-de" => {
+                with_geometry!($geometry, QwertzDeDe(), |$layout| $body)
+            }
+            "dvorak" | "dvorak-en-us" => {
+                with_geometry!($geometry, DvorakEnUs(), |$layout| $body)
+            }
+            "colemak" | "colemak-en-us" => {
+                with_geometry!($geometry, ColemakEnUs(), |$layout| $body)
+            }
+            "colemak-dh" | "colemak-dh-en-us" => {
+                with_geometry!($geometry, ColemakDhEnUs(), |$layout| $body)
+            }
+            "workman" | "workman-en-us" => {
+                with_geometry!($geometry, WorkmanEnUs(), |$layout| $body)
+            }
+            "neo2" | "neo2-de-de" => {
+                with_geometry!($geometry, Neo2DeDe(), |$layout| $body)
+            }
+            "bone" | "bone-de-de" => {
+                with_geometry!($geometry, BoneDeDe(), |$layout| $body)
+            }
+            "koy" | "koy-de-de" => {
+                with_geometry!($geometry, KoyDeDe(), |$layout| $body)
+            }
+            path => with_custom_layout!(path, $geometry, |$layout| $body, 46, 48),
+        }
+    };
+}
+
+macro_rules! with_custom_layout {
+    ($path:expr, $geometry:expr, |$layout:ident| $body:expr, $n:literal $(, $rest:literal)*) => {
+        match CustomLayout::<$n>::from_file($path) {
+            Ok(layout) => with_geometry!($geometry, layout, |$layout| $body),
+            Err(LayoutDefinitionError::WrongKeyCount {
+                field: "letters", ..
+            }) => with_custom_layout!($path, $geometry, |$layout| $body $(, $rest)*),
+            Err(err) => return Err(format!("{}: {err}", $path).into()),
+        }
+    };
+    ($path:expr, $geometry:expr, |$layout:ident| $body:expr) => {
+        return Err(format!("{}: unsupported number of keys", $path).into())
+    };
+}
+
+macro_rules! with_geometry {
+    ($geometry:expr, $value:expr, |$layout:ident| $body:expr) => {
+        match $geometry {
+            None => {
+                let $layout = &$value;
+                $body
+            }
+            Some(geometry) => {
+                let layout = GeometryLayout::new($value, Geometry::from(geometry))
+                    .map_err(|err| err.to_string())?;
+                let $layout = &layout;
+                $body
+            }
+        }
+    };
+}
+
+fn get_genome<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    genome: &Option<String>,
+) -> Result<[char; N], Box<dyn Error>> {
+    match genome {
+        Some(genome) => parse_genome(genome, layout.get_letter_list()).ok_or_else(|| {
+            format!(
+                "genome must contain each of these {N} characters exactly once: {}",
+                layout.get_letter_list().iter().collect::<String>()
+            )
+            .into()
+        }),
+        None => Ok(*layout.get_genome()),
+    }
+}
+
+fn get_effort_model(path: &Option<String>) -> Result<EffortModel, Box<dyn Error>> {
+    match path {
+        Some(path) => EffortModel::from_file(path).map_err(|err| format!("{path}: {err}").into()),
+        None => Ok(EffortModel::default()),
+    }
+}
+
+fn get_constraints<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    path: &Option<String>,
+) -> Result<Constraints<N>, Box<dyn Error>> {
+    match path {
+        Some(path) => {
+            Constraints::from_file(path, layout).map_err(|err| format!("{path}: {err}").into())
+        }
+        None => Ok(Constraints::none(layout.get_letter_list())),
+    }
+}
+
+fn optimize(args: OptimizeArgs) -> Result<(), Box<dyn Error>> {
+    let start_time = Utc::now().time();
+
+    let file_content = std::fs::read_to_string(&args.layout.corpus)?;
+    let effort_model = get_effort_model(&args.effort)?;
+
+    let iterations = args
+        .iterations
+        .unwrap_or(args.strategy.default_iterations());
+    let setup = SaSetup {
+        temperature: args.temperature,
+        epoch: args.epoch,
+        cooling_rate: args.cooling_rate,
+        num_iterations: iterations,
+        seed: args.seed,
+    };
+    let logging = LoggingOptions::from(&args.logging);
+
+    if args.strategy == Strategy::Genetic && args.iterations.is_some() {
+        return Err("--iterations is not used by the genetic algorithm, set --generations".into());
+    }
+    if args.strategy != Strategy::Annealing && args.chains != 1 {
+        return Err("--chains is only supported with simulated annealing".into());
+    }
+    if args.logging.checkpoint_every.is_some()
+        && (args.strategy != Strategy::Annealing || args.chains != 1)
+    {
+        return Err("--checkpoint-every is only supported with a single annealing chain".into());
+    }
+
+    let result = with_layout!(
+        args.layout.layout.as_str(),
+        args.layout.geometry,
+        |layout| {
+            let constraints = get_constraints(layout, &args.constraints)?;
+            let optimizer: &dyn Optimizer<_> = match args.strategy {
+                Strategy::Annealing => &SimulatedAnnealing { setup },
+                Strategy::HillClimbing => &HillClimbing {
+                    max_steps: iterations,
+                },
+                Strategy::Tabu => &TabuSearch {
+                    iterations,
+                    tenure: args.tabu_tenure,
+                },
+                Strategy::LateAcceptance => &LateAcceptance {
+                    iterations,
+                    history_length: args.history_length,
+                },
+                Strategy::Genetic => &GeneticAlgorithm {
+                    population_size: args.population,
+                    generations: args.generations,
+                    crossover: args.crossover.into(),
+                    tournament_size: 3,
+                    elitism: 2,
+                    mutation_rate: 0.2,
+                    mutated_keys: 2,
+                    print_stats: !args.logging.quiet,
+                },
+            };
+
+            match args.chains {
+                0 => return Err("at least one chain is needed".into()),
+                _ if args.strategy != Strategy::Annealing => {
+                    let result = run_optimizer(
+                        layout,
+                        &file_content,
+                        &effort_model,
+                        &constraints,
+                        optimizer,
+                        args.seed,
+                    )?;
+                    if !args.logging.quiet {
+                        println!("{}: {:+.2} %", optimizer.name(), result.score.relative);
+                    }
+                    result.genome
+                }
+                1 => run_sa(
+                    layout,
+                    &file_content,
+                    &effort_model,
+                    &constraints,
+                    setup,
+                    logging,
+                )?,
+                chains => {
+                    run_sa_parallel(
+                        layout,
+                        &file_content,
+                        &effort_model,
+                        &constraints,
+                        setup,
+                        chains,
+                        logging,
+                    )?
+                    .best
+                    .genome
+                }
+            }
+            .iter()
+            .collect::<String>()
+        }
     );
 
     let end_time = Utc::now().time();
     let diff = end_time - start_time;
 
-    println!("Duration: {diff}");
-    println!("Result: {:?}", result);
+    if !args.logging.quiet {
+        println!("Duration: {diff}");
+    }
+    println!("Result: {result}");
+
+    Ok(())
+}
+
+fn resume(
+    layout: LayoutArgs,
+    checkpoint: &str,
+    logging: LoggingArgs,
+) -> Result<(), Box<dyn Error>> {
+    let file_content = std::fs::read_to_string(&layout.corpus)?;
+
+    let result = with_layout!(layout.layout.as_str(), layout.geometry, |layout| {
+        resume_sa(
+            layout,
+            &file_content,
+            checkpoint,
+            LoggingOptions::from(&logging),
+        )
+        .map_err(|err| format!("{checkpoint}: {err}"))?
+        .iter()
+        .collect::<String>()
+    });
+
+    println!("Result: {result}");
+
+    Ok(())
+}
+
+fn score(args: GenomeArgs, effort: &Option<String>) -> Result<(), Box<dyn Error>> {
+    let file_content = std::fs::read_to_string(&args.layout.corpus)?;
+    let effort_model = get_effort_model(effort)?;
+
+    let score = with_layout!(
+        args.layout.layout.as_str(),
+        args.layout.geometry,
+        |layout| {
+            score_genome(
+                layout,
+                &file_content,
+                &get_genome(layout, &args.genome)?,
+                &effort_model,
+            )?
+        }
+    );
+
+    println!("Score: {:.2}", score.absolute);
+    println!("Relative to baseline: {:+.2} %", score.relative);
+
+    Ok(())
+}
+
+fn analyze(args: GenomeArgs, effort: &Option<String>) -> Result<(), Box<dyn Error>> {
+    let file_content = std::fs::read_to_string(&args.layout.corpus)?;
+    let effort_model = get_effort_model(effort)?;
+
+    let analysis = with_layout!(
+        args.layout.layout.as_str(),
+        args.layout.geometry,
+        |layout| {
+            analyze_genome(
+                layout,
+                &file_content,
+                &get_genome(layout, &args.genome)?,
+                &effort_model,
+            )?
+        }
+    );
+
+    print!("{analysis}");
+
+    Ok(())
+}
+
+fn draw(args: GenomeArgs, id: &str) -> Result<(), Box<dyn Error>> {
+    with_layout!(
+        args.layout.layout.as_str(),
+        args.layout.geometry,
+        |layout| draw_genome(layout, &get_genome(layout, &args.genome)?, id)
+    );
+
+    println!("Saved results/{id}.png");
+
+    Ok(())
+}
+
+fn compare(layouts: &[String], corpus: &str, efforts: &[String]) -> Result<(), Box<dyn Error>> {
+    let file_content = std::fs::read_to_string(corpus)?;
+
+    let effort_models = match efforts.is_empty() {
+        true => vec![(String::from("default"), EffortModel::default())],
+        false => efforts
+            .iter()
+            .map(|path| Ok((path.clone(), get_effort_model(&Some(path.clone()))?)))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?,
+    };
+
+    // a character that only some layouts can type would make the others score better
+    let mut untyped = Vec::new();
+    for name in layouts {
+        untyped.push(with_layout!(name.as_str(), None::<GeometryArg>, |layout| {
+            untyped_characters(layout, &file_content)
+        }));
+    }
+    let skipped: BTreeSet<char> = untyped.iter().flatten().copied().collect();
+    let partly_typed: String = skipped
+        .iter()
+        .filter(|c| untyped.iter().any(|characters| !characters.contains(c)))
+        .collect();
+    if !partly_typed.is_empty() {
+        eprintln!(
+            "Warning: the characters {partly_typed} can not be typed with every layout and are skipped for all"
+        );
+    }
+    let file_content: String = file_content
+        .chars()
+        .filter(|c| !skipped.contains(c))
+        .collect();
+
+    let width = layouts.iter().map(|name| name.len()).max().unwrap_or(0);
+
+    for (effort_name, effort_model) in effort_models {
+        let mut scores = Vec::new();
+        for name in layouts {
+            let score = with_layout!(name.as_str(), None::<GeometryArg>, |layout| {
+                score_genome(layout, &file_content, layout.get_genome(), &effort_model)?
+            });
+            scores.push((name, score.absolute));
+        }
+
+        let (_, reference) = scores[0];
+
+        println!("Effort model: {effort_name}");
+        println!("{:width$}\t{:>12}\t{:>10}", "Layout", "Score", "Relative");
+        for (name, absolute) in scores {
+            let relative = (absolute / reference - 1.0) * 100.0;
+            println!("{name:width$}\t{absolute:>12.2}\t{relative:>+9.2}%");
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+fn references(
+    layout: &str,
+    geometry: Option<GeometryArg>,
+    corpora: &[String],
+    effort: &Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let effort_model = get_effort_model(effort)?;
+
+    for corpus in corpora {
+        let file_content = std::fs::read_to_string(corpus)?;
+
+        let (analyses, untyped) = with_layout!(layout, geometry, |layout| {
+            (
+                analyze_reference_genomes(layout, &file_content, &effort_model)?,
+                untyped_characters(layout, &file_content),
+            )
+        });
+        if !untyped.is_empty() {
+            let untyped: String = untyped.into_iter().collect();
+            eprintln!(
+                "Warning: the characters {untyped} can not be typed with {layout} and are skipped"
+            );
+        }
+
+        let width = analyses
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+
+        println!("Corpus: {corpus}");
+        print!(
+            "{:width$}\t{:>12}\t{:>9}\t{:>6}",
+            "Layout", "Score", "Relative", "Home"
+        );
+        for header in [
+            "SFB", "Skip", "Stretch", "Scissor", "In", "Out", "Redir.", "Alt.",
+        ] {
+            print!("\t{header:>7}");
+        }
+        println!();
+
+        for (name, analysis) in analyses {
+            print!(
+                "{name:width$}\t{:>12.2}\t{:>+8.2}%\t{:>5.1}%",
+                analysis.score.absolute, analysis.score.relative, analysis.home_row
+            );
+            for (_, rate) in analysis.metrics.named() {
+                print!("\t{rate:>6.2}%");
+            }
+            println!();
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+fn export(
+    args: GenomeArgs,
+    format: ExportFormat,
+    output: &str,
+    board: &Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let name = args.layout.layout.as_str();
+    let path = std::path::Path::new(output);
+    let board = board
+        .as_deref()
+        .map(|board| Board::from_file(board).map_err(|err| format!("{board}: {err}")))
+        .transpose()?;
+    let get_board = || {
+        board
+            .as_ref()
+            .ok_or("--board is needed for firmware keymaps")
+    };
+    let content = with_layout!(name, args.layout.geometry, |layout| {
+        let genome = get_genome(layout, &args.genome)?;
+        match format {
+            ExportFormat::Kle => export_kle(layout, &genome, name)?,
+            ExportFormat::Xkb => export_xkb(layout, &genome, name)?,
+            ExportFormat::Klc => export_klc(layout, &genome, name)?,
+            ExportFormat::Keylayout => export_keylayout(layout, &genome, name)?,
+            ExportFormat::Qmk => export_qmk(layout, &genome, name, get_board()?)?,
+            ExportFormat::Zmk => export_zmk(layout, &genome, name, get_board()?)?,
+        }
+    });
+    let bytes = match format {
+        // Microsoft Keyboard Layout Creator only reads UTF-16 with a byte order mark
+        ExportFormat::Klc => "\u{feff}"
+            .encode_utf16()
+            .chain(content.encode_utf16())
+            .flat_map(u16::to_le_bytes)
+            .collect(),
+        _ => content.into_bytes(),
+    };
+    std::fs::write(output, bytes).map_err(|err| format!("{output}: {err}"))?;
+
+    println!("Saved {output}");
+    if let ExportFormat::Xkb = format {
+        println!("Activate it with:\n{}", setxkbmap_snippet(path));
+    }
+
+    Ok(())
+}
+
+fn import_kle_file(
+    kle: &str,
+    layout: &str,
+    assignment: &Option<String>,
+    output: &str,
+) -> Result<(), Box<dyn Error>> {
+    let keyboard = KleKeyboard::from_file(kle).map_err(|err| format!("{kle}: {err}"))?;
+    let assignment = assignment.as_deref().map(std::path::Path::new);
+
+    with_layout!(layout, None::<GeometryArg>, |layout| {
+        import_kle(&keyboard, layout, assignment)
+            .map_err(|err| format!("{kle}: {err}"))?
+            .save(output)
+            .map_err(|err| format!("{output}: {err}"))?
+    });
+    println!("Saved {output}");
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    match Cli::parse().command {
+        Command::Optimize(args) => optimize(args),
+        Command::Resume {
+            layout,
+            checkpoint,
+            logging,
+        } => resume(layout, &checkpoint, logging),
+        Command::Score { genome, effort } => score(genome, &effort),
+        Command::Analyze { genome, effort } => analyze(genome, &effort),
+        Command::Draw { genome, id } => draw(genome, &id),
+        Command::Compare {
+            layouts,
+            corpus,
+            efforts,
+        } => compare(&layouts, &corpus, &efforts),
+        Command::References {
+            layout,
+            geometry,
+            corpora,
+            effort,
+        } => references(&layout, geometry, &corpora, &effort),
+        Command::Export {
+            genome,
+            format,
+            output,
+            board,
+        } => export(genome, format, &output, &board),
+        Command::ImportKle {
+            kle,
+            layout,
+            assignment,
+            output,
+        } => import_kle_file(&kle, &layout, &assignment, &output),
+    }
 }
 
 // #[cfg(test)]
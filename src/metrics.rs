@@ -0,0 +1,276 @@
+use std::ops::{Add, Mul};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::layout_map::{KeyboardKey, KeyboardRow};
+use crate::models::Finger;
+use crate::ngrams::NgramStats;
+
+/// Ergonomic metrics of consecutive letters.
+///
+/// As a report every value is a % of the letter bigrams, skip-bigrams and redirects of the
+/// letter trigrams. As weights of the effort model every occurrence adds its weight to the objective.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metrics {
+    /// two different keys with the same finger
+    pub same_finger: f64,
+    /// same finger for the first and last key of a trigram
+    pub skip_bigram: f64,
+    /// neighbouring fingers that are at least two keys apart horizontally
+    pub lateral_stretch: f64,
+    /// neighbouring fingers that are at least two rows apart
+    pub scissor: f64,
+    /// same hand, from the pinky towards the index finger
+    pub inward_roll: f64,
+    /// same hand, from the index finger towards the pinky
+    pub outward_roll: f64,
+    /// three keys of one hand that change direction
+    pub redirect: f64,
+    /// two keys of different hands
+    pub alternation: f64,
+}
+
+impl Metrics {
+    pub(crate) fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sum of the values weighted with `weights`
+    pub(crate) fn weighted(&self, weights: &Metrics) -> f64 {
+        self.same_finger * weights.same_finger
+            + self.skip_bigram * weights.skip_bigram
+            + self.lateral_stretch * weights.lateral_stretch
+            + self.scissor * weights.scissor
+            + self.inward_roll * weights.inward_roll
+            + self.outward_roll * weights.outward_roll
+            + self.redirect * weights.redirect
+            + self.alternation * weights.alternation
+    }
+
+    /// Every metric with its name in the order of the fields
+    pub fn named(&self) -> [(&'static str, f64); 8] {
+        [
+            ("same finger bigrams", self.same_finger),
+            ("skip-bigrams", self.skip_bigram),
+            ("lateral stretches", self.lateral_stretch),
+            ("scissors", self.scissor),
+            ("inward rolls", self.inward_roll),
+            ("outward rolls", self.outward_roll),
+            ("redirects", self.redirect),
+            ("alternation", self.alternation),
+        ]
+    }
+}
+
+impl Add for Metrics {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            same_finger: self.same_finger + other.same_finger,
+            skip_bigram: self.skip_bigram + other.skip_bigram,
+            lateral_stretch: self.lateral_stretch + other.lateral_stretch,
+            scissor: self.scissor + other.scissor,
+            inward_roll: self.inward_roll + other.inward_roll,
+            outward_roll: self.outward_roll + other.outward_roll,
+            redirect: self.redirect + other.redirect,
+            alternation: self.alternation + other.alternation,
+        }
+    }
+}
+
+impl Mul<f64> for Metrics {
+    type Output = Self;
+
+    fn mul(self, factor: f64) -> Self {
+        Self {
+            same_finger: self.same_finger * factor,
+            skip_bigram: self.skip_bigram * factor,
+            lateral_stretch: self.lateral_stretch * factor,
+            scissor: self.scissor * factor,
+            inward_roll: self.inward_roll * factor,
+            outward_roll: self.outward_roll * factor,
+            redirect: self.redirect * factor,
+            alternation: self.alternation * factor,
+        }
+    }
+}
+
+fn flag(value: bool) -> f64 {
+    if value {
+        1.
+    } else {
+        0.
+    }
+}
+
+/// Neither key is pressed by a thumb
+fn no_thumb(a: &KeyboardKey, b: &KeyboardKey) -> bool {
+    a.finger != Finger::Thumb && b.finger != Finger::Thumb
+}
+
+/// Fingers of the same hand that are next to each other, the thumb is not next to the index finger
+fn neighbouring_fingers(a: &KeyboardKey, b: &KeyboardKey) -> bool {
+    no_thumb(a, b) && a.hand == b.hand && (a.finger as i32).abs_diff(b.finger as i32) == 1
+}
+
+/// Metrics of two consecutive letters, 1 for every metric that applies
+pub(crate) fn bigram_metrics(a: &KeyboardKey, b: &KeyboardKey) -> Metrics {
+    if a == b {
+        return Metrics::default();
+    }
+
+    let same_hand = a.hand == b.hand;
+    let same_finger = same_hand && a.finger == b.finger;
+    let letter_rows = a.row != KeyboardRow::Thumb && b.row != KeyboardRow::Thumb;
+    let rolling = same_hand && no_thumb(a, b);
+
+    Metrics {
+        same_finger: flag(same_finger),
+        lateral_stretch: flag(neighbouring_fingers(a, b) && (a.x - b.x).abs() >= 2.),
+        scissor: flag(
+            neighbouring_fingers(a, b) && letter_rows && (a.row as i32).abs_diff(b.row as i32) >= 2,
+        ),
+        // fingers are numbered from the pinky to the index finger on both hands
+        inward_roll: flag(rolling && b.finger as i32 > a.finger as i32),
+        outward_roll: flag(rolling && (b.finger as i32) < a.finger as i32),
+        alternation: flag(!same_hand),
+        ..Default::default()
+    }
+}
+
+/// Metrics that need three consecutive letters, 1 for every metric that applies
+pub(crate) fn trigram_metrics(a: &KeyboardKey, b: &KeyboardKey, c: &KeyboardKey) -> Metrics {
+    let one_hand = a.hand == b.hand && b.hand == c.hand;
+    let first = b.finger as i32 - a.finger as i32;
+    let second = c.finger as i32 - b.finger as i32;
+
+    Metrics {
+        skip_bigram: flag(a != c && a.hand == c.hand && a.finger == c.finger),
+        redirect: flag(one_hand && first * second < 0),
+        ..Default::default()
+    }
+}
+
+/// Number of occurrences of every metric in the letter n-grams of a text
+pub(crate) fn count_metrics<const N: usize>(
+    ngram_stats: &NgramStats,
+    key_positions: &[usize; N],
+    layout_map: &[KeyboardKey; N],
+) -> Metrics {
+    let key = |letter: usize| &layout_map[key_positions[letter]];
+
+    let bigrams = ngram_stats
+        .letter_bigrams
+        .iter()
+        .map(|([a, b], count)| bigram_metrics(key(*a), key(*b)) * *count as f64);
+    let trigrams = ngram_stats
+        .letter_trigrams
+        .iter()
+        .map(|([a, b, c], count)| trigram_metrics(key(*a), key(*b), key(*c)) * *count as f64);
+
+    bigrams.chain(trigrams).fold(Metrics::default(), Add::add)
+}
+
+/// Metrics as % of the letter bigrams and trigrams of a text
+pub(crate) fn rate_metrics<const N: usize>(
+    ngram_stats: &NgramStats,
+    key_positions: &[usize; N],
+    layout_map: &[KeyboardKey; N],
+) -> Metrics {
+    let counts = count_metrics(ngram_stats, key_positions, layout_map);
+
+    let percent = |total: usize| match total {
+        0 => 0.,
+        total => 100. / total as f64,
+    };
+    let bigrams = percent(
+        ngram_stats
+            .letter_bigrams
+            .iter()
+            .map(|(_, count)| count)
+            .sum(),
+    );
+    let trigrams = percent(
+        ngram_stats
+            .letter_trigrams
+            .iter()
+            .map(|(_, count)| count)
+            .sum(),
+    );
+
+    Metrics {
+        skip_bigram: counts.skip_bigram * trigrams,
+        redirect: counts.redirect * trigrams,
+        ..counts * bigrams
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objective::{get_key_positions, get_key_presses};
+    use crate::prelude::*;
+
+    #[test]
+    fn test_metrics_of_qwerty() {
+        let layout = QwertyEnUs();
+        let layout_map = layout.get_layout_map();
+        let key = |c: char| {
+            let index = layout
+                .get_genome()
+                .iter()
+                .position(|key| *key == c)
+                .expect("should be in genome");
+            &layout_map[index]
+        };
+        let bigram = |text: &str| {
+            let chars: Vec<char> = text.chars().collect();
+            bigram_metrics(key(chars[0]), key(chars[1]))
+        };
+        let trigram = |text: &str| {
+            let chars: Vec<char> = text.chars().collect();
+            trigram_metrics(key(chars[0]), key(chars[1]), key(chars[2]))
+        };
+
+        assert_eq!(bigram("ED").same_finger, 1.);
+        assert_eq!(bigram("EE"), Metrics::default());
+        assert_eq!(bigram("TE").lateral_stretch, 1.);
+        assert_eq!(bigram("CR").scissor, 1.);
+        assert_eq!(bigram("SF").inward_roll, 1.);
+        assert_eq!(bigram("FS").outward_roll, 1.);
+        assert_eq!(bigram("EJ").alternation, 1.);
+        assert_eq!(bigram("EJ").inward_roll, 0.);
+        assert_eq!(trigram("EJD").skip_bigram, 1.);
+        assert_eq!(trigram("SAD").redirect, 1.);
+        assert_eq!(trigram("ASD").redirect, 0.);
+
+        // a letter on the left thumb is neither a stretch nor a roll from the index finger
+        let index = KeyboardKey::from((4.5, 1.5, 4, 4, false));
+        let thumb = KeyboardKey::from((7., 0.5, 5, 9, false));
+        for metrics in [
+            bigram_metrics(&index, &thumb),
+            bigram_metrics(&thumb, &index),
+        ] {
+            assert_eq!(metrics, Metrics::default());
+        }
+    }
+
+    #[test]
+    fn test_rates_are_percentages() {
+        let layout = QwertyEnUs();
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let ngram_stats = NgramStats::new(&get_key_presses(&layout, &text), true);
+        let key_positions = get_key_positions(layout.get_genome(), layout.get_letter_list());
+
+        let rates = rate_metrics(&ngram_stats, &key_positions, &layout.get_layout_map());
+
+        for (name, rate) in rates.named() {
+            assert!((0. ..=100.).contains(&rate), "{name}: {rate}");
+        }
+        let same_hand = rates.inward_roll + rates.outward_roll + rates.same_finger;
+        assert!(rates.alternation + same_hand <= 100. + 1e-9);
+        assert!(rates.same_finger > 0. && rates.alternation > 0.);
+    }
+}
@@ -1,5 +1,8 @@
+pub(crate) mod constraints;
+pub mod custom_layout;
 pub(crate) mod finger_list;
 pub(crate) mod genome;
+pub mod geometry;
 pub(crate) mod key_maps;
 pub mod layout;
 pub(crate) mod layout_map;
@@ -11,9 +14,10 @@ pub(crate) enum Hand {
     Right,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum Finger {
-    Ring = 0,
+    Pinky = 0,
+    Ring,
     Middle,
     Index,
     Thumb,
@@ -0,0 +1,524 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_file::{self, ConfigFileError};
+
+use super::{
+    genome::GetGenome,
+    layout_map::{GetLayoutMap, KeyboardKey},
+    letter_list::GetLetterList,
+    Hand,
+};
+
+/// Penalty of a violated group without a configured penalty,
+/// large enough that the optimizers never accept such a candidate
+const REJECTED: f64 = 1e9;
+
+/// Rules that the optimized genomes have to follow.
+///
+/// Pinned characters are locked to fixed positions of the layout map, the random start genome
+/// and every move of the optimizers only change the free positions.
+/// Groups of characters are checked against the geometry of the keys, a violated group either
+/// rejects the genome or adds its penalty to the score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraints<const N: usize> {
+    letter_list: [char; N],
+    /// character locked to each layout map index
+    pinned: [Option<char>; N],
+    /// layout map indices that can change
+    free: Vec<usize>,
+    groups: Vec<Group<N>>,
+}
+
+/// Raw content of a constraints file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ConstraintsDefinition {
+    /// characters that stay where the baseline genome of the layout has them
+    keep: String,
+    /// character -> layout map index
+    pinned: HashMap<char, usize>,
+    groups: Vec<GroupDefinition>,
+}
+
+/// Rule for a group of characters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Rule {
+    /// every character is next to the following one in the same row
+    Adjacent,
+    /// all characters are typed by this hand
+    Hand { hand: Side },
+    /// all characters are typed by the same hand
+    SameHand,
+    /// all characters are typed by one of these fingers, numbered like in layout files
+    Fingers { fingers: Vec<i32> },
+    /// all characters are in one of these rows, numbered like in layout files
+    Rows { rows: Vec<i32> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GroupDefinition {
+    keys: String,
+    #[serde(flatten)]
+    rule: Rule,
+    /// added to the score for every violation, violations are rejected without a penalty
+    #[serde(default)]
+    penalty: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Group<const N: usize> {
+    definition: GroupDefinition,
+    keys: Vec<char>,
+    check: GroupCheck<N>,
+}
+
+/// Group rule resolved to layout map indices
+#[derive(Debug, Clone, PartialEq)]
+enum GroupCheck<const N: usize> {
+    /// every key has to be at one of the allowed indices
+    Positions([bool; N]),
+    /// right neighbour of every index, every key has to be left of the following key
+    Adjacent(Vec<[bool; N]>),
+    /// whether an index is typed by the left hand, all keys have to be on the same hand
+    SameHand([bool; N]),
+}
+
+#[derive(Debug)]
+pub enum ConstraintError {
+    File(ConfigFileError),
+    UnknownLetter(char),
+    InvalidIndex(usize),
+    /// two characters pinned to the same index
+    DuplicateIndex(usize),
+    /// one character pinned to two indices
+    DuplicateLetter(char),
+    TooFewFreeKeys,
+    InvalidGroup(String),
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(err) => write!(f, "{err}"),
+            Self::UnknownLetter(c) => write!(f, "'{c}' is not a letter of the layout"),
+            Self::InvalidIndex(index) => write!(f, "{index} is not a key of the layout map"),
+            Self::DuplicateIndex(index) => {
+                write!(f, "more than one character is pinned to key {index}")
+            }
+            Self::DuplicateLetter(c) => write!(f, "'{c}' is pinned to more than one key"),
+            Self::TooFewFreeKeys => write!(f, "at least two keys have to stay free"),
+            Self::InvalidGroup(reason) => write!(f, "invalid group: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintError {}
+
+impl From<ConfigFileError> for ConstraintError {
+    fn from(value: ConfigFileError) -> Self {
+        Self::File(value)
+    }
+}
+
+impl<const N: usize> Constraints<N> {
+    /// Every key of the layout is free
+    pub fn none(letter_list: &[char; N]) -> Self {
+        Self {
+            letter_list: *letter_list,
+            pinned: [None; N],
+            free: (0..N).collect(),
+            groups: Vec::new(),
+        }
+    }
+
+    /// Load a TOML or JSON constraints file for a layout
+    pub fn from_file<L: GetLetterList<N> + GetGenome<N> + GetLayoutMap<N>>(
+        path: impl AsRef<Path>,
+        layout: &L,
+    ) -> Result<Self, ConstraintError> {
+        let definition: ConstraintsDefinition = config_file::read_config_file(path.as_ref())?;
+        let mut constraints = Self::none(layout.get_letter_list());
+
+        for c in definition.keep.chars() {
+            constraints = constraints.keep(c, layout.get_genome())?;
+        }
+
+        let mut pinned: Vec<_> = definition.pinned.into_iter().collect();
+        pinned.sort_unstable();
+        for (c, index) in pinned {
+            constraints = constraints.pin(c, index)?;
+        }
+
+        let layout_map = layout.get_layout_map();
+        for group in definition.groups {
+            constraints = constraints.add_group(&layout_map, group)?;
+        }
+
+        Ok(constraints)
+    }
+
+    /// Add a rule for a group of characters, violations are rejected without a penalty
+    pub fn group<L: GetLayoutMap<N>>(
+        self,
+        layout: &L,
+        keys: &str,
+        rule: Rule,
+        penalty: Option<f64>,
+    ) -> Result<Self, ConstraintError> {
+        let definition = GroupDefinition {
+            keys: keys.to_string(),
+            rule,
+            penalty,
+        };
+
+        self.add_group(&layout.get_layout_map(), definition)
+    }
+
+    pub(crate) fn add_group(
+        mut self,
+        layout_map: &[KeyboardKey; N],
+        definition: GroupDefinition,
+    ) -> Result<Self, ConstraintError> {
+        let keys: Vec<char> = definition.keys.chars().collect();
+        if let Some(c) = keys.iter().find(|c| !self.letter_list.contains(c)) {
+            return Err(ConstraintError::UnknownLetter(*c));
+        }
+        if keys.len() < 2 && definition.rule == Rule::Adjacent {
+            return Err(ConstraintError::InvalidGroup(
+                "adjacent groups need at least two keys".to_string(),
+            ));
+        }
+
+        let positions = |allowed: &dyn Fn(&KeyboardKey) -> bool| {
+            GroupCheck::Positions(layout_map.map(|key| allowed(&key)))
+        };
+
+        let check = match &definition.rule {
+            Rule::Adjacent => GroupCheck::Adjacent(get_neighbours(layout_map)),
+            Rule::Hand { hand } => {
+                let hand = match hand {
+                    Side::Left => Hand::Left,
+                    Side::Right => Hand::Right,
+                };
+                positions(&|key| key.hand == hand)
+            }
+            Rule::SameHand => GroupCheck::SameHand(layout_map.map(|key| key.hand == Hand::Left)),
+            Rule::Fingers { fingers } => {
+                if let Some(finger) = fingers.iter().find(|finger| !(1..=10).contains(*finger)) {
+                    return Err(ConstraintError::InvalidGroup(format!(
+                        "finger {finger} does not exist, expected 1-10"
+                    )));
+                }
+                positions(&|key| fingers.contains(&key.get_finger_number()))
+            }
+            Rule::Rows { rows } => {
+                if let Some(row) = rows.iter().find(|row| !(1..=5).contains(*row)) {
+                    return Err(ConstraintError::InvalidGroup(format!(
+                        "row {row} does not exist, expected 1-5"
+                    )));
+                }
+                positions(&|key| rows.contains(&(key.row as i32 + 1)))
+            }
+        };
+
+        self.groups.push(Group {
+            definition,
+            keys,
+            check,
+        });
+
+        Ok(self)
+    }
+
+    /// Lock a character to a layout map index
+    pub fn pin(mut self, c: char, index: usize) -> Result<Self, ConstraintError> {
+        if !self.letter_list.contains(&c) {
+            return Err(ConstraintError::UnknownLetter(c));
+        }
+        if index >= N {
+            return Err(ConstraintError::InvalidIndex(index));
+        }
+        if self.pinned[index].is_some() {
+            return Err(ConstraintError::DuplicateIndex(index));
+        }
+        if self.pinned.contains(&Some(c)) {
+            return Err(ConstraintError::DuplicateLetter(c));
+        }
+        if self.free.len() <= 2 {
+            return Err(ConstraintError::TooFewFreeKeys);
+        }
+
+        self.pinned[index] = Some(c);
+        self.free.retain(|free| *free != index);
+
+        Ok(self)
+    }
+
+    /// Lock a character to its position in `genome`
+    pub fn keep(self, c: char, genome: &[char; N]) -> Result<Self, ConstraintError> {
+        let index = genome
+            .iter()
+            .position(|key| *key == c)
+            .ok_or(ConstraintError::UnknownLetter(c))?;
+
+        self.pin(c, index)
+    }
+
+    /// Pinned characters and their layout map indices
+    pub fn pinned(&self) -> impl Iterator<Item = (char, usize)> + '_ {
+        self.pinned
+            .iter()
+            .enumerate()
+            .filter_map(|(index, c)| c.map(|c| (c, index)))
+    }
+
+    /// Layout map indices that can change
+    pub(crate) fn free(&self) -> &[usize] {
+        &self.free
+    }
+
+    /// Characters that are not pinned in the order of the letter list
+    pub(crate) fn free_letters(&self) -> impl Iterator<Item = char> + '_ {
+        self.letter_list
+            .iter()
+            .copied()
+            .filter(|c| !self.pinned.contains(&Some(*c)))
+    }
+
+    pub(crate) fn is_pinned(&self, index: usize) -> bool {
+        self.pinned[index].is_some()
+    }
+
+    pub(crate) fn group_definitions(&self) -> impl Iterator<Item = &GroupDefinition> {
+        self.groups.iter().map(|group| &group.definition)
+    }
+
+    pub(crate) fn has_groups(&self) -> bool {
+        !self.groups.is_empty()
+    }
+
+    /// Whether every pinned character is at its index and no group without penalty is violated
+    pub fn allows(&self, genome: &[char; N]) -> bool {
+        self.pinned().all(|(c, index)| genome[index] == c) && self.rejected(genome) == 0
+    }
+
+    /// Violations of groups without penalty
+    pub(crate) fn rejected(&self, genome: &[char; N]) -> usize {
+        self.groups
+            .iter()
+            .filter(|group| group.definition.penalty.is_none())
+            .map(|group| group.violations(genome))
+            .sum()
+    }
+
+    /// Sum of the penalties of all violated groups, added to the relative score
+    pub fn penalty(&self, genome: &[char; N]) -> f64 {
+        self.groups
+            .iter()
+            .map(|group| {
+                group.violations(genome) as f64 * group.definition.penalty.unwrap_or(REJECTED)
+            })
+            .sum()
+    }
+
+    /// Swap free keys until no group without penalty is violated or no swap reduces the violations
+    pub(crate) fn repair(&self, genome: &mut [char; N]) {
+        let mut violations = self.rejected(genome);
+
+        while violations > 0 {
+            let mut best = None;
+            for (i, a) in self.free.iter().enumerate() {
+                for b in &self.free[i + 1..] {
+                    genome.swap(*a, *b);
+                    let swapped = self.rejected(genome);
+                    genome.swap(*a, *b);
+
+                    if swapped < best.map_or(violations, |(_, best)| best) {
+                        best = Some(((*a, *b), swapped));
+                    }
+                }
+            }
+
+            let Some(((a, b), swapped)) = best else {
+                break;
+            };
+            genome.swap(a, b);
+            violations = swapped;
+        }
+    }
+}
+
+impl<const N: usize> Group<N> {
+    fn violations(&self, genome: &[char; N]) -> usize {
+        let positions = self.keys.iter().map(|c| {
+            genome
+                .iter()
+                .position(|key| key == c)
+                .expect("group keys should be in the genome")
+        });
+
+        match &self.check {
+            GroupCheck::Positions(allowed) => positions.filter(|index| !allowed[*index]).count(),
+            GroupCheck::Adjacent(neighbours) => {
+                let positions: Vec<usize> = positions.collect();
+                positions
+                    .windows(2)
+                    .filter(|pair| !neighbours[pair[0]][pair[1]])
+                    .count()
+            }
+            GroupCheck::SameHand(left) => {
+                let left_count = positions.filter(|index| left[*index]).count();
+                left_count.min(self.keys.len() - left_count)
+            }
+        }
+    }
+}
+
+/// Whether the second key is the right neighbour of the first in the same row
+fn get_neighbours<const N: usize>(layout_map: &[KeyboardKey; N]) -> Vec<[bool; N]> {
+    let mut neighbours = vec![[false; N]; N];
+
+    for (i, key) in layout_map.iter().enumerate() {
+        let right_neighbour = layout_map
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| other.row == key.row && other.x > key.x)
+            .min_by(|(_, a), (_, b)| a.x.total_cmp(&b.x));
+
+        if let Some((j, _)) = right_neighbour {
+            neighbours[i][j] = true;
+        }
+    }
+
+    neighbours
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::models::genome::create_genome;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_pins_are_validated() {
+        let layout = QwertzDeDe();
+        let constraints = Constraints::none(layout.get_letter_list());
+
+        assert!(matches!(
+            constraints.clone().pin('€', 0),
+            Err(ConstraintError::UnknownLetter('€'))
+        ));
+        assert!(matches!(
+            constraints.clone().pin('Q', 48),
+            Err(ConstraintError::InvalidIndex(48))
+        ));
+        assert!(matches!(
+            constraints.clone().pin('Q', 0).and_then(|c| c.pin('W', 0)),
+            Err(ConstraintError::DuplicateIndex(0))
+        ));
+        assert!(matches!(
+            constraints.clone().pin('Q', 0).and_then(|c| c.pin('Q', 1)),
+            Err(ConstraintError::DuplicateLetter('Q'))
+        ));
+
+        let mut all = Ok(constraints);
+        for (index, c) in layout.get_genome().iter().enumerate() {
+            all = all.and_then(|constraints| constraints.pin(*c, index));
+        }
+        assert!(matches!(all, Err(ConstraintError::TooFewFreeKeys)));
+    }
+
+    #[test]
+    fn test_example_file_keeps_number_row() {
+        let layout = QwertzDeDe();
+        let constraints =
+            Constraints::from_file("resources/constraints.toml", &layout).expect("should load");
+
+        for (c, index) in constraints.pinned() {
+            if c.is_ascii_digit() {
+                assert_eq!(layout.get_genome()[index], c);
+            }
+        }
+        assert!(constraints.pinned().any(|(c, _)| c == 'Ö'));
+    }
+
+    #[test]
+    fn test_groups_are_checked_against_key_geometry() {
+        let layout = QwertyEnUs();
+        let genome = layout.get_genome();
+        let group = |keys, rule| {
+            Constraints::none(layout.get_letter_list())
+                .group(&layout, keys, rule, Some(1.))
+                .expect("should add group")
+                .penalty(genome)
+        };
+
+        assert_eq!(group("[]", Rule::Adjacent), 0.);
+        assert_eq!(group("][", Rule::Adjacent), 1.);
+        assert_eq!(group("QWEP", Rule::Adjacent), 1.);
+        assert_eq!(group("[W", Rule::Adjacent), 1.);
+        assert_eq!(group("ZXCV", Rule::Hand { hand: Side::Left }), 0.);
+        assert_eq!(group("ZXCM", Rule::Hand { hand: Side::Left }), 1.);
+        assert_eq!(group("AEIOU", Rule::SameHand), 2.);
+        assert_eq!(group("QP", Rule::Fingers { fingers: vec![1] }), 1.);
+        assert_eq!(
+            group(
+                "JP",
+                Rule::Fingers {
+                    fingers: vec![5, 8]
+                }
+            ),
+            0.
+        );
+        assert_eq!(group("ASDF", Rule::Rows { rows: vec![3] }), 0.);
+        assert_eq!(group("1QA", Rule::Rows { rows: vec![3] }), 2.);
+
+        let constraints = Constraints::none(layout.get_letter_list());
+        assert!(matches!(
+            constraints
+                .clone()
+                .group(&layout, "Q", Rule::Adjacent, None),
+            Err(ConstraintError::InvalidGroup(_))
+        ));
+        assert!(matches!(
+            constraints
+                .clone()
+                .group(&layout, "Q", Rule::Fingers { fingers: vec![11] }, None),
+            Err(ConstraintError::InvalidGroup(_))
+        ));
+        assert!(matches!(
+            constraints.group(&layout, "Ö", Rule::SameHand, None),
+            Err(ConstraintError::UnknownLetter('Ö'))
+        ));
+    }
+
+    #[test]
+    fn test_random_genomes_keep_groups_without_penalty() {
+        let layout = QwertyEnUs();
+        let constraints = Constraints::none(layout.get_letter_list())
+            .pin('A', 0)
+            .and_then(|c| c.group(&layout, "[]", Rule::Adjacent, None))
+            .and_then(|c| c.group(&layout, "ZXCV", Rule::Hand { hand: Side::Left }, None))
+            .and_then(|c| c.group(&layout, "AEIOU", Rule::SameHand, Some(5.)))
+            .expect("should add groups");
+
+        let mut rng = rand_chacha::ChaCha12Rng::seed_from_u64(0);
+        for _ in 0..20 {
+            let genome = create_genome(&constraints, &mut rng);
+            assert!(constraints.allows(&genome));
+            assert!(constraints.penalty(&genome) < REJECTED);
+        }
+    }
+}
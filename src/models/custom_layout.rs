@@ -0,0 +1,598 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_file::{self, ConfigFileError};
+
+use super::{
+    genome::GetGenome,
+    geometry::GeometryError,
+    key_maps::GetKeyMap,
+    layout::{ILayout, Layout},
+    layout_map::{
+        get_default_fixed_key, FixedKey, FixedKeys, GetLayoutMap, KeyboardKey, KEY_PITCH_MM,
+    },
+    letter_list::GetLetterList,
+};
+
+/// Layout that is loaded at runtime from a TOML or JSON layout definition file.
+///
+/// The number of keys is still a compile time parameter, loading a file with a different
+/// number of keys results in an error.
+#[derive(Debug, Clone)]
+pub struct CustomLayout<const N: usize> {
+    name: String,
+    genome: [char; N],
+    letter_list: [char; N],
+    layout_map: [KeyboardKey; N],
+    fixed_keys: FixedKeys,
+    key_map: HashMap<char, (usize, bool)>,
+    dead_keys: Vec<char>,
+}
+
+/// Raw content of a layout definition file
+#[derive(Debug, Serialize, Deserialize)]
+struct LayoutDefinition {
+    name: String,
+    letters: Vec<char>,
+    genome: Vec<char>,
+    /// unit of the positions and sizes of the keys
+    #[serde(default)]
+    units: Units,
+    keys: Vec<KeyDefinition>,
+    /// shift, space, tab, enter and backspace keys, placed next to the rows if missing
+    #[serde(default)]
+    fixed_keys: BTreeMap<String, KeyDefinition>,
+    /// letter -> [unshifted, shifted]
+    #[serde(serialize_with = "serialize_key_map")]
+    key_map: BTreeMap<char, Vec<char>>,
+    /// characters of the key map that change the next character instead of typing
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dead_keys: Vec<char>,
+}
+
+/// TOML tables only have string keys
+fn serialize_key_map<S: serde::Serializer>(
+    key_map: &BTreeMap<char, Vec<char>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        key_map
+            .iter()
+            .map(|(letter, characters)| (letter.to_string(), characters)),
+    )
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Units {
+    /// width of a standard key
+    #[default]
+    Key,
+    Mm,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyDefinition {
+    /// center of the key, y grows towards the number row
+    x: f64,
+    y: f64,
+    /// a standard key if missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<f64>,
+    /// degrees clockwise around the center
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotation: Option<f64>,
+    row: i32,
+    finger: i32,
+    home: bool,
+}
+
+#[derive(Debug)]
+pub enum LayoutDefinitionError {
+    File(ConfigFileError),
+    WrongKeyCount {
+        field: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    DuplicateLetter(char),
+    GenomeMismatch(char),
+    InvalidRow(String),
+    InvalidFinger(String),
+    DuplicateHomeKey(String),
+    UnknownLetter(char),
+    TooManyCharacters(char),
+    DuplicateCharacter(char),
+    UnknownFixedKey(String),
+    MissingFixedKey(&'static str),
+    UnknownDeadKey(char),
+}
+
+impl fmt::Display for LayoutDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(err) => write!(f, "{err}"),
+            Self::WrongKeyCount {
+                field,
+                expected,
+                found,
+            } => write!(f, "'{field}' must have {expected} entries, found {found}"),
+            Self::DuplicateLetter(c) => write!(f, "letter '{c}' is listed more than once"),
+            Self::GenomeMismatch(c) => {
+                write!(
+                    f,
+                    "genome is not a permutation of the letters, '{c}' does not match"
+                )
+            }
+            Self::InvalidRow(key) => write!(f, "{key} has an invalid row, expected 1-5"),
+            Self::InvalidFinger(key) => {
+                write!(f, "{key} has an invalid finger, expected 1-10")
+            }
+            Self::DuplicateHomeKey(key) => {
+                write!(f, "{key} is a second home key for the same finger")
+            }
+            Self::UnknownLetter(c) => write!(f, "key map uses '{c}' which is not a letter"),
+            Self::TooManyCharacters(c) => {
+                write!(
+                    f,
+                    "key '{c}' has more than two characters, one per shift level"
+                )
+            }
+            Self::DuplicateCharacter(c) => {
+                write!(f, "character '{c}' is typed by more than one key")
+            }
+            Self::UnknownFixedKey(name) => write!(f, "unknown fixed key '{name}'"),
+            Self::MissingFixedKey(name) => {
+                write!(f, "fixed key '{name}' is required if its row is missing")
+            }
+            Self::UnknownDeadKey(c) => write!(f, "dead key '{c}' is not in the key map"),
+        }
+    }
+}
+
+impl std::error::Error for LayoutDefinitionError {}
+
+impl From<ConfigFileError> for LayoutDefinitionError {
+    fn from(value: ConfigFileError) -> Self {
+        Self::File(value)
+    }
+}
+
+impl<const N: usize> CustomLayout<N> {
+    /// Load a layout definition, the format is selected by the file extension (`.toml` or `.json`)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LayoutDefinitionError> {
+        let definition: LayoutDefinition = config_file::read_config_file(path.as_ref())?;
+        Self::try_from(definition)
+    }
+
+    pub fn from_toml_str(content: &str) -> Result<Self, LayoutDefinitionError> {
+        let definition: LayoutDefinition = config_file::from_toml_str(content)?;
+        Self::try_from(definition)
+    }
+
+    pub fn from_json_str(content: &str) -> Result<Self, LayoutDefinitionError> {
+        let definition: LayoutDefinition = config_file::from_json_str(content)?;
+        Self::try_from(definition)
+    }
+
+    /// Characters, key map and letter list of `layout` on the keys of another keyboard
+    pub(crate) fn from_layout<L: ILayout<N>>(
+        name: &str,
+        layout: &L,
+        genome: [char; N],
+        layout_map: [KeyboardKey; N],
+        fixed_keys: FixedKeys,
+    ) -> Self {
+        let key_map = layout.get_key_map_entries().into_iter().collect();
+
+        Self {
+            name: name.to_string(),
+            genome,
+            letter_list: *layout.get_letter_list(),
+            layout_map,
+            fixed_keys,
+            key_map,
+            dead_keys: layout.get_dead_keys().to_vec(),
+        }
+    }
+
+    /// Save as a layout definition file, the format is selected by the file extension (`.toml` or `.json`).
+    /// Characters that are only typed with shift on a letter without an unshifted character are lost.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigFileError> {
+        config_file::write_config_file(path.as_ref(), &LayoutDefinition::from(self))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<const N: usize> From<&CustomLayout<N>> for LayoutDefinition {
+    fn from(layout: &CustomLayout<N>) -> Self {
+        let mut characters: BTreeMap<char, [Option<char>; 2]> = BTreeMap::new();
+        for (c, (index, shift)) in &layout.key_map {
+            characters.entry(layout.letter_list[index - 1]).or_default()[*shift as usize] =
+                Some(*c);
+        }
+
+        Self {
+            name: layout.name.clone(),
+            letters: layout.letter_list.to_vec(),
+            genome: layout.genome.to_vec(),
+            units: Units::Key,
+            keys: layout
+                .layout_map
+                .iter()
+                .map(KeyDefinition::from_key)
+                .collect(),
+            fixed_keys: FixedKey::ALL
+                .iter()
+                .map(|key| {
+                    let definition = KeyDefinition::from_key(&layout.fixed_keys[*key]);
+                    (key.name().to_string(), definition)
+                })
+                .collect(),
+            key_map: characters
+                .into_iter()
+                .filter_map(|(letter, [unshifted, shifted])| {
+                    let characters = [unshifted?].into_iter().chain(shifted).collect();
+                    Some((letter, characters))
+                })
+                .collect(),
+            dead_keys: layout.dead_keys.clone(),
+        }
+    }
+}
+
+impl KeyDefinition {
+    fn from_key(key: &KeyboardKey) -> Self {
+        let optional = |value: f64, default: f64| (value != default).then_some(value);
+
+        Self {
+            x: key.x,
+            y: key.y,
+            width: optional(key.width, 1.),
+            height: optional(key.height, 1.),
+            rotation: optional(key.rotation, 0.),
+            row: key.row as i32 + 1,
+            finger: key.get_finger_number(),
+            home: key.home,
+        }
+    }
+
+    fn to_key(&self, units: Units) -> KeyboardKey {
+        let scale = match units {
+            Units::Key => 1.,
+            Units::Mm => 1. / KEY_PITCH_MM,
+        };
+
+        KeyboardKey::from((
+            self.x * scale,
+            self.y * scale,
+            self.row,
+            self.finger,
+            self.home,
+        ))
+        .with_size(
+            self.width.map_or(1., |width| width * scale),
+            self.height.map_or(1., |height| height * scale),
+        )
+        .with_rotation(self.rotation.unwrap_or_default())
+    }
+}
+
+fn to_array<const N: usize>(
+    field: &'static str,
+    values: Vec<char>,
+) -> Result<[char; N], LayoutDefinitionError> {
+    let found = values.len();
+    values
+        .try_into()
+        .map_err(|_| LayoutDefinitionError::WrongKeyCount {
+            field,
+            expected: N,
+            found,
+        })
+}
+
+impl<const N: usize> TryFrom<LayoutDefinition> for CustomLayout<N> {
+    type Error = LayoutDefinitionError;
+
+    fn try_from(definition: LayoutDefinition) -> Result<Self, Self::Error> {
+        let letter_list: [char; N] = to_array("letters", definition.letters)?;
+        let genome: [char; N] = to_array("genome", definition.genome)?;
+
+        for (i, letter) in letter_list.iter().enumerate() {
+            if letter_list[..i].contains(letter) {
+                return Err(LayoutDefinitionError::DuplicateLetter(*letter));
+            }
+        }
+
+        let mut sorted_letters = letter_list;
+        sorted_letters.sort_unstable();
+        let mut sorted_genome = genome;
+        sorted_genome.sort_unstable();
+        if let Some((_, c)) = sorted_letters
+            .iter()
+            .zip(sorted_genome.iter())
+            .find(|(l, g)| l != g)
+        {
+            return Err(LayoutDefinitionError::GenomeMismatch(*c));
+        }
+
+        if definition.keys.len() != N {
+            return Err(LayoutDefinitionError::WrongKeyCount {
+                field: "keys",
+                expected: N,
+                found: definition.keys.len(),
+            });
+        }
+
+        if let Some(name) = definition
+            .fixed_keys
+            .keys()
+            .find(|name| !FixedKey::ALL.iter().any(|key| key.name() == *name))
+        {
+            return Err(LayoutDefinitionError::UnknownFixedKey(name.clone()));
+        }
+
+        let mut home_fingers = [false; 10];
+        let keys = definition
+            .keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (format!("key {i}"), key));
+        let fixed_keys = definition
+            .fixed_keys
+            .iter()
+            .map(|(name, key)| (format!("fixed key '{name}'"), key));
+        for (name, key) in keys.chain(fixed_keys) {
+            if !(1..=5).contains(&key.row) {
+                return Err(LayoutDefinitionError::InvalidRow(name));
+            }
+            if !(1..=10).contains(&key.finger) {
+                return Err(LayoutDefinitionError::InvalidFinger(name));
+            }
+            if key.home {
+                let finger = (key.finger - 1) as usize;
+                if home_fingers[finger] {
+                    return Err(LayoutDefinitionError::DuplicateHomeKey(name));
+                }
+                home_fingers[finger] = true;
+            }
+        }
+
+        let layout_map: [KeyboardKey; N] = definition
+            .keys
+            .iter()
+            .map(|key| key.to_key(definition.units))
+            .collect::<Vec<_>>()
+            .try_into()
+            .expect("key count was validated");
+
+        let mut fixed_key_definitions = definition.fixed_keys;
+        let mut fixed_keys = Vec::new();
+        for fixed_key in FixedKey::ALL {
+            let key = match fixed_key_definitions.remove(fixed_key.name()) {
+                Some(key) => key.to_key(definition.units),
+                None => get_default_fixed_key(&layout_map, fixed_key)
+                    .ok_or(LayoutDefinitionError::MissingFixedKey(fixed_key.name()))?,
+            };
+            fixed_keys.push(key);
+        }
+        let fixed_keys = FixedKeys(fixed_keys.try_into().expect("all fixed keys were added"));
+
+        let mut key_map = HashMap::new();
+        for (letter, characters) in definition.key_map {
+            let index = letter_list
+                .iter()
+                .position(|l| *l == letter)
+                .ok_or(LayoutDefinitionError::UnknownLetter(letter))?;
+            if characters.len() > 2 {
+                return Err(LayoutDefinitionError::TooManyCharacters(letter));
+            }
+
+            for (shift, c) in characters.into_iter().enumerate() {
+                if key_map.insert(c, (index + 1, shift == 1)).is_some() {
+                    return Err(LayoutDefinitionError::DuplicateCharacter(c));
+                }
+            }
+        }
+
+        if let Some(c) = definition
+            .dead_keys
+            .iter()
+            .find(|c| !key_map.contains_key(c))
+        {
+            return Err(LayoutDefinitionError::UnknownDeadKey(*c));
+        }
+
+        Ok(Self {
+            name: definition.name,
+            genome,
+            letter_list,
+            layout_map,
+            fixed_keys,
+            key_map,
+            dead_keys: definition.dead_keys,
+        })
+    }
+}
+
+impl<const N: usize> GetGenome<N> for CustomLayout<N> {
+    fn get_genome(&self) -> &[char; N] {
+        &self.genome
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; N])> {
+        vec![(&self.name, self.genome)]
+    }
+}
+
+impl<const N: usize> GetKeyMap for CustomLayout<N> {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        self.key_map.get(&c).copied()
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        self.key_map.iter().map(|(c, key)| (*c, *key)).collect()
+    }
+
+    fn get_dead_keys(&self) -> &[char] {
+        &self.dead_keys
+    }
+}
+
+impl<const N: usize> GetLetterList<N> for CustomLayout<N> {
+    fn get_letter_list(&self) -> &[char; N] {
+        &self.letter_list
+    }
+}
+
+impl<const N: usize> GetLayoutMap<N> for CustomLayout<N> {
+    fn get_layout_map(&self) -> [KeyboardKey; N] {
+        self.layout_map
+    }
+
+    fn get_fixed_keys(&self) -> Result<FixedKeys, GeometryError> {
+        Ok(self.fixed_keys)
+    }
+}
+
+impl<const N: usize> ILayout<N> for CustomLayout<N> {
+    fn get(&self) -> Layout {
+        Layout::Custom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::key_maps::sorted_key_map_entries;
+    use crate::models::layout::{
+        BoneDeDe, ColemakDhEnUs, ColemakEnUs, DvorakEnUs, KoyDeDe, Neo2DeDe, QwertyEnUs,
+        QwertzDeDe, WorkmanEnUs,
+    };
+
+    fn assert_same_layout<L: ILayout<N>, const N: usize>(custom: &CustomLayout<N>, layout: &L) {
+        assert_eq!(custom.get_genome(), layout.get_genome());
+        assert_eq!(custom.get_letter_list(), layout.get_letter_list());
+
+        assert_eq!(custom.get_layout_map(), layout.get_layout_map());
+        assert_eq!(
+            custom.get_fixed_keys().expect("fixed keys"),
+            layout.get_fixed_keys().expect("fixed keys")
+        );
+
+        assert_eq!(
+            sorted_key_map_entries(custom),
+            sorted_key_map_entries(layout)
+        );
+        assert_eq!(custom.get_dead_keys(), layout.get_dead_keys());
+    }
+
+    #[test]
+    fn test_toml_matches_builtin_layouts() {
+        let qwerty = CustomLayout::<46>::from_file("resources/layouts/qwerty_en_us.toml")
+            .expect("should load layout");
+        assert_same_layout(&qwerty, &QwertyEnUs());
+
+        let qwertz = CustomLayout::<48>::from_file("resources/layouts/qwertz_de_de.toml")
+            .expect("should load layout");
+        assert_same_layout(&qwertz, &QwertzDeDe());
+
+        let load = |file| {
+            CustomLayout::<46>::from_file(format!("resources/layouts/{file}.toml"))
+                .expect("should load layout")
+        };
+        assert_same_layout(&load("dvorak_en_us"), &DvorakEnUs());
+        assert_same_layout(&load("colemak_en_us"), &ColemakEnUs());
+        assert_same_layout(&load("colemak_dh_en_us"), &ColemakDhEnUs());
+        assert_same_layout(&load("workman_en_us"), &WorkmanEnUs());
+        assert_same_layout(&load("neo2_de_de"), &Neo2DeDe());
+        assert_same_layout(&load("bone_de_de"), &BoneDeDe());
+        assert_same_layout(&load("koy_de_de"), &KoyDeDe());
+    }
+
+    #[test]
+    fn test_json_layout_is_validated() {
+        let json = r#"{
+            "name": "tiny",
+            "letters": ["A", "B"],
+            "genome": ["A", "A"],
+            "keys": [
+                { "x": 0, "y": 0, "row": 3, "finger": 1, "home": true },
+                { "x": 1, "y": 0, "row": 3, "finger": 8, "home": true }
+            ],
+            "fixed_keys": {
+                "left_shift": { "x": -1, "y": -1, "row": 4, "finger": 1, "home": false },
+                "right_shift": { "x": 2, "y": -1, "row": 4, "finger": 8, "home": false },
+                "left_space": { "x": 0, "y": -2, "row": 5, "finger": 9, "home": true },
+                "right_space": { "x": 1, "y": -2, "row": 5, "finger": 10, "home": true },
+                "tab": { "x": -1, "y": 0, "row": 3, "finger": 1, "home": false },
+                "enter": { "x": 2, "y": 0, "row": 3, "finger": 8, "home": false },
+                "backspace": { "x": 2, "y": 1, "row": 3, "finger": 8, "home": false }
+            },
+            "key_map": { "A": ["a", "A"], "B": ["b", "B"] }
+        }"#;
+
+        assert!(matches!(
+            CustomLayout::<2>::from_json_str(json),
+            Err(LayoutDefinitionError::GenomeMismatch(_))
+        ));
+        assert!(matches!(
+            CustomLayout::<3>::from_json_str(json),
+            Err(LayoutDefinitionError::WrongKeyCount { .. })
+        ));
+
+        let json = json.replace(r#"["A", "A"]"#, r#"["B", "A"]"#);
+        assert!(matches!(
+            CustomLayout::<2>::from_json_str(&json.replace(r#"["a", "A"]"#, r#"["a", "A", "ä"]"#)),
+            Err(LayoutDefinitionError::TooManyCharacters('A'))
+        ));
+
+        let layout = CustomLayout::<2>::from_json_str(&json).expect("should load layout");
+        assert_eq!(layout.get_key_map('B'), Some((2, true)));
+    }
+
+    #[test]
+    fn test_millimetres_are_converted_to_key_units() {
+        let content = std::fs::read_to_string("resources/layouts/qwerty_en_us.toml")
+            .expect("should read file");
+        let millimetres = content
+            .lines()
+            .map(|line| match line.trim_start().strip_prefix("{ x = ") {
+                Some(key) => {
+                    let (x, rest) = key.split_once(", y = ").expect("key has y");
+                    let (y, rest) = rest.split_once(',').expect("key has a row");
+                    let mm = |value: &str| value.parse::<f64>().expect("number") * KEY_PITCH_MM;
+                    format!(
+                        "{{ x = {}, y = {}, width = {KEY_PITCH_MM},{rest}",
+                        mm(x),
+                        mm(y)
+                    )
+                }
+                None => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let layout = CustomLayout::<46>::from_toml_str(&format!("units = \"mm\"\n{millimetres}"))
+            .expect("should load layout");
+        for (key, expected) in layout
+            .get_layout_map()
+            .iter()
+            .zip(QwertyEnUs().get_layout_map().iter())
+        {
+            assert!((key.x - expected.x).abs() < 1e-9 && (key.y - expected.y).abs() < 1e-9);
+            assert!((key.width - 1.).abs() < 1e-9 && key.height == 1.);
+        }
+    }
+}
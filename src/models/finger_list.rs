@@ -1,5 +1,5 @@
 // (homeX, homeY, currentX, currentY, distanceCounter, objectiveCounter)
-pub type FingerList = [FingerListItem; 8];
+pub type FingerList = [FingerListItem; 10];
 
 pub fn get_finger_list() -> FingerList {
     [
@@ -11,15 +11,18 @@ pub fn get_finger_list() -> FingerList {
         FingerListItem::default(),
         FingerListItem::default(),
         FingerListItem::default(),
+        FingerListItem::default(),
+        FingerListItem::default(),
     ]
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct FingerListItem {
-    pub home_x: i32,
-    pub home_y: i32,
-    pub current_x: i32,
-    pub current_y: i32,
-    pub distance_counter: i32,
+    pub home_x: f64,
+    pub home_y: f64,
+    pub current_x: f64,
+    pub current_y: f64,
+    /// key units
+    pub distance_counter: f64,
     pub objective_counter: f64,
 }
@@ -1,26 +1,72 @@
-use rand::{seq::SliceRandom, RngCore};
+use rand::{seq::SliceRandom, Rng, RngCore};
 
-use super::layout::{QwertyEnUs, QwertzDeDe};
+use super::constraints::Constraints;
+use super::layout::{
+    BoneDeDe, ColemakDhEnUs, ColemakEnUs, DvorakEnUs, KoyDeDe, Neo2DeDe, QwertyEnUs, QwertzDeDe,
+    WorkmanEnUs,
+};
 
+/// Random genome with the pinned characters of `constraints` in place,
+/// groups that have to be kept are repaired by swapping free keys
 pub(crate) fn create_genome<const N: usize>(
-    letter_list: &mut [char; N],
+    constraints: &Constraints<N>,
     rng: &mut impl RngCore,
 ) -> [char; N] {
-    letter_list.shuffle(rng);
-    *letter_list
+    let mut genome = [' '; N];
+    for (c, index) in constraints.pinned() {
+        genome[index] = c;
+    }
+
+    let mut letters: Vec<char> = constraints.free_letters().collect();
+    letters.shuffle(rng);
+    for (index, c) in constraints.free().iter().zip(letters) {
+        genome[*index] = c;
+    }
+    constraints.repair(&mut genome);
+
+    genome
+}
+
+/// Parse a genome from a string with one character per key,
+/// returns `None` if it is not a permutation of the letter list
+pub fn parse_genome<const N: usize>(value: &str, letter_list: &[char; N]) -> Option<[char; N]> {
+    let genome: [char; N] = value.chars().collect::<Vec<_>>().try_into().ok()?;
+
+    let mut sorted_genome = genome;
+    sorted_genome.sort_unstable();
+    let mut sorted_letters = *letter_list;
+    sorted_letters.sort_unstable();
+
+    (sorted_genome == sorted_letters).then_some(genome)
 }
 
 // SA OPTIMIZER
+/// Two distinct free positions of a genome to swap
+pub(crate) fn random_swap<const N: usize>(
+    constraints: &Constraints<N>,
+    rng: &mut impl RngCore,
+) -> (usize, usize) {
+    let free = constraints.free();
+    let a = rng.gen_range(0..free.len());
+    let b = (a + rng.gen_range(1..free.len())) % free.len();
+    (free[a], free[b])
+}
+
 pub(crate) fn shuffle_genome<const N: usize>(
     current_genome: &[char; N],
     temperature: f64,
+    constraints: &Constraints<N>,
     rng: &mut impl RngCore,
 ) -> [char; N] {
     // setup
-    let no_switches = (temperature / 100.0).floor().min(N as f64).max(2.0) as usize;
+    let free = constraints.free();
+    let no_switches = (temperature / 100.0)
+        .floor()
+        .min(free.len() as f64)
+        .max(2.0) as usize;
 
     // positions of switched letterList
-    let mut switched_positions: Vec<usize> = (0..N).collect();
+    let mut switched_positions = free.to_vec();
     switched_positions.shuffle(rng);
     let switched_positions = &switched_positions[0..no_switches];
 
@@ -41,45 +87,191 @@ pub(crate) fn shuffle_genome<const N: usize>(
 // comparisons
 // initial index defines the starting key a character will be placed on
 pub trait GetGenome<const N: usize> {
-    fn get_genome() -> &'static [char; N];
+    fn get_genome(&self) -> &[char; N];
+
+    /// Known genomes for the keys of the layout by name, the baseline genome first
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; N])> {
+        vec![("baseline", *self.get_genome())]
+    }
 }
 
 impl GetGenome<46> for QwertyEnUs {
-    fn get_genome() -> &'static [char; 46] {
+    fn get_genome(&self) -> &[char; 46] {
         &QWERTY_GENOME
     }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 46])> {
+        baseline_first(self.get_genome(), &EN_US_GENOMES)
+    }
+}
+
+impl GetGenome<46> for DvorakEnUs {
+    fn get_genome(&self) -> &[char; 46] {
+        &DVORAK_GENOME
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 46])> {
+        baseline_first(self.get_genome(), &EN_US_GENOMES)
+    }
+}
+
+impl GetGenome<46> for ColemakEnUs {
+    fn get_genome(&self) -> &[char; 46] {
+        &COLEMAK_GENOME
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 46])> {
+        baseline_first(self.get_genome(), &EN_US_GENOMES)
+    }
+}
+
+impl GetGenome<46> for ColemakDhEnUs {
+    fn get_genome(&self) -> &[char; 46] {
+        &COLEMAK_DH_GENOME
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 46])> {
+        baseline_first(self.get_genome(), &EN_US_GENOMES)
+    }
+}
+
+impl GetGenome<46> for WorkmanEnUs {
+    fn get_genome(&self) -> &[char; 46] {
+        &WORKMAN_GENOME
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 46])> {
+        baseline_first(self.get_genome(), &EN_US_GENOMES)
+    }
 }
 
 impl GetGenome<48> for QwertzDeDe {
-    fn get_genome() -> &'static [char; 48] {
+    fn get_genome(&self) -> &[char; 48] {
         &QWERTZ_GENOME
     }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 48])> {
+        vec![("QWERTZ", QWERTZ_GENOME)]
+    }
+}
+
+impl GetGenome<46> for Neo2DeDe {
+    fn get_genome(&self) -> &[char; 46] {
+        &NEO2_GENOME
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 46])> {
+        baseline_first(self.get_genome(), &NEO_GENOMES)
+    }
+}
+
+impl GetGenome<46> for BoneDeDe {
+    fn get_genome(&self) -> &[char; 46] {
+        &BONE_GENOME
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 46])> {
+        baseline_first(self.get_genome(), &NEO_GENOMES)
+    }
 }
 
-#[allow(dead_code)]
+impl GetGenome<46> for KoyDeDe {
+    fn get_genome(&self) -> &[char; 46] {
+        &KOY_GENOME
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; 46])> {
+        baseline_first(self.get_genome(), &NEO_GENOMES)
+    }
+}
+
+/// Named genomes with the one equal to `baseline` first
+fn baseline_first<const N: usize>(
+    baseline: &[char; N],
+    genomes: &[(&'static str, [char; N])],
+) -> Vec<(&'static str, [char; N])> {
+    let mut genomes = genomes.to_vec();
+    if let Some(index) = genomes.iter().position(|(_, genome)| genome == baseline) {
+        let genome = genomes.remove(index);
+        genomes.insert(0, genome);
+    }
+    genomes
+}
+
+/// Genomes for the keys of the US keyboard
+const EN_US_GENOMES: [(&str, [char; 46]); 6] = [
+    ("QWERTY", QWERTY_GENOME),
+    ("Dvorak", DVORAK_GENOME),
+    ("Colemak", COLEMAK_GENOME),
+    ("Colemak-DH", COLEMAK_DH_GENOME),
+    ("Workman", WORKMAN_GENOME),
+    ("ABC", ABC_GENOME),
+];
+
 pub const QWERTY_GENOME: [char; 46] = [
     '~', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '-', '+', 'Q', 'W', 'E', 'R', 'T', 'Y',
     'U', 'I', 'O', 'P', '[', ']', 'A', 'S', 'D', 'F', 'G', 'H', 'J', 'K', 'L', ';', '\'', 'Z', 'X',
     'C', 'V', 'B', 'N', 'M', '<', '>', '?',
 ];
 
-#[allow(dead_code)]
 pub const QWERTZ_GENOME: [char; 48] = [
     '^', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'ß', '´', 'Q', 'W', 'E', 'R', 'T', 'Z',
     'U', 'I', 'O', 'P', 'Ü', '+', 'A', 'S', 'D', 'F', 'G', 'H', 'J', 'K', 'L', 'Ö', 'Ä', '#', '<',
     'Y', 'X', 'C', 'V', 'B', 'N', 'M', ',', '.', '-',
 ];
 
-#[allow(dead_code)]
 pub const ABC_GENOME: [char; 46] = [
     '~', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '-', '+', 'A', 'B', 'C', 'D', 'E', 'F',
     'G', 'H', 'I', 'J', '[', ']', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S', ';', '\'', 'T', 'U',
     'V', 'W', 'X', 'Y', 'Z', '<', '>', '?',
 ];
 
-#[allow(dead_code)]
 pub const DVORAK_GENOME: [char; 46] = [
     '~', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '[', ']', '\'', '<', '>', 'P', 'Y', 'F',
     'G', 'C', 'R', 'L', '?', '+', 'A', 'O', 'E', 'U', 'I', 'D', 'H', 'T', 'N', 'S', '-', ';', 'Q',
     'J', 'K', 'X', 'B', 'M', 'W', 'V', 'Z',
 ];
+
+pub const COLEMAK_GENOME: [char; 46] = [
+    '~', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '-', '+', 'Q', 'W', 'F', 'P', 'G', 'J',
+    'L', 'U', 'Y', ';', '[', ']', 'A', 'R', 'S', 'T', 'D', 'H', 'N', 'E', 'I', 'O', '\'', 'Z', 'X',
+    'C', 'V', 'B', 'K', 'M', '<', '>', '?',
+];
+
+pub const COLEMAK_DH_GENOME: [char; 46] = [
+    '~', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '-', '+', 'Q', 'W', 'F', 'P', 'B', 'J',
+    'L', 'U', 'Y', ';', '[', ']', 'A', 'R', 'S', 'T', 'G', 'M', 'N', 'E', 'I', 'O', '\'', 'X', 'C',
+    'D', 'V', 'Z', 'K', 'H', '<', '>', '?',
+];
+
+pub const WORKMAN_GENOME: [char; 46] = [
+    '~', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '-', '+', 'Q', 'D', 'R', 'W', 'B', 'J',
+    'F', 'U', 'P', ';', '[', ']', 'A', 'S', 'H', 'T', 'G', 'Y', 'N', 'E', 'O', 'I', '\'', 'Z', 'X',
+    'M', 'C', 'V', 'K', 'L', '<', '>', '?',
+];
+
+/// Genomes of the Neo family, on the German keyboard without the modifier keys
+/// next to the home row and the bottom row
+const NEO_GENOMES: [(&str, [char; 46]); 3] = [
+    ("Neo2", NEO2_GENOME),
+    ("Bone", BONE_GENOME),
+    ("KOY", KOY_GENOME),
+];
+
+pub const NEO2_GENOME: [char; 46] = [
+    '^', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '-', '`', 'X', 'V', 'L', 'C', 'W', 'K',
+    'H', 'G', 'F', 'Q', 'ß', '´', 'U', 'I', 'A', 'E', 'O', 'S', 'N', 'R', 'T', 'D', 'Y', 'Ü', 'Ö',
+    'Ä', 'P', 'Z', 'B', 'M', ',', '.', 'J',
+];
+
+pub const BONE_GENOME: [char; 46] = [
+    '^', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '-', '`', 'J', 'D', 'U', 'A', 'X', 'P',
+    'H', 'L', 'M', 'W', 'ß', '´', 'C', 'T', 'I', 'E', 'O', 'B', 'N', 'R', 'S', 'G', 'Q', 'F', 'V',
+    'Ü', 'Ä', 'Ö', 'Y', 'Z', ',', '.', 'K',
+];
+
+pub const KOY_GENOME: [char; 46] = [
+    '^', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '-', '`', 'K', '.', 'O', ',', 'Y', 'V',
+    'G', 'C', 'L', 'ß', 'Z', '´', 'H', 'A', 'E', 'I', 'U', 'D', 'T', 'R', 'N', 'S', 'F', 'X', 'Q',
+    'Ä', 'Ü', 'Ö', 'B', 'P', 'W', 'M', 'J',
+];
@@ -0,0 +1,448 @@
+use std::{fmt, ops::RangeInclusive};
+
+use super::{
+    genome::GetGenome,
+    key_maps::GetKeyMap,
+    layout::{ILayout, Layout},
+    layout_map::{
+        get_default_fixed_key, FixedKey, FixedKeys, GetLayoutMap, KeyboardKey, KeyboardRow,
+        RawLayout,
+    },
+    letter_list::GetLetterList,
+};
+
+pub(crate) const ROW_NAMES: [&str; 4] = ["number", "top", "home", "bottom"];
+
+/// Height of the letter rows, number row to bottom row
+const ROW_Y: [f64; 4] = [4.5, 3.5, 2.5, 1.5];
+
+/// Fingers of the columns in layout file numbering, the number row of a row-staggered
+/// keyboard is shifted by half a key
+const COLUMN_FINGERS: [i32; 13] = [1, 1, 2, 3, 4, 4, 5, 5, 6, 7, 8, 8, 8];
+const STAGGERED_NUMBER_FINGERS: [i32; 13] = [1, 1, 1, 2, 3, 4, 4, 5, 6, 7, 8, 8, 8];
+
+/// Physical keyboard that the keys of a layout are placed on.
+///
+/// The keys of a layout keep their row and are assigned to columns from left to right:
+/// column 0 is left of `1` in the number row and left of `Z` for the ISO key in the bottom row,
+/// column 1 holds `1`, `Q`, `A` and `Z` of QWERTY.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Geometry {
+    /// row-staggered keyboard with a wide left shift
+    Ansi,
+    /// row-staggered keyboard with the `<` key left of the bottom row and a key left of enter
+    Iso,
+    /// keys in a grid like on a Planck or Preonic, space in the middle of the thumb row
+    Ortholinear,
+    /// split halves with column stagger like an Ergodox or Corne, backspace and enter
+    /// are in the thumb clusters next to space
+    Split,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeometryError {
+    MissingKey {
+        geometry: Geometry,
+        row: &'static str,
+        column: i32,
+    },
+    /// thumb keys of a layout map have no column
+    ThumbKey(usize),
+    /// the layout map has no row next to which the fixed key could be placed
+    MissingFixedKey(&'static str),
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingKey {
+                geometry,
+                row,
+                column,
+            } => write!(
+                f,
+                "the {} geometry has no key in column {column} of the {row} row",
+                geometry.name()
+            ),
+            Self::ThumbKey(index) => {
+                write!(
+                    f,
+                    "key {index} is a thumb key, it can not be moved to a geometry"
+                )
+            }
+            Self::MissingFixedKey(name) => {
+                write!(f, "the layout map has no row to place {name}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+impl Geometry {
+    pub const ALL: [Geometry; 4] = [
+        Geometry::Ansi,
+        Geometry::Iso,
+        Geometry::Ortholinear,
+        Geometry::Split,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Geometry::Ansi => "ANSI",
+            Geometry::Iso => "ISO",
+            Geometry::Ortholinear => "ortholinear",
+            Geometry::Split => "split",
+        }
+    }
+
+    /// Columns that have a key in a letter row, number row to bottom row
+    fn columns(&self, row: usize) -> RangeInclusive<i32> {
+        match (self, row) {
+            (_, 0) => 0..=12,
+            (_, 1) => 1..=12,
+            (Geometry::Ansi, 2) => 1..=11,
+            (_, 2) => 1..=12,
+            (Geometry::Ansi, _) => 1..=10,
+            (_, _) => 0..=10,
+        }
+    }
+
+    fn x(&self, row: usize, column: i32) -> f64 {
+        let column = column as f64;
+        match self {
+            Geometry::Ansi | Geometry::Iso => [0.5, 1., 1.25, 1.75][row] + column,
+            Geometry::Ortholinear => 0.5 + column,
+            Geometry::Split => 0.5 + column + if column >= 6. { 1. } else { 0. },
+        }
+    }
+
+    fn y(&self, row: usize, column: i32) -> f64 {
+        let stagger = match (self, column) {
+            // the middle finger column is the highest, the pinky columns the lowest
+            (Geometry::Split, 2 | 9) => 0.25,
+            (Geometry::Split, 3 | 8) => 0.4,
+            (Geometry::Split, 4 | 7) => 0.2,
+            (Geometry::Split, 5 | 6) => 0.1,
+            _ => 0.,
+        };
+
+        ROW_Y[row] + stagger
+    }
+
+    fn raw_key(&self, row: usize, column: i32) -> RawLayout {
+        let fingers = match (self, row) {
+            (Geometry::Ansi | Geometry::Iso, 0) => STAGGERED_NUMBER_FINGERS,
+            _ => COLUMN_FINGERS,
+        };
+        let home = row == 2 && matches!(column, 1..=4 | 7..=10);
+
+        (
+            self.x(row, column),
+            self.y(row, column),
+            row as i32 + 1,
+            fingers[column as usize],
+            home,
+        )
+    }
+
+    fn key(&self, row: usize, column: i32) -> KeyboardKey {
+        KeyboardKey::from(self.raw_key(row, column))
+    }
+
+    /// Every key of the letter rows
+    fn keys(&self) -> Vec<KeyboardKey> {
+        (0..ROW_NAMES.len())
+            .flat_map(|row| self.columns(row).map(move |column| self.key(row, column)))
+            .collect()
+    }
+
+    pub fn get_fixed_keys(&self) -> FixedKeys {
+        let outside = |row: usize, column: i32, finger: i32| -> RawLayout {
+            (
+                self.x(row, column),
+                self.y(row, column),
+                row as i32 + 1,
+                finger,
+                false,
+            )
+        };
+
+        let keys = match self {
+            Geometry::Ansi | Geometry::Iso => {
+                let keys = self.keys();
+                return FixedKeys(FixedKey::ALL.map(|key| {
+                    get_default_fixed_key(&keys, key).expect("geometry has every letter row")
+                }));
+            }
+            Geometry::Ortholinear => [
+                outside(3, -1, 1),
+                outside(3, 11, 8),
+                (5.5, 0.5, 5, 9, true),
+                (6.5, 0.5, 5, 10, true),
+                outside(1, 0, 1),
+                outside(2, 13, 8),
+                outside(0, 13, 8),
+            ],
+            Geometry::Split => [
+                outside(3, -1, 1),
+                outside(3, 11, 8),
+                (self.x(3, 5) + 0.5, 0.2, 5, 9, true),
+                (self.x(3, 6) - 0.5, 0.2, 5, 10, true),
+                outside(1, 0, 1),
+                (self.x(3, 7), 0.4, 5, 10, false),
+                (self.x(3, 4), 0.4, 5, 9, false),
+            ],
+        };
+
+        FixedKeys(keys.map(KeyboardKey::from))
+    }
+
+    /// Move the keys of a layout map to this geometry, the order of the keys is kept
+    pub fn get_layout_map<const N: usize>(
+        &self,
+        layout_map: &[KeyboardKey; N],
+    ) -> Result<[KeyboardKey; N], GeometryError> {
+        let columns = get_key_columns(layout_map)?;
+
+        let mut keys = *layout_map;
+        for (index, (row, column)) in columns.into_iter().enumerate() {
+            if !self.columns(row).contains(&column) {
+                return Err(GeometryError::MissingKey {
+                    geometry: *self,
+                    row: ROW_NAMES[row],
+                    column,
+                });
+            }
+            keys[index] = self.key(row, column);
+        }
+
+        Ok(keys)
+    }
+}
+
+/// Letter row (0 is the number row) and column of every key of a layout map,
+/// numbered like the columns of a [`Geometry`]
+pub(crate) fn get_key_columns<const N: usize>(
+    layout_map: &[KeyboardKey; N],
+) -> Result<[(usize, i32); N], GeometryError> {
+    if let Some(index) = layout_map
+        .iter()
+        .position(|key| key.row == KeyboardRow::Thumb)
+    {
+        return Err(GeometryError::ThumbKey(index));
+    }
+
+    let row_keys = |row: usize| {
+        let mut keys: Vec<usize> = (0..N)
+            .filter(|i| layout_map[*i].row as usize == row)
+            .collect();
+        keys.sort_by(|a, b| layout_map[*a].x.total_cmp(&layout_map[*b].x));
+        keys
+    };
+    let leftmost = |row: usize| row_keys(row).first().map(|i| layout_map[*i].x);
+
+    // a bottom row key left of the home row is the ISO key in column 0
+    let bottom_start = match (leftmost(3), leftmost(2)) {
+        (Some(bottom), Some(home)) if bottom < home => 0,
+        _ => 1,
+    };
+
+    let mut columns = [(0, 0); N];
+    for row in 0..ROW_NAMES.len() {
+        let start = match row {
+            0 => 0,
+            3 => bottom_start,
+            _ => 1,
+        };
+
+        for (i, index) in row_keys(row).into_iter().enumerate() {
+            columns[index] = (row, start + i as i32);
+        }
+    }
+
+    Ok(columns)
+}
+
+/// Characters of a layout on the keys of another physical keyboard
+#[derive(Debug, Clone)]
+pub struct GeometryLayout<L, const N: usize> {
+    layout: L,
+    geometry: Geometry,
+    layout_map: [KeyboardKey; N],
+}
+
+impl<L: ILayout<N>, const N: usize> GeometryLayout<L, N> {
+    pub fn new(layout: L, geometry: Geometry) -> Result<Self, GeometryError> {
+        let layout_map = geometry.get_layout_map(&layout.get_layout_map())?;
+
+        Ok(Self {
+            layout,
+            geometry,
+            layout_map,
+        })
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+}
+
+impl<L: ILayout<N>, const N: usize> GetGenome<N> for GeometryLayout<L, N> {
+    fn get_genome(&self) -> &[char; N] {
+        self.layout.get_genome()
+    }
+
+    fn get_reference_genomes(&self) -> Vec<(&str, [char; N])> {
+        self.layout.get_reference_genomes()
+    }
+}
+
+impl<L: ILayout<N>, const N: usize> GetKeyMap for GeometryLayout<L, N> {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        self.layout.get_key_map(c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        self.layout.get_key_map_entries()
+    }
+
+    fn get_dead_keys(&self) -> &[char] {
+        self.layout.get_dead_keys()
+    }
+}
+
+impl<L: ILayout<N>, const N: usize> GetLetterList<N> for GeometryLayout<L, N> {
+    fn get_letter_list(&self) -> &[char; N] {
+        self.layout.get_letter_list()
+    }
+}
+
+impl<L: ILayout<N>, const N: usize> GetLayoutMap<N> for GeometryLayout<L, N> {
+    fn get_layout_map(&self) -> [KeyboardKey; N] {
+        self.layout_map
+    }
+
+    fn get_fixed_keys(&self) -> Result<FixedKeys, GeometryError> {
+        Ok(self.geometry.get_fixed_keys())
+    }
+}
+
+impl<L: ILayout<N>, const N: usize> ILayout<N> for GeometryLayout<L, N> {
+    fn get(&self) -> Layout {
+        self.layout.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::layout::{Neo2DeDe, QwertyEnUs, QwertzDeDe};
+    use crate::models::{Finger, Hand};
+
+    fn key_of<L: ILayout<N>, const N: usize>(layout: &L, c: char) -> KeyboardKey {
+        let index = layout
+            .get_genome()
+            .iter()
+            .position(|key| *key == c)
+            .expect("should be in genome");
+        layout.get_layout_map()[index]
+    }
+
+    #[test]
+    fn test_row_staggered_geometries_match_traditional_keyboards() {
+        let qwerty = GeometryLayout::new(QwertyEnUs(), Geometry::Ansi).expect("ANSI layout");
+        assert_eq!(qwerty.get_layout_map(), QwertyEnUs().get_layout_map());
+        assert_eq!(
+            qwerty.get_fixed_keys().expect("fixed keys"),
+            QwertyEnUs().get_fixed_keys().expect("fixed keys")
+        );
+
+        let qwertz = GeometryLayout::new(QwertzDeDe(), Geometry::Iso).expect("ISO layout");
+        assert_eq!(qwertz.get_layout_map(), QwertzDeDe().get_layout_map());
+        assert_eq!(
+            qwertz.get_fixed_keys().expect("fixed keys"),
+            QwertzDeDe().get_fixed_keys().expect("fixed keys")
+        );
+
+        assert_eq!(
+            GeometryLayout::new(QwertzDeDe(), Geometry::Ansi).err(),
+            Some(GeometryError::MissingKey {
+                geometry: Geometry::Ansi,
+                row: "home",
+                column: 12
+            })
+        );
+
+        // without the `<` key the ISO keyboard keeps the short left shift
+        let neo = GeometryLayout::new(Neo2DeDe(), Geometry::Iso).expect("ISO layout");
+        assert_eq!(neo.get_layout_map(), Neo2DeDe().get_layout_map());
+        assert!(
+            neo.get_fixed_keys().expect("fixed keys")[FixedKey::LeftShift].x
+                < Neo2DeDe().get_fixed_keys().expect("fixed keys")[FixedKey::LeftShift].x
+        );
+    }
+
+    #[test]
+    fn test_every_layout_fits_the_keyboards_with_columns() {
+        for geometry in [Geometry::Ortholinear, Geometry::Split] {
+            let layout = GeometryLayout::new(QwertzDeDe(), geometry).expect("layout");
+            let key = |c| key_of(&layout, c);
+
+            // letters of a column share a finger and x on ortholinear keyboards
+            for column in ["1QAY", "3EDC", "4RFV", "7UJM", "0PÖ-"] {
+                let keys: Vec<KeyboardKey> = column.chars().map(key).collect();
+                assert!(keys.iter().all(|k| k.finger == keys[0].finger), "{column}");
+                assert!(keys.iter().all(|k| k.hand == keys[0].hand), "{column}");
+                if geometry == Geometry::Ortholinear {
+                    assert!(keys.iter().all(|k| k.x == keys[0].x), "{column}");
+                }
+            }
+            assert_eq!(key('6').hand, Hand::Right);
+            assert!(key('F').home && key('J').home && !key('G').home);
+            assert_eq!(key('<').x, key('1').x - 1.);
+
+            let fixed_keys = layout.get_fixed_keys().expect("fixed keys");
+            let layout_map = layout.get_layout_map();
+            for fixed_key in fixed_keys.iter() {
+                assert!(!layout_map
+                    .iter()
+                    .any(|k| (k.x, k.y) == (fixed_key.x, fixed_key.y)));
+            }
+
+            let thumbs = fixed_keys.iter().filter(|k| k.finger == Finger::Thumb);
+            let expected = match geometry {
+                Geometry::Split => 4,
+                _ => 2,
+            };
+            assert_eq!(thumbs.count(), expected);
+
+            GeometryLayout::new(QwertyEnUs(), geometry).expect("layout");
+        }
+
+        let split = GeometryLayout::new(QwertyEnUs(), Geometry::Split).expect("layout");
+        assert!(key_of(&split, 'E').y > key_of(&split, 'Q').y);
+        assert!(key_of(&split, 'Y').x - key_of(&split, 'T').x > 1.);
+        assert_eq!(
+            split.get_fixed_keys().expect("fixed keys")[FixedKey::Backspace].finger,
+            Finger::Thumb
+        );
+    }
+
+    /// Layout map with a single key on the number row
+    struct NumberRow;
+
+    impl GetLayoutMap<1> for NumberRow {
+        fn get_layout_map(&self) -> [KeyboardKey; 1] {
+            [KeyboardKey::from((0.5, 4.5, 1, 1, false))]
+        }
+    }
+
+    #[test]
+    fn test_missing_fixed_key_is_an_error() {
+        assert_eq!(
+            NumberRow.get_fixed_keys(),
+            Err(GeometryError::MissingFixedKey("left_shift"))
+        );
+    }
+}
@@ -1,228 +1,331 @@
-use super::layout::{QwertyEnUs, QwertzDeDe};
+use super::layout::{
+    BoneDeDe, ColemakDhEnUs, ColemakEnUs, DvorakEnUs, KoyDeDe, Neo2DeDe, QwertyEnUs, QwertzDeDe,
+    WorkmanEnUs,
+};
+use super::letter_list::GetLetterList;
 
 pub trait GetKeyMap {
-    fn get_key_map(c: char) -> Option<(usize, bool)>;
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)>;
+
+    /// Every character of `get_key_map` with its key and shift state, in no particular order
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))>;
+
+    /// Characters that type nothing on their own but change the next character,
+    /// like `^` and `e` for `ê`
+    fn get_dead_keys(&self) -> &[char] {
+        &[]
+    }
 }
 
 // map dictionary (key, shift)
 impl GetKeyMap for QwertyEnUs {
-    fn get_key_map(c: char) -> Option<(usize, bool)> {
-        get_key_qwerty(c)
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_QWERTY, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_QWERTY)
+    }
+}
+
+impl GetKeyMap for DvorakEnUs {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_QWERTY, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_QWERTY)
+    }
+}
+
+impl GetKeyMap for ColemakEnUs {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_QWERTY, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_QWERTY)
+    }
+}
+
+impl GetKeyMap for ColemakDhEnUs {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_QWERTY, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_QWERTY)
+    }
+}
+
+impl GetKeyMap for WorkmanEnUs {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_QWERTY, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_QWERTY)
     }
 }
 
 impl GetKeyMap for QwertzDeDe {
-    fn get_key_map(c: char) -> Option<(usize, bool)> {
-        get_key_qwertz(c)
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_QWERTZ, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_QWERTZ)
+    }
+
+    fn get_dead_keys(&self) -> &[char] {
+        &DEAD_KEYS_QWERTZ
     }
 }
 
-fn get_key_qwerty(c: char) -> Option<(usize, bool)> {
-    let key = match c {
-        'a' => (1, false),
-        'A' => (1, true),
-        'b' => (2, false),
-        'B' => (2, true),
-        'c' => (3, false),
-        'C' => (3, true),
-        'd' => (4, false),
-        'D' => (4, true),
-        'e' => (5, false),
-        'E' => (5, true),
-        'f' => (6, false),
-        'F' => (6, true),
-        'g' => (7, false),
-        'G' => (7, true),
-        'h' => (8, false),
-        'H' => (8, true),
-        'i' => (9, false),
-        'I' => (9, true),
-        'j' => (10, false),
-        'J' => (10, true),
-        'k' => (11, false),
-        'K' => (11, true),
-        'l' => (12, false),
-        'L' => (12, true),
-        'm' => (13, false),
-        'M' => (13, true),
-        'n' => (14, false),
-        'N' => (14, true),
-        'o' => (15, false),
-        'O' => (15, true),
-        'p' => (16, false),
-        'P' => (16, true),
-        'q' => (17, false),
-        'Q' => (17, true),
-        'r' => (18, false),
-        'R' => (18, true),
-        's' => (19, false),
-        'S' => (19, true),
-        't' => (20, false),
-        'T' => (20, true),
-        'u' => (21, false),
-        'U' => (21, true),
-        'v' => (22, false),
-        'V' => (22, true),
-        'w' => (23, false),
-        'W' => (23, true),
-        'x' => (24, false),
-        'X' => (24, true),
-        'y' => (25, false),
-        'Y' => (25, true),
-        'z' => (26, false),
-        'Z' => (26, true),
-        '0' => (27, false),
-        ')' => (27, true),
-        '1' => (28, false),
-        '!' => (28, true),
-        '2' => (29, false),
-        '@' => (29, true),
-        '3' => (30, false),
-        '#' => (30, true),
-        '4' => (31, false),
-        '$' => (31, true),
-        '5' => (32, false),
-        '%' => (32, true),
-        '6' => (33, false),
-        '^' => (33, true),
-        '7' => (34, false),
-        '&' => (34, true),
-        '8' => (35, false),
-        '*' => (35, true),
-        '9' => (36, false),
-        '(' => (36, true),
-        '`' => (37, false),
-        '~' => (37, true),
-        '-' => (38, false),
-        '_' => (38, true),
-        '=' => (39, false),
-        '+' => (39, true),
-        '[' => (40, false),
-        '{' => (40, true),
-        ']' => (41, false),
-        '}' => (41, true),
-        ';' => (42, false),
-        ':' => (42, true),
-        '\'' => (43, false),
-        '"' => (43, true),
-        ',' => (44, false),
-        '<' => (44, true),
-        '.' => (45, false),
-        '>' => (45, true),
-        '/' => (46, false),
-        '?' => (46, true),
-        _ => (0, false),
-    };
-
-    match key.0 {
-        0 => None,
-        _ => Some(key),
+impl GetKeyMap for Neo2DeDe {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_NEO, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_NEO)
+    }
+
+    fn get_dead_keys(&self) -> &[char] {
+        &DEAD_KEYS_NEO
     }
 }
 
-fn get_key_qwertz(c: char) -> Option<(usize, bool)> {
-    let key = match c {
-        'a' => (1, false),
-        'A' => (1, true),
-        'b' => (2, false),
-        'B' => (2, true),
-        'c' => (3, false),
-        'C' => (3, true),
-        'd' => (4, false),
-        'D' => (4, true),
-        'e' => (5, false),
-        'E' => (5, true),
-        'f' => (6, false),
-        'F' => (6, true),
-        'g' => (7, false),
-        'G' => (7, true),
-        'h' => (8, false),
-        'H' => (8, true),
-        'i' => (9, false),
-        'I' => (9, true),
-        'j' => (10, false),
-        'J' => (10, true),
-        'k' => (11, false),
-        'K' => (11, true),
-        'l' => (12, false),
-        'L' => (12, true),
-        'm' => (13, false),
-        'M' => (13, true),
-        'n' => (14, false),
-        'N' => (14, true),
-        'o' => (15, false),
-        'O' => (15, true),
-        'p' => (16, false),
-        'P' => (16, true),
-        'q' => (17, false),
-        'Q' => (17, true),
-        'r' => (18, false),
-        'R' => (18, true),
-        's' => (19, false),
-        'S' => (19, true),
-        't' => (20, false),
-        'T' => (20, true),
-        'u' => (21, false),
-        'U' => (21, true),
-        'v' => (22, false),
-        'V' => (22, true),
-        'w' => (23, false),
-        'W' => (23, true),
-        'x' => (24, false),
-        'X' => (24, true),
-        'y' => (25, false),
-        'Y' => (25, true),
-        'z' => (26, false),
-        'Z' => (26, true),
-        '0' => (27, false),
-        '=' => (27, true),
-        '1' => (28, false),
-        '!' => (28, true),
-        '2' => (29, false),
-        '"' => (29, true),
-        '3' => (30, false),
-        '§' => (30, true),
-        '4' => (31, false),
-        '$' => (31, true),
-        '5' => (32, false),
-        '%' => (32, true),
-        '6' => (33, false),
-        '&' => (33, true),
-        '7' => (34, false),
-        '/' => (34, true),
-        '8' => (35, false),
-        '(' => (35, true),
-        '9' => (36, false),
-        ')' => (36, true),
-        '^' => (37, false),
-        '°' => (37, true),
-        'ß' => (38, false),
-        '?' => (38, true),
-        '´' => (39, false),
-        '`' => (39, true),
-        'ü' => (40, false),
-        'Ü' => (40, true),
-        'ö' => (41, false),
-        'Ö' => (41, true),
-        'ä' => (42, false),
-        'Ä' => (42, true),
-        '+' => (43, false),
-        '*' => (43, true),
-        '#' => (44, false),
-        '\'' => (44, true),
-        '<' => (45, false),
-        '>' => (45, true),
-        ',' => (46, false),
-        ';' => (46, true),
-        '.' => (47, false),
-        ':' => (47, true),
-        '-' => (48, false),
-        '_' => (48, true),
-        _ => (0, false),
-    };
-
-    match key.0 {
-        0 => None,
-        _ => Some(key),
+impl GetKeyMap for BoneDeDe {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_NEO, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_NEO)
+    }
+
+    fn get_dead_keys(&self) -> &[char] {
+        &DEAD_KEYS_NEO
+    }
+}
+
+impl GetKeyMap for KoyDeDe {
+    fn get_key_map(&self, c: char) -> Option<(usize, bool)> {
+        find_key(&KEYS_NEO, c)
+    }
+
+    fn get_key_map_entries(&self) -> Vec<(char, (usize, bool))> {
+        key_map_entries(&KEYS_NEO)
+    }
+
+    fn get_dead_keys(&self) -> &[char] {
+        &DEAD_KEYS_NEO
     }
 }
+
+const DEAD_KEYS_QWERTZ: [char; 3] = ['^', '´', '`'];
+const DEAD_KEYS_NEO: [char; 6] = ['^', 'ˇ', '´', '˜', '`', '¸'];
+
+/// Unshifted and shifted character of every letter, in letter list order
+const KEYS_QWERTY: [[char; 2]; 46] = [
+    ['a', 'A'],
+    ['b', 'B'],
+    ['c', 'C'],
+    ['d', 'D'],
+    ['e', 'E'],
+    ['f', 'F'],
+    ['g', 'G'],
+    ['h', 'H'],
+    ['i', 'I'],
+    ['j', 'J'],
+    ['k', 'K'],
+    ['l', 'L'],
+    ['m', 'M'],
+    ['n', 'N'],
+    ['o', 'O'],
+    ['p', 'P'],
+    ['q', 'Q'],
+    ['r', 'R'],
+    ['s', 'S'],
+    ['t', 'T'],
+    ['u', 'U'],
+    ['v', 'V'],
+    ['w', 'W'],
+    ['x', 'X'],
+    ['y', 'Y'],
+    ['z', 'Z'],
+    ['0', ')'],
+    ['1', '!'],
+    ['2', '@'],
+    ['3', '#'],
+    ['4', '$'],
+    ['5', '%'],
+    ['6', '^'],
+    ['7', '&'],
+    ['8', '*'],
+    ['9', '('],
+    ['`', '~'],
+    ['-', '_'],
+    ['=', '+'],
+    ['[', '{'],
+    [']', '}'],
+    [';', ':'],
+    ['\'', '"'],
+    [',', '<'],
+    ['.', '>'],
+    ['/', '?'],
+];
+
+const KEYS_QWERTZ: [[char; 2]; 48] = [
+    ['a', 'A'],
+    ['b', 'B'],
+    ['c', 'C'],
+    ['d', 'D'],
+    ['e', 'E'],
+    ['f', 'F'],
+    ['g', 'G'],
+    ['h', 'H'],
+    ['i', 'I'],
+    ['j', 'J'],
+    ['k', 'K'],
+    ['l', 'L'],
+    ['m', 'M'],
+    ['n', 'N'],
+    ['o', 'O'],
+    ['p', 'P'],
+    ['q', 'Q'],
+    ['r', 'R'],
+    ['s', 'S'],
+    ['t', 'T'],
+    ['u', 'U'],
+    ['v', 'V'],
+    ['w', 'W'],
+    ['x', 'X'],
+    ['y', 'Y'],
+    ['z', 'Z'],
+    ['0', '='],
+    ['1', '!'],
+    ['2', '"'],
+    ['3', '§'],
+    ['4', '$'],
+    ['5', '%'],
+    ['6', '&'],
+    ['7', '/'],
+    ['8', '('],
+    ['9', ')'],
+    ['^', '°'],
+    ['ß', '?'],
+    ['´', '`'],
+    ['ü', 'Ü'],
+    ['ö', 'Ö'],
+    ['ä', 'Ä'],
+    ['+', '*'],
+    ['#', '\''],
+    ['<', '>'],
+    [',', ';'],
+    ['.', ':'],
+    ['-', '_'],
+];
+
+/// Layers 1 and 2 of Neo2, Bone and KOY, the characters of the other layers are not typed
+const KEYS_NEO: [[char; 2]; 46] = [
+    ['a', 'A'],
+    ['b', 'B'],
+    ['c', 'C'],
+    ['d', 'D'],
+    ['e', 'E'],
+    ['f', 'F'],
+    ['g', 'G'],
+    ['h', 'H'],
+    ['i', 'I'],
+    ['j', 'J'],
+    ['k', 'K'],
+    ['l', 'L'],
+    ['m', 'M'],
+    ['n', 'N'],
+    ['o', 'O'],
+    ['p', 'P'],
+    ['q', 'Q'],
+    ['r', 'R'],
+    ['s', 'S'],
+    ['t', 'T'],
+    ['u', 'U'],
+    ['v', 'V'],
+    ['w', 'W'],
+    ['x', 'X'],
+    ['y', 'Y'],
+    ['z', 'Z'],
+    ['0', '”'],
+    ['1', '°'],
+    ['2', '§'],
+    ['3', 'ℓ'],
+    ['4', '»'],
+    ['5', '«'],
+    ['6', '$'],
+    ['7', '€'],
+    ['8', '„'],
+    ['9', '“'],
+    ['^', 'ˇ'],
+    ['ß', 'ẞ'],
+    ['´', '˜'],
+    ['ü', 'Ü'],
+    ['ö', 'Ö'],
+    ['ä', 'Ä'],
+    ['`', '¸'],
+    [',', '–'],
+    ['.', '•'],
+    ['-', '—'],
+];
+
+/// Key (counted from 1) and shift state of a character in a table like `KEYS_QWERTY`
+fn find_key<const N: usize>(keys: &[[char; 2]; N], c: char) -> Option<(usize, bool)> {
+    keys.iter().enumerate().find_map(|(index, characters)| {
+        let shift = characters.iter().position(|other| *other == c)?;
+        Some((index + 1, shift == 1))
+    })
+}
+
+fn key_map_entries<const N: usize>(keys: &[[char; 2]; N]) -> Vec<(char, (usize, bool))> {
+    keys.iter()
+        .enumerate()
+        .flat_map(|(index, characters)| {
+            characters
+                .iter()
+                .zip([false, true])
+                .map(move |(c, shift)| (*c, (index + 1, shift)))
+        })
+        .collect()
+}
+
+/// Unshifted and shifted character on every key of a genome, in layout map order.
+/// If several characters are typed the same way, the one with the lowest code point is used.
+pub(crate) fn get_key_characters<L: GetKeyMap + GetLetterList<N>, const N: usize>(
+    layout: &L,
+    genome: &[char; N],
+) -> [[Option<char>; 2]; N] {
+    let mut by_letter = [[None; 2]; N];
+    for (c, (index, shift)) in layout.get_key_map_entries() {
+        let character = &mut by_letter[index - 1][shift as usize];
+        if character.is_none_or(|other| c < other) {
+            *character = Some(c);
+        }
+    }
+
+    let letter_list = layout.get_letter_list();
+    genome.map(|letter| {
+        let index = letter_list
+            .iter()
+            .position(|other| *other == letter)
+            .expect("genome is a permutation of the letter list");
+        by_letter[index]
+    })
+}
+
+/// Key map entries in a fixed order, for comparing the characters of two layouts
+#[cfg(test)]
+pub(crate) fn sorted_key_map_entries(layout: &impl GetKeyMap) -> Vec<(char, (usize, bool))> {
+    let mut entries = layout.get_key_map_entries();
+    entries.sort();
+    entries
+}
@@ -6,18 +6,26 @@ use super::{
 pub enum Layout {
     QwertyEnUs,
     QwertzDeDe,
+    DvorakEnUs,
+    ColemakEnUs,
+    ColemakDhEnUs,
+    WorkmanEnUs,
+    Neo2DeDe,
+    BoneDeDe,
+    KoyDeDe,
+    Custom,
 }
 
 pub trait ILayout<const N: usize>:
     GetGenome<N> + GetKeyMap + GetLetterList<N> + GetLayoutMap<N>
 {
-    fn get() -> Layout;
+    fn get(&self) -> Layout;
 }
 
 pub struct QwertyEnUs();
 
 impl ILayout<46> for QwertyEnUs {
-    fn get() -> Layout {
+    fn get(&self) -> Layout {
         Layout::QwertyEnUs
     }
 }
@@ -25,7 +33,70 @@ impl ILayout<46> for QwertyEnUs {
 pub struct QwertzDeDe();
 
 impl ILayout<48> for QwertzDeDe {
-    fn get() -> Layout {
+    fn get(&self) -> Layout {
         Layout::QwertzDeDe
     }
 }
+
+/// Dvorak on the US keyboard
+pub struct DvorakEnUs();
+
+impl ILayout<46> for DvorakEnUs {
+    fn get(&self) -> Layout {
+        Layout::DvorakEnUs
+    }
+}
+
+/// Colemak on the US keyboard
+pub struct ColemakEnUs();
+
+impl ILayout<46> for ColemakEnUs {
+    fn get(&self) -> Layout {
+        Layout::ColemakEnUs
+    }
+}
+
+/// Colemak-DH with the angle mod on the US keyboard
+pub struct ColemakDhEnUs();
+
+impl ILayout<46> for ColemakDhEnUs {
+    fn get(&self) -> Layout {
+        Layout::ColemakDhEnUs
+    }
+}
+
+/// Workman on the US keyboard
+pub struct WorkmanEnUs();
+
+impl ILayout<46> for WorkmanEnUs {
+    fn get(&self) -> Layout {
+        Layout::WorkmanEnUs
+    }
+}
+
+/// Neo2 on the German keyboard, layers 1 and 2
+pub struct Neo2DeDe();
+
+impl ILayout<46> for Neo2DeDe {
+    fn get(&self) -> Layout {
+        Layout::Neo2DeDe
+    }
+}
+
+/// Bone on the German keyboard, layers 1 and 2
+pub struct BoneDeDe();
+
+impl ILayout<46> for BoneDeDe {
+    fn get(&self) -> Layout {
+        Layout::BoneDeDe
+    }
+}
+
+/// KOY on the German keyboard, layers 1 and 2
+pub struct KoyDeDe();
+
+impl ILayout<46> for KoyDeDe {
+    fn get(&self) -> Layout {
+        Layout::KoyDeDe
+    }
+}
@@ -1,37 +1,112 @@
+use std::ops::Index;
+
 use super::{
-    layout::{QwertyEnUs, QwertzDeDe},
+    geometry::GeometryError,
+    layout::{
+        BoneDeDe, ColemakDhEnUs, ColemakEnUs, DvorakEnUs, KoyDeDe, Neo2DeDe, QwertyEnUs,
+        QwertzDeDe, WorkmanEnUs,
+    },
     Finger, Hand,
 };
 
 pub trait GetLayoutMap<const N: usize> {
-    fn get_layout_map() -> [KeyboardKey; N];
+    fn get_layout_map(&self) -> [KeyboardKey; N];
+
+    /// Keys that are not part of the genome, placed around the layout map by default
+    fn get_fixed_keys(&self) -> Result<FixedKeys, GeometryError> {
+        let layout_map = self.get_layout_map();
+
+        let mut keys = Vec::new();
+        for key in FixedKey::ALL {
+            keys.push(
+                get_default_fixed_key(&layout_map, key)
+                    .ok_or(GeometryError::MissingFixedKey(key.name()))?,
+            );
+        }
+
+        Ok(FixedKeys(
+            keys.try_into().expect("all fixed keys were placed"),
+        ))
+    }
 }
 
 impl GetLayoutMap<46> for QwertyEnUs {
-    fn get_layout_map() -> [KeyboardKey; 46] {
+    fn get_layout_map(&self) -> [KeyboardKey; 46] {
+        get_traditional_layout_map()
+    }
+}
+
+impl GetLayoutMap<46> for DvorakEnUs {
+    fn get_layout_map(&self) -> [KeyboardKey; 46] {
+        get_traditional_layout_map()
+    }
+}
+
+impl GetLayoutMap<46> for ColemakEnUs {
+    fn get_layout_map(&self) -> [KeyboardKey; 46] {
+        get_traditional_layout_map()
+    }
+}
+
+impl GetLayoutMap<46> for ColemakDhEnUs {
+    fn get_layout_map(&self) -> [KeyboardKey; 46] {
+        get_traditional_layout_map()
+    }
+}
+
+impl GetLayoutMap<46> for WorkmanEnUs {
+    fn get_layout_map(&self) -> [KeyboardKey; 46] {
         get_traditional_layout_map()
     }
 }
 
 impl GetLayoutMap<48> for QwertzDeDe {
-    fn get_layout_map() -> [KeyboardKey; 48] {
+    fn get_layout_map(&self) -> [KeyboardKey; 48] {
         get_traditional_qwertz_layout_map()
     }
 }
 
+impl GetLayoutMap<46> for Neo2DeDe {
+    fn get_layout_map(&self) -> [KeyboardKey; 46] {
+        get_neo_layout_map()
+    }
+}
+
+impl GetLayoutMap<46> for BoneDeDe {
+    fn get_layout_map(&self) -> [KeyboardKey; 46] {
+        get_neo_layout_map()
+    }
+}
+
+impl GetLayoutMap<46> for KoyDeDe {
+    fn get_layout_map(&self) -> [KeyboardKey; 46] {
+        get_neo_layout_map()
+    }
+}
+
 // ~~~ keyboard ~~~
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum KeyboardRow {
     Number = 0,
     TopLetter,
     MiddleLetter,
     BottomLetter,
+    Thumb,
 }
 
-#[derive(Debug)]
+/// Width of a standard key in millimetres
+pub const KEY_PITCH_MM: f64 = 19.05;
+
+/// A physical key, positions and sizes are in key units (the width of a standard key)
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct KeyboardKey {
-    pub(crate) x: i32,
-    pub(crate) y: i32,
+    /// center of the key, y grows from the thumb row towards the number row
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) width: f64,
+    pub(crate) height: f64,
+    /// degrees clockwise around the center
+    pub(crate) rotation: f64,
     pub(crate) row: KeyboardRow,
     pub(crate) hand: Hand,
     pub(crate) finger: Finger,
@@ -39,38 +114,74 @@ pub struct KeyboardKey {
 }
 
 impl KeyboardKey {
+    /// 0-3 left pinky to index, 4-7 right pinky to index, 8 left thumb, 9 right thumb
     pub fn get_finger_id(&self) -> usize {
-        (self.finger as usize)
-            + match self.hand {
-                Hand::Left => 0,
-                Hand::Right => 4,
-            }
+        match (self.finger, self.hand) {
+            (Finger::Thumb, Hand::Left) => 8,
+            (Finger::Thumb, Hand::Right) => 9,
+            (finger, Hand::Left) => finger as usize,
+            (finger, Hand::Right) => finger as usize + 4,
+        }
+    }
+
+    /// Finger numbered like in layout files:
+    /// 1-4 left pinky to index, 5-8 right index to pinky, 9 left thumb, 10 right thumb
+    pub(crate) fn get_finger_number(&self) -> i32 {
+        match (self.finger, self.hand) {
+            (Finger::Thumb, Hand::Left) => 9,
+            (Finger::Thumb, Hand::Right) => 10,
+            (finger, Hand::Left) => finger as i32 + 1,
+            (finger, Hand::Right) => 8 - finger as i32,
+        }
+    }
+
+    pub(crate) fn with_size(self, width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_rotation(self, rotation: f64) -> Self {
+        Self { rotation, ..self }
+    }
+
+    /// Manhattan distance between the key center and a point in key units
+    pub(crate) fn distance(&self, x: f64, y: f64) -> f64 {
+        (self.x - x).abs() + (self.y - y).abs()
     }
 }
 
-type RawLayout = (i32, i32, i32, i32, bool);
+/// `(x, y, row, finger, home)` of a key with the size of a standard key
+pub(crate) type RawLayout = (f64, f64, i32, i32, bool);
 
 impl From<RawLayout> for KeyboardKey {
     fn from(value: RawLayout) -> Self {
         Self {
             x: value.0,
             y: value.1,
+            width: 1.,
+            height: 1.,
+            rotation: 0.,
             row: match value.2 {
                 1 => KeyboardRow::Number,
                 2 => KeyboardRow::TopLetter,
                 3 => KeyboardRow::MiddleLetter,
                 4 => KeyboardRow::BottomLetter,
+                5 => KeyboardRow::Thumb,
                 _ => panic!(),
             },
-            hand: match value.3 > 4 {
-                true => Hand::Right,
-                false => Hand::Left,
+            hand: match value.3 {
+                1..=4 | 9 => Hand::Left,
+                _ => Hand::Right,
             },
             finger: match value.3 {
-                1 | 8 => Finger::Ring,
-                2 | 7 => Finger::Middle,
-                3 | 6 => Finger::Index,
-                4 | 5 => Finger::Thumb,
+                1 | 8 => Finger::Pinky,
+                2 | 7 => Finger::Ring,
+                3 | 6 => Finger::Middle,
+                4 | 5 => Finger::Index,
+                9 | 10 => Finger::Thumb,
                 _ => panic!(),
             },
             home: value.4,
@@ -78,6 +189,105 @@ impl From<RawLayout> for KeyboardKey {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixedKey {
+    LeftShift = 0,
+    RightShift,
+    LeftSpace,
+    RightSpace,
+    Tab,
+    Enter,
+    Backspace,
+}
+
+impl FixedKey {
+    pub const ALL: [FixedKey; 7] = [
+        FixedKey::LeftShift,
+        FixedKey::RightShift,
+        FixedKey::LeftSpace,
+        FixedKey::RightSpace,
+        FixedKey::Tab,
+        FixedKey::Enter,
+        FixedKey::Backspace,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FixedKey::LeftShift => "left_shift",
+            FixedKey::RightShift => "right_shift",
+            FixedKey::LeftSpace => "left_space",
+            FixedKey::RightSpace => "right_space",
+            FixedKey::Tab => "tab",
+            FixedKey::Enter => "enter",
+            FixedKey::Backspace => "backspace",
+        }
+    }
+}
+
+/// Shift, space, tab, enter and backspace, they are not part of the genome
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedKeys(pub(crate) [KeyboardKey; 7]);
+
+impl Index<FixedKey> for FixedKeys {
+    type Output = KeyboardKey;
+
+    fn index(&self, index: FixedKey) -> &Self::Output {
+        &self.0[index as usize]
+    }
+}
+
+impl FixedKeys {
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &KeyboardKey> {
+        self.0.iter()
+    }
+}
+
+/// Place a fixed key next to the rows of the layout map like on a traditional keyboard,
+/// returns `None` if the row it belongs to is missing
+pub(crate) fn get_default_fixed_key(
+    layout_map: &[KeyboardKey],
+    fixed_key: FixedKey,
+) -> Option<KeyboardKey> {
+    let row_keys = |row: KeyboardRow| layout_map.iter().filter(move |key| key.row == row);
+    let leftmost = |row| row_keys(row).min_by(|a, b| a.x.total_cmp(&b.x));
+    let rightmost = |row| row_keys(row).max_by(|a, b| a.x.total_cmp(&b.x));
+
+    let (raw, width): (RawLayout, f64) = match fixed_key {
+        FixedKey::LeftShift => {
+            let key = leftmost(KeyboardRow::BottomLetter)?;
+            ((key.x - 1.5, key.y, 4, 1, false), 2.)
+        }
+        FixedKey::RightShift => {
+            let key = rightmost(KeyboardRow::BottomLetter)?;
+            ((key.x + 1.5, key.y, 4, 8, false), 2.)
+        }
+        FixedKey::LeftSpace | FixedKey::RightSpace => {
+            let left = leftmost(KeyboardRow::BottomLetter)?;
+            let right = rightmost(KeyboardRow::BottomLetter)?;
+            let center = (left.x + right.x) / 2.;
+
+            match fixed_key {
+                FixedKey::LeftSpace => ((center - 1., left.y - 1., 5, 9, true), 2.),
+                _ => ((center + 1., left.y - 1., 5, 10, true), 2.),
+            }
+        }
+        FixedKey::Tab => {
+            let key = leftmost(KeyboardRow::TopLetter)?;
+            ((key.x - 1.25, key.y, 2, 1, false), 1.5)
+        }
+        FixedKey::Enter => {
+            let key = rightmost(KeyboardRow::MiddleLetter)?;
+            ((key.x + 1.5, key.y, 3, 8, false), 2.)
+        }
+        FixedKey::Backspace => {
+            let key = rightmost(KeyboardRow::Number)?;
+            ((key.x + 1.5, key.y, 1, 8, false), 2.)
+        }
+    };
+
+    Some(KeyboardKey::from(raw).with_size(width, 1.))
+}
+
 fn into_layout_map<const N: usize>(value: [RawLayout; N]) -> [KeyboardKey; N] {
     value
         .into_iter()
@@ -89,104 +299,112 @@ fn into_layout_map<const N: usize>(value: [RawLayout; N]) -> [KeyboardKey; N] {
 
 fn get_traditional_layout_map() -> [KeyboardKey; 46] {
     into_layout_map([
-        (50, 450, 1, 1, false),
-        (150, 450, 1, 1, false),
-        (250, 450, 1, 1, false),
-        (350, 450, 1, 2, false),
-        (450, 450, 1, 3, false),
-        (550, 450, 1, 4, false),
-        (650, 450, 1, 4, false),
-        (750, 450, 1, 5, false),
-        (850, 450, 1, 6, false),
-        (950, 450, 1, 7, false),
-        (1050, 450, 1, 8, false),
-        (1150, 450, 1, 8, false),
-        (1250, 450, 1, 8, false),
-        (200, 350, 2, 1, false),
-        (300, 350, 2, 2, false),
-        (400, 350, 2, 3, false),
-        (500, 350, 2, 4, false),
-        (600, 350, 2, 4, false),
-        (700, 350, 2, 5, false),
-        (800, 350, 2, 5, false),
-        (900, 350, 2, 6, false),
-        (1000, 350, 2, 7, false),
-        (1100, 350, 2, 8, false),
-        (1200, 350, 2, 8, false),
-        (1300, 350, 2, 8, false),
-        (225, 250, 3, 1, true),
-        (325, 250, 3, 2, true),
-        (425, 250, 3, 3, true),
-        (525, 250, 3, 4, true),
-        (625, 250, 3, 4, false),
-        (725, 250, 3, 5, false),
-        (825, 250, 3, 5, true),
-        (925, 250, 3, 6, true),
-        (1025, 250, 3, 7, true),
-        (1125, 250, 3, 8, true),
-        (1225, 250, 3, 8, false),
-        (275, 150, 4, 1, false),
-        (375, 150, 4, 2, false),
-        (475, 150, 4, 3, false),
-        (575, 150, 4, 4, false),
-        (675, 150, 4, 4, false),
-        (775, 150, 4, 5, false),
-        (875, 150, 4, 5, false),
-        (975, 150, 4, 6, false),
-        (1075, 150, 4, 7, false),
-        (1175, 150, 4, 8, false),
+        (0.5, 4.5, 1, 1, false),
+        (1.5, 4.5, 1, 1, false),
+        (2.5, 4.5, 1, 1, false),
+        (3.5, 4.5, 1, 2, false),
+        (4.5, 4.5, 1, 3, false),
+        (5.5, 4.5, 1, 4, false),
+        (6.5, 4.5, 1, 4, false),
+        (7.5, 4.5, 1, 5, false),
+        (8.5, 4.5, 1, 6, false),
+        (9.5, 4.5, 1, 7, false),
+        (10.5, 4.5, 1, 8, false),
+        (11.5, 4.5, 1, 8, false),
+        (12.5, 4.5, 1, 8, false),
+        (2.0, 3.5, 2, 1, false),
+        (3.0, 3.5, 2, 2, false),
+        (4.0, 3.5, 2, 3, false),
+        (5.0, 3.5, 2, 4, false),
+        (6.0, 3.5, 2, 4, false),
+        (7.0, 3.5, 2, 5, false),
+        (8.0, 3.5, 2, 5, false),
+        (9.0, 3.5, 2, 6, false),
+        (10.0, 3.5, 2, 7, false),
+        (11.0, 3.5, 2, 8, false),
+        (12.0, 3.5, 2, 8, false),
+        (13.0, 3.5, 2, 8, false),
+        (2.25, 2.5, 3, 1, true),
+        (3.25, 2.5, 3, 2, true),
+        (4.25, 2.5, 3, 3, true),
+        (5.25, 2.5, 3, 4, true),
+        (6.25, 2.5, 3, 4, false),
+        (7.25, 2.5, 3, 5, false),
+        (8.25, 2.5, 3, 5, true),
+        (9.25, 2.5, 3, 6, true),
+        (10.25, 2.5, 3, 7, true),
+        (11.25, 2.5, 3, 8, true),
+        (12.25, 2.5, 3, 8, false),
+        (2.75, 1.5, 4, 1, false),
+        (3.75, 1.5, 4, 2, false),
+        (4.75, 1.5, 4, 3, false),
+        (5.75, 1.5, 4, 4, false),
+        (6.75, 1.5, 4, 4, false),
+        (7.75, 1.5, 4, 5, false),
+        (8.75, 1.5, 4, 5, false),
+        (9.75, 1.5, 4, 6, false),
+        (10.75, 1.5, 4, 7, false),
+        (11.75, 1.5, 4, 8, false),
     ])
 }
 
 fn get_traditional_qwertz_layout_map() -> [KeyboardKey; 48] {
     into_layout_map([
-        (50, 450, 1, 1, false),
-        (150, 450, 1, 1, false),
-        (250, 450, 1, 1, false),
-        (350, 450, 1, 2, false),
-        (450, 450, 1, 3, false),
-        (550, 450, 1, 4, false),
-        (650, 450, 1, 4, false),
-        (750, 450, 1, 5, false),
-        (850, 450, 1, 6, false),
-        (950, 450, 1, 7, false),
-        (1050, 450, 1, 8, false),
-        (1150, 450, 1, 8, false),
-        (1250, 450, 1, 8, false),
-        (200, 350, 2, 1, false),
-        (300, 350, 2, 2, false),
-        (400, 350, 2, 3, false),
-        (500, 350, 2, 4, false),
-        (600, 350, 2, 4, false),
-        (700, 350, 2, 5, false),
-        (800, 350, 2, 5, false),
-        (900, 350, 2, 6, false),
-        (1000, 350, 2, 7, false),
-        (1100, 350, 2, 8, false),
-        (1200, 350, 2, 8, false),
-        (1300, 350, 2, 8, false),
-        (225, 250, 3, 1, true),
-        (325, 250, 3, 2, true),
-        (425, 250, 3, 3, true),
-        (525, 250, 3, 4, true),
-        (625, 250, 3, 4, false),
-        (725, 250, 3, 5, false),
-        (825, 250, 3, 5, true),
-        (925, 250, 3, 6, true),
-        (1025, 250, 3, 7, true),
-        (1125, 250, 3, 8, true),
-        (1225, 250, 3, 8, false),
-        (1325, 250, 3, 8, false),
-        (175, 150, 4, 1, false),
-        (275, 150, 4, 1, false),
-        (375, 150, 4, 2, false),
-        (475, 150, 4, 3, false),
-        (575, 150, 4, 4, false),
-        (675, 150, 4, 4, false),
-        (775, 150, 4, 5, false),
-        (875, 150, 4, 5, false),
-        (975, 150, 4, 6, false),
-        (1075, 150, 4, 7, false),
-        (1175, 150, 4, 8, false),
+        (0.5, 4.5, 1, 1, false),
+        (1.5, 4.5, 1, 1, false),
+        (2.5, 4.5, 1, 1, false),
+        (3.5, 4.5, 1, 2, false),
+        (4.5, 4.5, 1, 3, false),
+        (5.5, 4.5, 1, 4, false),
+        (6.5, 4.5, 1, 4, false),
+        (7.5, 4.5, 1, 5, false),
+        (8.5, 4.5, 1, 6, false),
+        (9.5, 4.5, 1, 7, false),
+        (10.5, 4.5, 1, 8, false),
+        (11.5, 4.5, 1, 8, false),
+        (12.5, 4.5, 1, 8, false),
+        (2.0, 3.5, 2, 1, false),
+        (3.0, 3.5, 2, 2, false),
+        (4.0, 3.5, 2, 3, false),
+        (5.0, 3.5, 2, 4, false),
+        (6.0, 3.5, 2, 4, false),
+        (7.0, 3.5, 2, 5, false),
+        (8.0, 3.5, 2, 5, false),
+        (9.0, 3.5, 2, 6, false),
+        (10.0, 3.5, 2, 7, false),
+        (11.0, 3.5, 2, 8, false),
+        (12.0, 3.5, 2, 8, false),
+        (13.0, 3.5, 2, 8, false),
+        (2.25, 2.5, 3, 1, true),
+        (3.25, 2.5, 3, 2, true),
+        (4.25, 2.5, 3, 3, true),
+        (5.25, 2.5, 3, 4, true),
+        (6.25, 2.5, 3, 4, false),
+        (7.25, 2.5, 3, 5, false),
+        (8.25, 2.5, 3, 5, true),
+        (9.25, 2.5, 3, 6, true),
+        (10.25, 2.5, 3, 7, true),
+        (11.25, 2.5, 3, 8, true),
+        (12.25, 2.5, 3, 8, false),
+        (13.25, 2.5, 3, 8, false),
+        (1.75, 1.5, 4, 1, false),
+        (2.75, 1.5, 4, 1, false),
+        (3.75, 1.5, 4, 2, false),
+        (4.75, 1.5, 4, 3, false),
+        (5.75, 1.5, 4, 4, false),
+        (6.75, 1.5, 4, 4, false),
+        (7.75, 1.5, 4, 5, false),
+        (8.75, 1.5, 4, 5, false),
+        (9.75, 1.5, 4, 6, false),
+        (10.75, 1.5, 4, 7, false),
+        (11.75, 1.5, 4, 8, false),
     ])
 }
+
+/// German keyboard without the keys right of the home row and left of the bottom row,
+/// they are modifiers in the Neo family
+fn get_neo_layout_map() -> [KeyboardKey; 46] {
+    let qwertz = get_traditional_qwertz_layout_map();
+
+    [&qwertz[..36], &qwertz[38..]].concat().try_into().unwrap()
+}
@@ -1,18 +1,63 @@
-use super::layout::{QwertyEnUs, QwertzDeDe};
+use super::layout::{
+    BoneDeDe, ColemakDhEnUs, ColemakEnUs, DvorakEnUs, KoyDeDe, Neo2DeDe, QwertyEnUs, QwertzDeDe,
+    WorkmanEnUs,
+};
 
 // alphabet
 pub trait GetLetterList<const N: usize> {
-    fn get_letter_list() -> &'static [char; N];
+    fn get_letter_list(&self) -> &[char; N];
 }
 
 impl GetLetterList<46> for QwertyEnUs {
-    fn get_letter_list() -> &'static [char; 46] {
+    fn get_letter_list(&self) -> &[char; 46] {
         &LETTER_LIST_QWERTY
     }
 }
 
+impl GetLetterList<46> for DvorakEnUs {
+    fn get_letter_list(&self) -> &[char; 46] {
+        &LETTER_LIST_QWERTY
+    }
+}
+
+impl GetLetterList<46> for ColemakEnUs {
+    fn get_letter_list(&self) -> &[char; 46] {
+        &LETTER_LIST_QWERTY
+    }
+}
+
+impl GetLetterList<46> for ColemakDhEnUs {
+    fn get_letter_list(&self) -> &[char; 46] {
+        &LETTER_LIST_QWERTY
+    }
+}
+
+impl GetLetterList<46> for WorkmanEnUs {
+    fn get_letter_list(&self) -> &[char; 46] {
+        &LETTER_LIST_QWERTY
+    }
+}
+
+impl GetLetterList<46> for Neo2DeDe {
+    fn get_letter_list(&self) -> &[char; 46] {
+        &LETTER_LIST_NEO
+    }
+}
+
+impl GetLetterList<46> for BoneDeDe {
+    fn get_letter_list(&self) -> &[char; 46] {
+        &LETTER_LIST_NEO
+    }
+}
+
+impl GetLetterList<46> for KoyDeDe {
+    fn get_letter_list(&self) -> &[char; 46] {
+        &LETTER_LIST_NEO
+    }
+}
+
 impl GetLetterList<48> for QwertzDeDe {
-    fn get_letter_list() -> &'static [char; 48] {
+    fn get_letter_list(&self) -> &[char; 48] {
         &LETTER_LIST_QWERTZ
     }
 }
@@ -28,3 +73,9 @@ const LETTER_LIST_QWERTZ: [char; 48] = [
     'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '^', 'ß',
     '´', 'Ü', 'Ö', 'Ä', '+', '#', '<', ',', '.', '-',
 ];
+
+const LETTER_LIST_NEO: [char; 46] = [
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
+    'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '^', 'ß',
+    '´', 'Ü', 'Ö', 'Ä', '`', ',', '.', '-',
+];
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use crate::objective::KeyPress;
+
+/// Everything that is needed to restore the typing state before a key press.
+///
+/// Only the finger of the last key press is away from its home key, so the last key press
+/// is enough, except for spaces where the thumb depends on the key press before them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Context {
+    Start,
+    After(KeyPress),
+    /// `odd` spaces in a row of spaces after `after` (or the start of the text)
+    Space {
+        after: Option<KeyPress>,
+        odd: bool,
+    },
+}
+
+/// Corpus condensed into key press counts.
+///
+/// The objective of a genome only depends on pairs of context and key press,
+/// so evaluating the bigrams gives the same score as replaying the whole text.
+/// Summing the counts of a key press over all contexts gives its unigram count.
+#[derive(Debug, Clone)]
+pub(crate) struct NgramStats {
+    pub(crate) bigrams: Vec<(Context, KeyPress, usize)>,
+    /// consecutive letters as indices into the letter list, any other key press separates them
+    pub(crate) letter_bigrams: Vec<([usize; 2], usize)>,
+    pub(crate) letter_trigrams: Vec<([usize; 3], usize)>,
+    /// whether space, enter, tab and backspace are part of the key presses
+    pub(crate) whitespace: bool,
+}
+
+impl NgramStats {
+    pub(crate) fn new(key_presses: &[KeyPress], whitespace: bool) -> Self {
+        let mut bigrams = BTreeMap::new();
+        let mut letter_bigrams = BTreeMap::new();
+        let mut letter_trigrams = BTreeMap::new();
+
+        let mut context = Context::Start;
+        let mut letters: [Option<usize>; 2] = [None; 2];
+
+        for key_press in key_presses {
+            let letter = match key_press {
+                KeyPress::Letter(key, _) => Some(*key),
+                _ => None,
+            };
+            if let (Some(c), [a, Some(b)]) = (letter, letters) {
+                *letter_bigrams.entry([b, c]).or_insert(0) += 1;
+                if let Some(a) = a {
+                    *letter_trigrams.entry([a, b, c]).or_insert(0) += 1;
+                }
+            }
+            letters = match letter {
+                Some(c) => [letters[1], Some(c)],
+                None => [None; 2],
+            };
+
+            if !whitespace && !matches!(key_press, KeyPress::Letter(..)) {
+                continue;
+            }
+
+            *bigrams.entry((context, *key_press)).or_insert(0) += 1;
+
+            context = match (key_press, context) {
+                (KeyPress::Space, Context::Start) => Context::Space {
+                    after: None,
+                    odd: false,
+                },
+                (KeyPress::Space, Context::After(after)) => Context::Space {
+                    after: Some(after),
+                    odd: false,
+                },
+                (KeyPress::Space, Context::Space { after, odd }) => {
+                    Context::Space { after, odd: !odd }
+                }
+                (key_press, _) => Context::After(*key_press),
+            };
+        }
+
+        Self {
+            bigrams: bigrams
+                .into_iter()
+                .map(|((context, key_press), count)| (context, key_press, count))
+                .collect(),
+            letter_bigrams: letter_bigrams.into_iter().collect(),
+            letter_trigrams: letter_trigrams.into_iter().collect(),
+            whitespace,
+        }
+    }
+}
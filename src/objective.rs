@@ -1,35 +1,147 @@
 use std::collections::BTreeMap;
 
-use crate::advanced_setup::{
-    DISTANCE_EFFORT, DOUBLE_FINGER_EFFORT, DOUBLE_HAND_EFFORT, EFFORT_WEIGHTING, FINGER_EFFORT,
-    ROW_EFFORT,
-};
+use crate::advanced_setup::EffortModel;
+use crate::metrics::count_metrics;
 use crate::models::finger_list::{get_finger_list, FingerList};
 use crate::models::layout::ILayout;
-use crate::models::layout_map::KeyboardKey;
+use crate::models::layout_map::{FixedKey, FixedKeys, KeyboardKey};
 use crate::models::Hand;
+use crate::ngrams::{Context, NgramStats};
+
+/// Key units are counted in hundredths for the distance penalty,
+/// the scale that the weights of the effort model were tuned with
+const DISTANCE_SCALE: f64 = 100.;
 
 // OBJECTIVE FUNCTIONS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum KeyPress {
+    /// Index into the letter list and whether shift is held
+    Letter(usize, bool),
+    Space,
+    Enter,
+    Tab,
+    Backspace,
+}
+
 pub(crate) fn determine_keypress<L: ILayout<N>, const N: usize>(
+    layout: &L,
     current_character: char,
-) -> Option<usize> {
-    // proceed if valid key (e.g. we don't care about spaces now)
-    L::get_key_map(current_character).map(|(kp, _)| kp - 1)
+) -> Option<KeyPress> {
+    if let Some((kp, shift)) = layout.get_key_map(current_character) {
+        return Some(KeyPress::Letter(kp - 1, shift));
+    }
+
+    // proceed if valid key (e.g. we don't care about carriage returns)
+    match current_character {
+        ' ' => Some(KeyPress::Space),
+        '\n' => Some(KeyPress::Enter),
+        '\t' => Some(KeyPress::Tab),
+        '\u{8}' => Some(KeyPress::Backspace),
+        _ => None,
+    }
+}
+
+pub(crate) fn get_key_presses<L: ILayout<N>, const N: usize>(
+    layout: &L,
+    text: &str,
+) -> Vec<KeyPress> {
+    text.chars()
+        .filter_map(|c| determine_keypress(layout, c))
+        .collect()
 }
 
+/// Effort model with the derived finger and row efforts precomputed
+pub(crate) struct Efforts<'a> {
+    model: &'a EffortModel,
+    finger: [f64; 10],
+    row: [f64; 5],
+}
+
+impl<'a> From<&'a EffortModel> for Efforts<'a> {
+    fn from(model: &'a EffortModel) -> Self {
+        Self {
+            model,
+            finger: model.finger_effort(),
+            row: model.row_effort(),
+        }
+    }
+}
+
+/// Weighted penalties of a key press in the order of `effort_weighting`, then the shift effort
+pub(crate) type Penalties = [f64; 6];
+
+/// Key index of every letter of the letter list in a genome
+pub(crate) fn get_key_positions<const N: usize>(
+    my_genome: &[char; N],
+    letter_list: &[char; N],
+) -> [usize; N] {
+    let genome_key_map =
+        BTreeMap::<char, usize>::from_iter(my_genome.iter().enumerate().map(|x| (*x.1, x.0)));
+
+    letter_list.map(|named_key| {
+        *genome_key_map
+            .get(&named_key)
+            .expect("Key should be in genome")
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn do_keypress<const N: usize>(
     my_finger_list: &mut FingerList,
-    my_genome: &BTreeMap<char, usize>,
-    key_press: usize,
+    key_positions: &[usize; N],
+    key_press: KeyPress,
     old_finger: &mut usize,
     old_hand: &mut Option<Hand>,
     layout_map: &[KeyboardKey; N],
-    letter_list: &[char; N],
+    fixed_keys: &FixedKeys,
+    efforts: &Efforts,
+    on_press: &mut impl FnMut(&KeyboardKey, &Penalties),
 ) {
-    let named_key = letter_list[key_press];
-    let actual_key = my_genome.get(&named_key).expect("Key should be in genome");
+    let fixed_key = match key_press {
+        KeyPress::Letter(key, shift) => {
+            let layout = &layout_map[key_positions[key]];
+
+            if shift && efforts.model.shift {
+                // shift is pressed with the outer finger of the other hand
+                let shift_key = match layout.hand {
+                    Hand::Left => &fixed_keys[FixedKey::RightShift],
+                    Hand::Right => &fixed_keys[FixedKey::LeftShift],
+                };
+                let mut penalties =
+                    press_key(my_finger_list, shift_key, old_finger, old_hand, efforts);
+                my_finger_list[shift_key.get_finger_id()].objective_counter +=
+                    efforts.model.shift_effort;
+                penalties[5] = efforts.model.shift_effort;
+                on_press(shift_key, &penalties);
+            }
 
-    let layout = &layout_map[*actual_key];
+            let penalties = press_key(my_finger_list, layout, old_finger, old_hand, efforts);
+            on_press(layout, &penalties);
+            return;
+        }
+        _ if !efforts.model.whitespace => return,
+        // space is pressed with the thumb of the other hand than the previous key
+        KeyPress::Space => match old_hand {
+            Some(Hand::Left) => FixedKey::RightSpace,
+            _ => FixedKey::LeftSpace,
+        },
+        KeyPress::Enter => FixedKey::Enter,
+        KeyPress::Tab => FixedKey::Tab,
+        KeyPress::Backspace => FixedKey::Backspace,
+    };
+
+    let layout = &fixed_keys[fixed_key];
+    let penalties = press_key(my_finger_list, layout, old_finger, old_hand, efforts);
+    on_press(layout, &penalties);
+}
+
+fn press_key(
+    my_finger_list: &mut FingerList,
+    layout: &KeyboardKey,
+    old_finger: &mut usize,
+    old_hand: &mut Option<Hand>,
+    efforts: &Efforts,
+) -> Penalties {
     let current_hand = layout.hand;
     let layout_finger_id = layout.get_finger_id();
 
@@ -45,13 +157,12 @@ pub(crate) fn do_keypress<const N: usize>(
     let finger_id = layout_finger_id;
     let my_finger = &mut my_finger_list[finger_id];
 
-    let distance =
-        (layout.x.abs_diff(my_finger.current_x) + layout.y.abs_diff(my_finger.current_y)) as i32;
-    let distance_penalty = distance.pow(DISTANCE_EFFORT);
+    let distance = layout.distance(my_finger.current_x, my_finger.current_y);
+    let distance_penalty = (distance * DISTANCE_SCALE).powi(efforts.model.distance_effort as i32);
     let new_distance = my_finger.distance_counter + distance;
 
-    let double_finger_penalty = if finger_id != *old_finger && *old_finger != 0 && distance != 0 {
-        DOUBLE_FINGER_EFFORT
+    let double_finger_penalty = if finger_id != *old_finger && *old_finger != 0 && distance != 0. {
+        efforts.model.double_finger_effort
     } else {
         0
     };
@@ -59,47 +170,47 @@ pub(crate) fn do_keypress<const N: usize>(
     *old_finger = finger_id;
 
     let double_hand_penalty = if Some(current_hand) != *old_hand && *old_hand != Some(Hand::Left) {
-        DOUBLE_HAND_EFFORT
+        efforts.model.double_hand_effort
     } else {
         0
     };
     *old_hand = Some(current_hand);
 
-    let finger_penalty = FINGER_EFFORT[finger_id];
-    let row_penalty = ROW_EFFORT[layout.row as usize];
+    let finger_penalty = efforts.finger[finger_id];
+    let row_penalty = efforts.row[layout.row as usize];
 
     let penalties = [
-        distance_penalty as f64,
+        distance_penalty,
         double_finger_penalty as f64,
         double_hand_penalty as f64,
         finger_penalty,
         row_penalty,
     ];
-    let penalty = penalties
-        .iter()
-        .zip(EFFORT_WEIGHTING.iter())
-        .map(|(x, y)| x * (*y))
-        .sum::<f64>();
+    let mut weighted: Penalties = [0.; 6];
+    for (weighted, (x, y)) in weighted
+        .iter_mut()
+        .zip(penalties.iter().zip(efforts.model.effort_weighting.iter()))
+    {
+        *weighted = x * y;
+    }
+    let penalty = weighted.iter().sum::<f64>();
     let new_objective = my_finger.objective_counter + penalty;
 
     my_finger.current_x = layout.x;
     my_finger.current_y = layout.y;
     my_finger.distance_counter = new_distance;
     my_finger.objective_counter = new_objective;
+
+    weighted
 }
 
-/// Calculate the objective function for a given file, genome, and layout map
-pub(crate) fn objective_function<L: ILayout<N>, const N: usize>(
-    key_presses: &[usize],
-    my_genome: &[char; N],
+pub(crate) fn get_home_finger_list<const N: usize>(
     layout_map: &[KeyboardKey; N],
-    layout_score: Option<f64>,
-    letter_list: &[char; N],
-) -> f64 {
-    // create hand
+    fixed_keys: &FixedKeys,
+) -> FingerList {
     let mut my_finger_list: FingerList = get_finger_list();
 
-    for layout in layout_map {
+    for layout in layout_map.iter().chain(fixed_keys.iter()) {
         if layout.home {
             let my_finger = &mut my_finger_list[layout.get_finger_id()];
             my_finger.home_x = layout.x;
@@ -109,45 +220,297 @@ pub(crate) fn objective_function<L: ILayout<N>, const N: usize>(
         }
     }
 
+    my_finger_list
+}
+
+/// Key of the last press of a context
+fn get_context_key<'a, const N: usize>(
+    context: &Context,
+    key_positions: &[usize; N],
+    layout_map: &'a [KeyboardKey; N],
+    fixed_keys: &'a FixedKeys,
+) -> Option<&'a KeyboardKey> {
+    let get_key = |key_press: &KeyPress| match key_press {
+        KeyPress::Letter(key, _) => &layout_map[key_positions[*key]],
+        KeyPress::Enter => &fixed_keys[FixedKey::Enter],
+        KeyPress::Tab => &fixed_keys[FixedKey::Tab],
+        KeyPress::Backspace => &fixed_keys[FixedKey::Backspace],
+        KeyPress::Space => unreachable!("spaces have their own context"),
+    };
+
+    match context {
+        Context::Start => None,
+        Context::After(key_press) => Some(get_key(key_press)),
+        Context::Space { after, odd } => {
+            // same rule as in `do_keypress`
+            let first_left = after.map(|key_press| get_key(&key_press).hand) != Some(Hand::Left);
+            match first_left != *odd {
+                true => Some(&fixed_keys[FixedKey::LeftSpace]),
+                false => Some(&fixed_keys[FixedKey::RightSpace]),
+            }
+        }
+    }
+}
+
+/// Objective of a single key press after its context
+pub(crate) fn bigram_objective<const N: usize>(
+    context: &Context,
+    key_press: KeyPress,
+    key_positions: &[usize; N],
+    home_finger_list: &FingerList,
+    layout_map: &[KeyboardKey; N],
+    fixed_keys: &FixedKeys,
+    efforts: &Efforts,
+) -> f64 {
+    // restore the typing state after the context
+    let mut my_finger_list = home_finger_list.clone();
+    let mut old_finger: usize = 0;
+    let mut old_hand: Option<Hand> = None;
+
+    if let Some(layout) = get_context_key(context, key_positions, layout_map, fixed_keys) {
+        let finger_id = layout.get_finger_id();
+        my_finger_list[finger_id].current_x = layout.x;
+        my_finger_list[finger_id].current_y = layout.y;
+        old_finger = finger_id;
+        old_hand = Some(layout.hand);
+    }
+
+    do_keypress(
+        &mut my_finger_list,
+        key_positions,
+        key_press,
+        &mut old_finger,
+        &mut old_hand,
+        layout_map,
+        fixed_keys,
+        efforts,
+        &mut |_, _| {},
+    );
+
+    my_finger_list
+        .iter()
+        .map(|finger| finger.objective_counter)
+        .sum::<f64>()
+}
+
+/// Calculate the objective function for the n-gram statistics of a text, genome, and layout map
+pub(crate) fn objective_function<const N: usize>(
+    ngram_stats: &NgramStats,
+    my_genome: &[char; N],
+    layout_map: &[KeyboardKey; N],
+    fixed_keys: &FixedKeys,
+    layout_score: Option<f64>,
+    letter_list: &[char; N],
+    effort_model: &EffortModel,
+) -> f64 {
+    debug_assert_eq!(ngram_stats.whitespace, effort_model.whitespace);
+
+    // create hand
+    let home_finger_list = get_home_finger_list(layout_map, fixed_keys);
+
+    let efforts = Efforts::from(effort_model);
+    let key_positions = get_key_positions(my_genome, letter_list);
+
+    let mut objective = 0.;
+
+    for (context, key_press, count) in &ngram_stats.bigrams {
+        objective += bigram_objective(
+            context,
+            *key_press,
+            &key_positions,
+            &home_finger_list,
+            layout_map,
+            fixed_keys,
+            &efforts,
+        ) * *count as f64;
+    }
+
+    if !effort_model.metric_weights.is_zero() {
+        objective += count_metrics(ngram_stats, &key_positions, layout_map)
+            .weighted(&effort_model.metric_weights);
+    }
+
+    if let Some(layout_score) = layout_score {
+        objective = (objective / layout_score - 1.0) * 100.0;
+    }
+
+    objective
+}
+
+/// Calculate the objective function by replaying every key press of a text,
+/// reference for the n-gram based `objective_function`
+#[cfg(test)]
+pub(crate) fn replay_objective_function<const N: usize>(
+    key_presses: &[KeyPress],
+    my_genome: &[char; N],
+    layout_map: &[KeyboardKey; N],
+    fixed_keys: &FixedKeys,
+    letter_list: &[char; N],
+    effort_model: &EffortModel,
+) -> f64 {
+    // create hand
+    let mut my_finger_list = get_home_finger_list(layout_map, fixed_keys);
+
     // load text
     let mut old_finger: usize = 0;
     let mut old_hand: Option<Hand> = None;
 
-    let genome_key_map =
-        BTreeMap::<char, usize>::from_iter(my_genome.iter().enumerate().map(|x| (*x.1, x.0)));
+    let efforts = Efforts::from(effort_model);
+
+    let key_positions = get_key_positions(my_genome, letter_list);
 
     for key_press in key_presses {
         do_keypress(
             &mut my_finger_list,
-            &genome_key_map,
+            &key_positions,
             *key_press,
             &mut old_finger,
             &mut old_hand,
             layout_map,
-            letter_list,
+            fixed_keys,
+            &efforts,
+            &mut |_, _| {},
         );
     }
 
     // calculate objective
-    let mut objective = my_finger_list
+    my_finger_list
         .iter()
         .map(|finger| finger.objective_counter)
-        .sum::<f64>();
-
-    if let Some(layout_score) = layout_score {
-        objective = (objective / layout_score - 1.0) * 100.0;
-    }
-
-    // return
-    objective
+        .sum::<f64>()
 }
 
 /// Calculate the baseline objective function for a given file, genome, and layout map
-pub(crate) fn baseline_objective_function<L: ILayout<N>, const N: usize>(
-    key_presses: &[usize],
+pub(crate) fn baseline_objective_function<const N: usize>(
+    ngram_stats: &NgramStats,
     my_genome: &[char; N],
     layout_map: &[KeyboardKey; N],
+    fixed_keys: &FixedKeys,
     letter_list: &[char; N],
+    effort_model: &EffortModel,
 ) -> f64 {
-    objective_function::<L, N>(key_presses, my_genome, layout_map, None, letter_list)
+    objective_function(
+        ngram_stats,
+        my_genome,
+        layout_map,
+        fixed_keys,
+        None,
+        letter_list,
+        effort_model,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::prelude::*;
+
+    fn score(text: &str, effort_model: &EffortModel) -> f64 {
+        let layout = QwertzDeDe();
+
+        replay_objective_function(
+            &get_key_presses(&layout, text),
+            layout.get_genome(),
+            &layout.get_layout_map(),
+            &layout.get_fixed_keys().expect("fixed keys"),
+            layout.get_letter_list(),
+            effort_model,
+        )
+    }
+
+    #[test]
+    fn test_shift_is_penalized() {
+        let effort_model = EffortModel::default();
+        assert!(score("Hallo Welt", &effort_model) > score("hallo welt", &effort_model));
+        assert!(score("?", &effort_model) > score("ß", &effort_model));
+
+        let effort_model = EffortModel {
+            shift: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            score("Hallo Welt", &effort_model),
+            score("hallo welt", &effort_model)
+        );
+    }
+
+    #[test]
+    fn test_whitespace_is_pressed() {
+        let effort_model = EffortModel::default();
+        assert_eq!(score("", &effort_model), 0.);
+        assert!(score(" ", &effort_model) > 0.);
+        assert!(score("\n", &effort_model) > 0.);
+        assert!(score("\t", &effort_model) > 0.);
+        assert_eq!(score("\r", &effort_model), 0.);
+
+        let effort_model = EffortModel {
+            whitespace: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            score("hallo welt\n", &effort_model),
+            score("hallowelt", &effort_model)
+        );
+    }
+
+    fn assert_equivalent<L: ILayout<N>, const N: usize>(
+        layout: &L,
+        text: &str,
+        effort_model: &EffortModel,
+    ) {
+        let key_presses = get_key_presses(layout, text);
+        let ngram_stats = NgramStats::new(&key_presses, effort_model.whitespace);
+        let constraints = Constraints::none(layout.get_letter_list());
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let genomes = [
+            *layout.get_genome(),
+            crate::models::genome::create_genome(&constraints, &mut rng),
+        ];
+
+        for genome in genomes {
+            let replayed = replay_objective_function(
+                &key_presses,
+                &genome,
+                &layout.get_layout_map(),
+                &layout.get_fixed_keys().expect("fixed keys"),
+                layout.get_letter_list(),
+                effort_model,
+            );
+            let counted = baseline_objective_function(
+                &ngram_stats,
+                &genome,
+                &layout.get_layout_map(),
+                &layout.get_fixed_keys().expect("fixed keys"),
+                layout.get_letter_list(),
+                effort_model,
+            );
+
+            assert!(
+                (replayed - counted).abs() <= replayed * 1e-9,
+                "{replayed} {counted}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ngram_objective_matches_replay() {
+        let text = std::fs::read_to_string("resources/meinBuch.txt").expect("should read file");
+
+        for effort_model in [
+            EffortModel::default(),
+            EffortModel {
+                whitespace: false,
+                distance_effort: 2,
+                effort_weighting: [1.0; 5],
+                ..Default::default()
+            },
+        ] {
+            assert_equivalent(&QwertyEnUs(), &text, &effort_model);
+            assert_equivalent(&QwertzDeDe(), &text, &effort_model);
+            assert_equivalent(&QwertzDeDe(), "  a   Ab\n \t  ", &effort_model);
+        }
+    }
 }
@@ -0,0 +1,290 @@
+pub(crate) mod annealing;
+pub(crate) mod genetic;
+pub(crate) mod hill_climbing;
+pub(crate) mod late_acceptance;
+pub(crate) mod tabu;
+
+use rand::RngCore;
+use rand_chacha::ChaCha12Rng;
+
+use crate::delta::{DeltaEvaluator, EvaluatorState};
+use crate::models::constraints::Constraints;
+use crate::models::genome::{create_genome, random_swap};
+use crate::models::layout_map::KeyboardKey;
+
+/// Search strategy that improves a genome by swapping keys
+pub trait Optimizer<const N: usize>: Sync {
+    fn name(&self) -> &'static str;
+
+    /// Search from the current genome of `objective` and return the best genome found.
+    /// All random decisions have to use `rng`, so runs are reproducible.
+    fn optimize(&self, objective: &mut Objective<N>, rng: &mut ChaCha12Rng) -> [char; N];
+}
+
+/// Score of a genome that changes by swapping two keys.
+///
+/// Scores are + % worse and - % better than the baseline genome of the layout,
+/// evaluating a swap only looks at the n-grams of the two swapped keys.
+/// The penalties of violated constraint groups are added to the score.
+pub struct Objective<'p, const N: usize> {
+    evaluator: DeltaEvaluator<'p, N>,
+    baseline: f64,
+    constraints: &'p Constraints<N>,
+    /// penalty of the current genome
+    penalty: f64,
+    /// penalty after the last evaluated swap
+    pending_penalty: f64,
+}
+
+/// Saved genome of an `Objective` with everything its score depends on
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ObjectiveState<const N: usize> {
+    evaluator: EvaluatorState<N>,
+    penalty: f64,
+}
+
+impl<'p, const N: usize> Objective<'p, N> {
+    pub(crate) fn new(
+        evaluator: DeltaEvaluator<'p, N>,
+        baseline: f64,
+        constraints: &'p Constraints<N>,
+    ) -> Self {
+        let penalty = constraints.penalty(evaluator.genome());
+
+        Self {
+            evaluator,
+            baseline,
+            constraints,
+            penalty,
+            pending_penalty: penalty,
+        }
+    }
+
+    pub(crate) fn relative(&self, absolute: f64) -> f64 {
+        (absolute / self.baseline - 1.0) * 100.0
+    }
+
+    pub fn genome(&self) -> &[char; N] {
+        self.evaluator.genome()
+    }
+
+    /// Score of the current genome
+    pub fn score(&self) -> f64 {
+        self.relative(self.evaluator.objective()) + self.penalty
+    }
+
+    /// Raw objective of the current genome
+    pub fn absolute(&self) -> f64 {
+        self.evaluator.objective()
+    }
+
+    /// Score after swapping the keys at positions `a` and `b`,
+    /// the genome only changes with `commit_swap`
+    pub fn swap_score(&mut self, a: usize, b: usize) -> f64 {
+        let absolute = self.evaluator.objective() + self.evaluator.swap_delta(a, b);
+
+        self.pending_penalty = self.penalty;
+        if self.constraints.has_groups() {
+            let mut swapped = *self.genome();
+            swapped.swap(a, b);
+            self.pending_penalty = self.constraints.penalty(&swapped);
+        }
+
+        self.relative(absolute) + self.pending_penalty
+    }
+
+    /// Apply the swap of the last `swap_score`
+    pub fn commit_swap(&mut self) {
+        self.evaluator.commit_swap();
+        self.penalty = self.pending_penalty;
+    }
+
+    pub fn set_genome(&mut self, genome: &[char; N]) {
+        self.evaluator.set_genome(genome);
+        self.penalty = self.constraints.penalty(genome);
+        self.pending_penalty = self.penalty;
+    }
+
+    pub(crate) fn save(&self) -> ObjectiveState<N> {
+        ObjectiveState {
+            evaluator: self.evaluator.save(),
+            penalty: self.penalty,
+        }
+    }
+
+    /// Go back to a saved genome without scoring it again
+    pub(crate) fn restore(&mut self, state: &ObjectiveState<N>) {
+        self.evaluator.restore(&state.evaluator);
+        self.penalty = state.penalty;
+        self.pending_penalty = state.penalty;
+    }
+
+    /// Characters that moves must not change
+    pub fn constraints(&self) -> &'p Constraints<N> {
+        self.constraints
+    }
+
+    /// Random genome with the pinned characters in place and the groups repaired as far as possible
+    pub fn random_genome(&self, rng: &mut impl RngCore) -> [char; N] {
+        create_genome(self.constraints, rng)
+    }
+
+    /// Random pair of free positions to swap
+    pub fn random_swap(&self, rng: &mut impl RngCore) -> (usize, usize) {
+        random_swap(self.constraints, rng)
+    }
+
+    /// Every pair of free positions
+    pub fn swaps(&self) -> impl Iterator<Item = (usize, usize)> + 'p {
+        let free = self.constraints.free();
+        free.iter()
+            .enumerate()
+            .flat_map(move |(i, a)| free[i + 1..].iter().map(move |b| (*a, *b)))
+    }
+
+    /// Swap that gives the lowest score, the first one on ties
+    fn best_swap(
+        &mut self,
+        mut allowed: impl FnMut((usize, usize), f64) -> bool,
+    ) -> Option<((usize, usize), f64)> {
+        let mut best: Option<((usize, usize), f64)> = None;
+        for swap in self.swaps().collect::<Vec<_>>() {
+            let score = self.swap_score(swap.0, swap.1);
+            if allowed(swap, score) && best.is_none_or(|(_, best_score)| score < best_score) {
+                best = Some((swap, score));
+            }
+        }
+        best
+    }
+
+    /// Swap the keys at positions `a` and `b` right away
+    fn swap(&mut self, a: usize, b: usize) {
+        self.swap_score(a, b);
+        self.commit_swap();
+    }
+
+    pub(crate) fn resume(&mut self, absolute: f64) {
+        self.evaluator.resume(absolute);
+    }
+
+    pub(crate) fn layout_map(&self) -> &[KeyboardKey; N] {
+        self.evaluator.layout_map()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_optimizers_improve_random_genome() {
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let layout = QwertyEnUs();
+        let effort_model = EffortModel::default();
+
+        let optimizers: [&dyn Optimizer<46>; 5] = [
+            &SimulatedAnnealing {
+                setup: SaSetup {
+                    temperature: 500.,
+                    epoch: 20.,
+                    cooling_rate: 0.99,
+                    num_iterations: 2000,
+                    seed: 0,
+                },
+            },
+            &HillClimbing { max_steps: 20 },
+            &TabuSearch {
+                iterations: 20,
+                tenure: 5,
+            },
+            &LateAcceptance {
+                iterations: 2000,
+                history_length: 20,
+            },
+            &GeneticAlgorithm {
+                population_size: 20,
+                generations: 10,
+                crossover: Crossover::Pmx,
+                tournament_size: 3,
+                elitism: 2,
+                mutation_rate: 0.2,
+                mutated_keys: 2,
+                print_stats: false,
+            },
+        ];
+
+        let constraints = Constraints::none(layout.get_letter_list())
+            .keep('E', layout.get_genome())
+            .and_then(|constraints| constraints.pin('1', 30))
+            .expect("should pin");
+
+        let run = |optimizer| {
+            run_optimizer(&layout, &text, &effort_model, &constraints, optimizer, 3)
+                .expect("fixed keys")
+        };
+
+        let start = run(&HillClimbing { max_steps: 0 });
+
+        for optimizer in optimizers {
+            let result = run(optimizer);
+            assert!(
+                result.score.absolute < start.score.absolute,
+                "{} did not improve",
+                optimizer.name()
+            );
+            assert!(
+                constraints.allows(&result.genome),
+                "{} moved a pinned key",
+                optimizer.name()
+            );
+
+            let score =
+                score_genome(&layout, &text, &result.genome, &effort_model).expect("fixed keys");
+            assert!((score.absolute - result.score.absolute).abs() < 1e-6 * score.absolute);
+
+            let again = run(optimizer);
+            assert_eq!(
+                result.genome,
+                again.genome,
+                "{} is not reproducible",
+                optimizer.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_genetic_algorithm_reports_every_generation() {
+        let text = std::fs::read_to_string("resources/myBook.txt").expect("should read file");
+        let effort_model = EffortModel::default();
+        let constraints = Constraints::none(QwertyEnUs().get_letter_list());
+        let problem = crate::SaProblem::new(&QwertyEnUs(), &text, &effort_model, &constraints)
+            .expect("fixed keys");
+
+        for crossover in [Crossover::Pmx, Crossover::Order, Crossover::Cycle] {
+            let genetic_algorithm = GeneticAlgorithm {
+                population_size: 10,
+                generations: 5,
+                crossover,
+                tournament_size: 2,
+                elitism: 1,
+                mutation_rate: 0.5,
+                mutated_keys: 3,
+                print_stats: false,
+            };
+
+            let (mut objective, mut rng) = problem.random_start(1);
+            let (genome, stats) = genetic_algorithm.evolve(&mut objective, &mut rng);
+
+            assert_eq!(stats.len(), 5);
+            assert_eq!(objective.genome(), &genome);
+            // elitism keeps the best genome
+            for generations in stats.windows(2) {
+                assert!(generations[1].best <= generations[0].best);
+            }
+            for generation in &stats {
+                assert!(generation.best <= generation.mean && generation.mean <= generation.worst);
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use rand::Rng;
+use rand_chacha::ChaCha12Rng;
+
+use super::{Objective, ObjectiveState, Optimizer};
+use crate::draw::draw_keyboard;
+use crate::logging::{append_to_file, append_updates, LoggingOptions, SaveImageOption};
+use crate::SaSetup;
+
+/// Simulated annealing with random swaps as in `run_sa`,
+/// the seed of the setup is not used because the random generator is passed in
+pub struct SimulatedAnnealing {
+    pub setup: SaSetup,
+}
+
+impl<const N: usize> Optimizer<N> for SimulatedAnnealing {
+    fn name(&self) -> &'static str {
+        "simulated annealing"
+    }
+
+    fn optimize(&self, objective: &mut Objective<N>, rng: &mut ChaCha12Rng) -> [char; N] {
+        let mut state = SaState::new(objective, &self.setup);
+        anneal(
+            &mut state,
+            objective,
+            rng,
+            &self.setup,
+            &LoggingOptions::silent(),
+            |_, _, _| {},
+        );
+        state.best_genome
+    }
+}
+
+/// Progress of an annealing run between two iterations
+pub(crate) struct SaState<const N: usize> {
+    /// last finished iteration
+    pub(crate) iteration: usize,
+    pub(crate) temperature: f64,
+    pub(crate) static_count: f64,
+    pub(crate) current_objective: f64,
+    pub(crate) best_genome: [char; N],
+    pub(crate) best_objective: f64,
+    pub(crate) best_absolute: f64,
+    /// the best genome is restored from this instead of being scored again
+    pub(crate) best_state: ObjectiveState<N>,
+}
+
+impl<const N: usize> SaState<N> {
+    pub(crate) fn new(objective: &Objective<N>, setup: &SaSetup) -> Self {
+        Self {
+            iteration: 0,
+            temperature: setup.temperature,
+            static_count: 0.0,
+            current_objective: objective.score(),
+            best_genome: *objective.genome(),
+            best_objective: objective.score(),
+            best_absolute: objective.absolute(),
+            best_state: objective.save(),
+        }
+    }
+}
+
+/// Continue an annealing run until the temperature or the iterations of `setup` run out,
+/// `on_iteration` is called after every finished iteration
+pub(crate) fn anneal<const N: usize>(
+    state: &mut SaState<N>,
+    objective: &mut Objective<N>,
+    rng: &mut ChaCha12Rng,
+    setup: &SaSetup,
+    logging: &LoggingOptions,
+    mut on_iteration: impl FnMut(&SaState<N>, &Objective<N>, &ChaCha12Rng),
+) {
+    let SaSetup {
+        epoch,
+        cooling_rate,
+        num_iterations,
+        ..
+    } = *setup;
+
+    // run SA
+    for iteration in state.iteration + 1..=num_iterations {
+        let SaState {
+            temperature,
+            static_count,
+            current_objective,
+            best_genome,
+            best_objective,
+            best_absolute,
+            best_state,
+            ..
+        } = state;
+
+        if *temperature <= 1.0 {
+            break;
+        }
+
+        // ~ create new genome ~
+        let (a, b) = objective.random_swap(rng);
+
+        // ~ asses ~
+        // only the n-grams with the two swapped keys change
+        let new_objective = objective.swap_score(a, b);
+        let delta = new_objective - *current_objective;
+
+        if logging.verbosity.is_normal() {
+            println!("{temperature:.2}\t{iteration}\t{best_objective:.2}\t{new_objective:.2}");
+        }
+
+        if delta < 0.0 {
+            objective.commit_swap();
+            *current_objective = new_objective;
+
+            if logging.text {
+                let update_line = format!(
+                    "{temperature:.2}, {iteration}, {best_objective:.5}, {new_objective:.5}"
+                );
+                append_updates(&update_line);
+            }
+
+            if new_objective < *best_objective {
+                *best_genome = *objective.genome();
+                *best_objective = new_objective;
+                *best_absolute = objective.absolute();
+                *best_state = objective.save();
+
+                if SaveImageOption::AllBest == logging.image {
+                    println!("(new best, png being saved)");
+                    draw_keyboard(
+                        best_genome,
+                        iteration.to_string().as_str(),
+                        objective.layout_map(),
+                    );
+                }
+
+                if logging.text {
+                    append_to_file(
+                        "results/bestGenomes.txt",
+                        &format!("{iteration}: {best_genome:#?}\n"),
+                    );
+                }
+
+                if logging.image == SaveImageOption::None && logging.verbosity.is_normal() {
+                    println!("(new best)");
+                }
+            }
+        } else if (-delta / *temperature).exp() > rng.gen() {
+            objective.commit_swap();
+            *current_objective = new_objective;
+        }
+
+        *static_count += 1.0;
+
+        if *static_count > epoch {
+            *static_count = 0.0;
+            *temperature *= cooling_rate;
+
+            if rng.gen::<f64>() < 0.5 {
+                objective.restore(best_state);
+                *current_objective = *best_objective;
+            }
+        }
+
+        state.iteration = iteration;
+
+        on_iteration(state, objective, rng);
+    }
+}
@@ -0,0 +1,310 @@
+use rand::Rng;
+use rand_chacha::ChaCha12Rng;
+
+use super::{Objective, Optimizer};
+use crate::models::constraints::Constraints;
+use crate::models::genome::{create_genome, shuffle_genome};
+
+/// Crossover operators that keep the child a permutation of the parents
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crossover {
+    /// partially mapped crossover
+    Pmx,
+    /// order crossover
+    Order,
+    Cycle,
+}
+
+/// Scores of the population after a generation, + % worse and - % better than the baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationStats {
+    pub generation: usize,
+    pub best: f64,
+    pub mean: f64,
+    pub worst: f64,
+}
+
+/// Genetic algorithm over whole genomes
+pub struct GeneticAlgorithm {
+    pub population_size: usize,
+    pub generations: usize,
+    pub crossover: Crossover,
+    /// number of individuals that compete for every parent
+    pub tournament_size: usize,
+    /// number of best individuals copied unchanged into the next generation
+    pub elitism: usize,
+    /// probability that a child is mutated with `shuffle_genome`
+    pub mutation_rate: f64,
+    /// number of keys a mutation moves, at least 2
+    pub mutated_keys: usize,
+    /// print the statistics of every generation
+    pub print_stats: bool,
+}
+
+impl<const N: usize> Optimizer<N> for GeneticAlgorithm {
+    fn name(&self) -> &'static str {
+        "genetic algorithm"
+    }
+
+    fn optimize(&self, objective: &mut Objective<N>, rng: &mut ChaCha12Rng) -> [char; N] {
+        let (genome, _) = self.evolve(objective, rng);
+        genome
+    }
+}
+
+impl GeneticAlgorithm {
+    /// Evolve a population that starts with the current genome of `objective` and random genomes,
+    /// returns the best genome found and the statistics of every generation
+    pub fn evolve<const N: usize>(
+        &self,
+        objective: &mut Objective<N>,
+        rng: &mut ChaCha12Rng,
+    ) -> ([char; N], Vec<GenerationStats>) {
+        let population_size = self.population_size.max(2);
+        let elitism = self.elitism.min(population_size);
+
+        let start = *objective.genome();
+        let constraints = objective.constraints();
+
+        let mut evaluate = |genome: [char; N]| {
+            objective.set_genome(&genome);
+            (genome, objective.score())
+        };
+
+        let mut population: Vec<([char; N], f64)> = (0..population_size)
+            .map(|i| match i {
+                0 => evaluate(start),
+                _ => evaluate(create_genome(constraints, rng)),
+            })
+            .collect();
+        sort_by_score(&mut population);
+
+        let mut best = population[0];
+        let mut stats = Vec::with_capacity(self.generations);
+
+        if self.print_stats {
+            println!("Generation\tBest Score\tMean Score\tWorst Score");
+        }
+
+        for generation in 1..=self.generations {
+            let mut next: Vec<[char; N]> = population[..elitism]
+                .iter()
+                .map(|(genome, _)| *genome)
+                .collect();
+
+            while next.len() < population_size {
+                let parent_1 = self.tournament(&population, rng);
+                let parent_2 = self.tournament(&population, rng);
+
+                let mut child = match self.crossover {
+                    Crossover::Pmx => pmx(parent_1, parent_2, rng),
+                    Crossover::Order => order_crossover(parent_1, parent_2, constraints, rng),
+                    Crossover::Cycle => cycle_crossover(parent_1, parent_2, rng),
+                };
+
+                if rng.gen::<f64>() < self.mutation_rate {
+                    let temperature = self.mutated_keys as f64 * 100.0;
+                    child = shuffle_genome(&child, temperature, constraints, rng);
+                }
+
+                next.push(child);
+            }
+
+            population = next.into_iter().map(&mut evaluate).collect();
+            sort_by_score(&mut population);
+
+            if population[0].1 < best.1 {
+                best = population[0];
+            }
+
+            let scores = population.iter().map(|(_, score)| *score);
+            let generation_stats = GenerationStats {
+                generation,
+                best: population[0].1,
+                mean: scores.sum::<f64>() / population.len() as f64,
+                worst: population[population.len() - 1].1,
+            };
+
+            if self.print_stats {
+                let GenerationStats {
+                    best, mean, worst, ..
+                } = generation_stats;
+                println!("{generation}\t{best:.2}\t{mean:.2}\t{worst:.2}");
+            }
+
+            stats.push(generation_stats);
+        }
+
+        objective.set_genome(&best.0);
+
+        (best.0, stats)
+    }
+
+    /// Best of `tournament_size` random individuals
+    fn tournament<'p, const N: usize>(
+        &self,
+        population: &'p [([char; N], f64)],
+        rng: &mut ChaCha12Rng,
+    ) -> &'p [char; N] {
+        let (genome, _) = (0..self.tournament_size.max(1))
+            .map(|_| &population[rng.gen_range(0..population.len())])
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .expect("tournament has at least one individual");
+        genome
+    }
+}
+
+fn sort_by_score<const N: usize>(population: &mut [([char; N], f64)]) {
+    population.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+}
+
+/// Two cut points `start < end` of a genome
+fn cut_points<const N: usize>(rng: &mut ChaCha12Rng) -> (usize, usize) {
+    let a = rng.gen_range(0..N);
+    let b = rng.gen_range(0..N);
+    (a.min(b), a.max(b) + 1)
+}
+
+fn position<const N: usize>(genome: &[char; N], c: char) -> usize {
+    genome
+        .iter()
+        .position(|key| *key == c)
+        .expect("parents should be permutations of the same keys")
+}
+
+/// Partially mapped crossover, keeps a segment of the first parent in place
+/// and as many keys of the second parent in place as possible
+pub(crate) fn pmx<const N: usize>(
+    parent_1: &[char; N],
+    parent_2: &[char; N],
+    rng: &mut ChaCha12Rng,
+) -> [char; N] {
+    let (start, end) = cut_points::<N>(rng);
+
+    let mut child = [None; N];
+    for i in start..end {
+        child[i] = Some(parent_1[i]);
+    }
+
+    for i in start..end {
+        let c = parent_2[i];
+        if parent_1[start..end].contains(&c) {
+            continue;
+        }
+
+        // follow the mapping of the segment until a free position outside of it
+        let mut position_2 = i;
+        while (start..end).contains(&position_2) {
+            position_2 = position(parent_2, parent_1[position_2]);
+        }
+        child[position_2] = Some(c);
+    }
+
+    for i in 0..N {
+        child[i].get_or_insert(parent_2[i]);
+    }
+
+    child.map(|c| c.expect("every position is filled"))
+}
+
+/// Order crossover, keeps a segment and the pinned keys of the first parent in place
+/// and fills the rest with the other keys in the order of the second parent
+pub(crate) fn order_crossover<const N: usize>(
+    parent_1: &[char; N],
+    parent_2: &[char; N],
+    constraints: &Constraints<N>,
+    rng: &mut ChaCha12Rng,
+) -> [char; N] {
+    let (start, end) = cut_points::<N>(rng);
+    let kept = |i: usize| (start..end).contains(&i) || constraints.is_pinned(i);
+
+    let mut child = *parent_1;
+    let kept_keys: Vec<char> = (0..N).filter(|i| kept(*i)).map(|i| parent_1[i]).collect();
+    let rest = (0..N)
+        .map(|i| parent_2[(end + i) % N])
+        .filter(|c| !kept_keys.contains(c));
+    let free = (0..N).map(|i| (end + i) % N).filter(|i| !kept(*i));
+
+    for (i, c) in free.zip(rest) {
+        child[i] = c;
+    }
+
+    child
+}
+
+/// Cycle crossover, every key stays at its position in one of the parents,
+/// the cycles are taken from the parents alternately starting with a random one
+pub(crate) fn cycle_crossover<const N: usize>(
+    parent_1: &[char; N],
+    parent_2: &[char; N],
+    rng: &mut ChaCha12Rng,
+) -> [char; N] {
+    let mut child = [None; N];
+    let mut from_first = rng.gen_bool(0.5);
+
+    for start in 0..N {
+        if child[start].is_some() {
+            continue;
+        }
+
+        let mut i = start;
+        loop {
+            child[i] = Some(if from_first { parent_1[i] } else { parent_2[i] });
+            i = position(parent_1, parent_2[i]);
+            if i == start {
+                break;
+            }
+        }
+
+        from_first = !from_first;
+    }
+
+    child.map(|c| c.expect("every position is filled"))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::models::genome::QWERTY_GENOME;
+
+    #[test]
+    fn test_crossover_gives_permutation() {
+        let mut rng = ChaCha12Rng::seed_from_u64(1);
+        let mut sorted_keys = QWERTY_GENOME;
+        sorted_keys.sort_unstable();
+
+        let constraints = Constraints::none(&QWERTY_GENOME)
+            .pin('Q', 0)
+            .and_then(|constraints| constraints.pin('Z', 45))
+            .expect("should pin");
+
+        for _ in 0..100 {
+            let parent_1 = create_genome(&constraints, &mut rng);
+            let parent_2 = shuffle_genome(&parent_1, 1500., &constraints, &mut rng);
+
+            let children = [
+                pmx(&parent_1, &parent_2, &mut rng),
+                order_crossover(&parent_1, &parent_2, &constraints, &mut rng),
+                cycle_crossover(&parent_1, &parent_2, &mut rng),
+            ];
+            for mut child in children {
+                assert!(constraints.allows(&child));
+                child.sort_unstable();
+                assert_eq!(child, sorted_keys);
+            }
+
+            // keys that are in the same place in both parents stay there
+            let pmx_child = pmx(&parent_1, &parent_2, &mut rng);
+            let cycle_child = cycle_crossover(&parent_1, &parent_2, &mut rng);
+            for i in 0..46 {
+                if parent_1[i] == parent_2[i] {
+                    assert_eq!(pmx_child[i], parent_1[i]);
+                    assert_eq!(cycle_child[i], parent_1[i]);
+                }
+                assert!(cycle_child[i] == parent_1[i] || cycle_child[i] == parent_2[i]);
+            }
+        }
+    }
+}
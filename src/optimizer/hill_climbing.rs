@@ -0,0 +1,28 @@
+use rand_chacha::ChaCha12Rng;
+
+use super::{Objective, Optimizer};
+
+/// Steepest descent, takes the best of all swaps until no swap improves the genome
+pub struct HillClimbing {
+    /// stop after this many swaps even if the genome could still improve
+    pub max_steps: usize,
+}
+
+impl<const N: usize> Optimizer<N> for HillClimbing {
+    fn name(&self) -> &'static str {
+        "hill climbing"
+    }
+
+    fn optimize(&self, objective: &mut Objective<N>, _rng: &mut ChaCha12Rng) -> [char; N] {
+        for _ in 0..self.max_steps {
+            let score = objective.score();
+
+            match objective.best_swap(|_, _| true) {
+                Some(((a, b), new_score)) if new_score < score => objective.swap(a, b),
+                _ => break,
+            }
+        }
+
+        *objective.genome()
+    }
+}
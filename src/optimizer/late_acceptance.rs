@@ -0,0 +1,43 @@
+use rand_chacha::ChaCha12Rng;
+
+use super::{Objective, Optimizer};
+
+/// Late acceptance hill climbing, a random swap is accepted if it is not worse than
+/// the current genome or the genome from `history_length` iterations ago
+pub struct LateAcceptance {
+    pub iterations: usize,
+    pub history_length: usize,
+}
+
+impl<const N: usize> Optimizer<N> for LateAcceptance {
+    fn name(&self) -> &'static str {
+        "late acceptance"
+    }
+
+    fn optimize(&self, objective: &mut Objective<N>, rng: &mut ChaCha12Rng) -> [char; N] {
+        let mut score = objective.score();
+        let mut best_genome = *objective.genome();
+        let mut best_score = score;
+
+        let mut history = vec![score; self.history_length.max(1)];
+
+        for iteration in 0..self.iterations {
+            let (a, b) = objective.random_swap(rng);
+            let new_score = objective.swap_score(a, b);
+
+            let slot = iteration % history.len();
+            if new_score <= history[slot] || new_score <= score {
+                objective.commit_swap();
+                score = new_score;
+
+                if score < best_score {
+                    best_genome = *objective.genome();
+                    best_score = score;
+                }
+            }
+            history[slot] = score;
+        }
+
+        best_genome
+    }
+}
@@ -0,0 +1,42 @@
+use rand_chacha::ChaCha12Rng;
+
+use super::{Objective, Optimizer};
+
+/// Tabu search, always takes the best swap that was not made in the last `tenure` iterations,
+/// a tabu swap is allowed if it gives a new best genome
+pub struct TabuSearch {
+    pub iterations: usize,
+    pub tenure: usize,
+}
+
+impl<const N: usize> Optimizer<N> for TabuSearch {
+    fn name(&self) -> &'static str {
+        "tabu search"
+    }
+
+    fn optimize(&self, objective: &mut Objective<N>, _rng: &mut ChaCha12Rng) -> [char; N] {
+        let mut best_genome = *objective.genome();
+        let mut best_score = objective.score();
+
+        // iteration until which a swap of two positions is tabu
+        let mut tabu_until = vec![[0; N]; N];
+
+        for iteration in 1..=self.iterations {
+            let Some(((a, b), score)) = objective
+                .best_swap(|(a, b), score| tabu_until[a][b] < iteration || score < best_score)
+            else {
+                break;
+            };
+
+            objective.swap(a, b);
+            tabu_until[a][b] = iteration + self.tenure;
+
+            if score < best_score {
+                best_genome = *objective.genome();
+                best_score = score;
+            }
+        }
+
+        best_genome
+    }
+}
@@ -1,5 +1,40 @@
 pub use crate::{
+    advanced_setup::EffortModel,
+    analysis::{analyze_genome, analyze_reference_genomes, Analysis, FingerStats, PenaltyTerms},
+    checkpoint::CheckpointError,
+    config_file::ConfigFileError,
+    draw_genome,
+    export::{
+        firmware::{export_qmk, export_zmk, Board},
+        keylayout::export_keylayout,
+        klc::export_klc,
+        xkb::{export_xkb, setxkbmap_snippet},
+        ExportError,
+    },
+    kle::{export_kle, import_kle, KleError, KleKey, KleKeyboard},
     logging::{LoggingOptions, SaveImageOption, Verbosity},
-    models::layout::{QwertyEnUs, QwertzDeDe},
-    run_sa, SaSetup,
+    metrics::Metrics,
+    models::{
+        constraints::{ConstraintError, Constraints, Rule, Side},
+        custom_layout::{CustomLayout, LayoutDefinitionError},
+        genome::{parse_genome, GetGenome},
+        geometry::{Geometry, GeometryError, GeometryLayout},
+        key_maps::GetKeyMap,
+        layout::{
+            BoneDeDe, ColemakDhEnUs, ColemakEnUs, DvorakEnUs, ILayout, KoyDeDe, Neo2DeDe,
+            QwertyEnUs, QwertzDeDe, WorkmanEnUs,
+        },
+        layout_map::GetLayoutMap,
+        letter_list::GetLetterList,
+    },
+    optimizer::{
+        annealing::SimulatedAnnealing,
+        genetic::{Crossover, GenerationStats, GeneticAlgorithm},
+        hill_climbing::HillClimbing,
+        late_acceptance::LateAcceptance,
+        tabu::TabuSearch,
+        Objective, Optimizer,
+    },
+    resume_sa, run_optimizer, run_sa, run_sa_parallel, score_genome, untyped_characters,
+    ChainResult, ParallelSaResult, SaSetup, Score,
 };